@@ -0,0 +1,206 @@
+//! The abstract syntax tree of a message in the protobuf text format, as returned by
+//! [`text_format::parse()`](crate::text_format::parse).
+
+use std::fmt;
+
+use super::{Float, FullIdent, Ident, Int, String};
+use crate::Span;
+
+/// A message in the text format, consisting of a sequence of fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Message {
+    /// The fields of the message, in the order they appear in the source.
+    pub fields: Vec<Field>,
+    /// The span of the message, including any surrounding `{}` or `<>` delimiters.
+    pub span: Span,
+}
+
+/// A field of a text format message, such as `foo: 1` or `bar { baz: true }`.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Field {
+    /// The name of the field.
+    pub name: FieldName,
+    /// The value of the field.
+    pub value: FieldValue,
+    /// The span of the field, including any trailing `,` or `;`.
+    pub span: Span,
+}
+
+/// The name of a field in a text format message.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum FieldName {
+    /// The name of a regular field, such as `foo`.
+    Ident(Ident),
+    /// The name of an extension field, such as `[foo.bar]`.
+    Extension(FullIdent, Span),
+    /// The type URL of an expanded `google.protobuf.Any` value, such as `[type.googleapis.com/foo.Bar]`.
+    Any {
+        /// The URL prefix, such as `type.googleapis.com`.
+        domain: std::string::String,
+        /// The full name of the message type.
+        type_name: FullIdent,
+        /// The span of the name, including brackets.
+        span: Span,
+    },
+}
+
+/// The value of a field in a text format message.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum FieldValue {
+    /// A single scalar value, such as `foo: 1`.
+    Scalar(Scalar),
+    /// A list of scalar values, such as `foo: [1, 2]`. An empty list `[]` is always parsed as a scalar list.
+    ScalarList(Vec<Scalar>, Span),
+    /// A single message value, such as `foo { bar: 1 }`.
+    Message(Message),
+    /// A list of message values, such as `foo: [{ bar: 1 }, { bar: 2 }]`.
+    MessageList(Vec<Message>, Span),
+}
+
+/// A scalar value in a text format message.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Scalar {
+    /// An identifier, such as an enum value name, `true` or `inf`.
+    Ident {
+        /// Whether the identifier is preceded by `-`.
+        negative: bool,
+        /// The identifier.
+        ident: Ident,
+        /// The span of the value, including any sign.
+        span: Span,
+    },
+    /// An integer literal.
+    Int(Int),
+    /// A floating-point literal.
+    Float(Float),
+    /// A string literal. Adjacent string literals are concatenated.
+    String(String),
+}
+
+impl FieldName {
+    /// Gets the span of the field name.
+    pub fn span(&self) -> Span {
+        match self {
+            FieldName::Ident(ident) => ident.span.clone(),
+            FieldName::Extension(_, span) | FieldName::Any { span, .. } => span.clone(),
+        }
+    }
+}
+
+impl FieldValue {
+    /// Gets the span of the field value.
+    pub fn span(&self) -> Span {
+        match self {
+            FieldValue::Scalar(scalar) => scalar.span(),
+            FieldValue::Message(message) => message.span.clone(),
+            FieldValue::ScalarList(_, span) | FieldValue::MessageList(_, span) => span.clone(),
+        }
+    }
+}
+
+impl Scalar {
+    /// Gets the span of the value.
+    pub fn span(&self) -> Span {
+        match self {
+            Scalar::Ident { span, .. } => span.clone(),
+            Scalar::Int(int) => int.span.clone(),
+            Scalar::Float(float) => float.span.clone(),
+            Scalar::String(string) => string.span.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, field) in self.fields.iter().enumerate() {
+            if index != 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            FieldValue::Message(message) => write!(f, "{} {}", self.name, MessageValue(message)),
+            value => write!(f, "{}: {}", self.name, value),
+        }
+    }
+}
+
+impl fmt::Display for FieldName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldName::Ident(ident) => write!(f, "{}", ident),
+            FieldName::Extension(name, _) => write!(f, "[{}]", name),
+            FieldName::Any {
+                domain, type_name, ..
+            } => write!(f, "[{}/{}]", domain, type_name),
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Scalar(scalar) => write!(f, "{}", scalar),
+            FieldValue::Message(message) => write!(f, "{}", MessageValue(message)),
+            FieldValue::ScalarList(values, _) => fmt_list(f, values.iter()),
+            FieldValue::MessageList(values, _) => fmt_list(f, values.iter().map(MessageValue)),
+        }
+    }
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Ident {
+                negative, ident, ..
+            } => {
+                if *negative {
+                    write!(f, "-{}", ident)
+                } else {
+                    write!(f, "{}", ident)
+                }
+            }
+            Scalar::Int(int) => int.fmt(f),
+            Scalar::Float(float) => float.fmt(f),
+            Scalar::String(string) => write!(f, "\"{}\"", string),
+        }
+    }
+}
+
+/// Formats a message with surrounding braces.
+struct MessageValue<'a>(&'a Message);
+
+impl<'a> fmt::Display for MessageValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.fields.is_empty() {
+            write!(f, "{{}}")
+        } else {
+            write!(f, "{{ {} }}", self.0)
+        }
+    }
+}
+
+fn fmt_list<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    values: impl Iterator<Item = T>,
+) -> fmt::Result {
+    write!(f, "[")?;
+    for (index, value) in values.enumerate() {
+        if index != 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", value)?;
+    }
+    write!(f, "]")
+}
@@ -0,0 +1,257 @@
+use super::{
+    text_format, Enum, EnumValue, Extend, Field, FieldKind, File, FileItem, Message, MessageBody,
+    MessageItem, Method, Oneof, OptionBody, OptionList, OptionValue, Service,
+};
+
+/// A visitor over the syntax tree of a file, as returned by [`parse_ast()`](crate::parse_ast).
+///
+/// Each method is called when the corresponding node is reached, and by default visits the children of the node
+/// using the `walk_*` function of the same name. An implementation which overrides a method can call the `walk_*`
+/// function itself to continue visiting the children of the node.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{ast::{self, Visitor}, parse_ast};
+/// struct FieldNames(Vec<String>);
+///
+/// impl Visitor for FieldNames {
+///     fn visit_text_format_field(&mut self, field: &ast::text_format::Field) {
+///         self.0.push(field.name.to_string());
+///         ast::walk_text_format_field(self, field);
+///     }
+/// }
+///
+/// let file = parse_ast("foo.proto", "option (my_option) = { foo: 1 bar { baz: 2 } };").unwrap();
+/// let mut visitor = FieldNames(Vec::new());
+/// visitor.visit_file(&file);
+/// assert_eq!(visitor.0, ["foo", "bar", "baz"]);
+/// ```
+pub trait Visitor {
+    /// Visits a file.
+    fn visit_file(&mut self, file: &File) {
+        walk_file(self, file)
+    }
+
+    /// Visits a message definition.
+    fn visit_message(&mut self, message: &Message) {
+        walk_message(self, message)
+    }
+
+    /// Visits a field definition, in a message, oneof or extend block.
+    fn visit_field(&mut self, field: &Field) {
+        walk_field(self, field)
+    }
+
+    /// Visits a oneof definition.
+    fn visit_oneof(&mut self, oneof: &Oneof) {
+        walk_oneof(self, oneof)
+    }
+
+    /// Visits an enum definition.
+    fn visit_enum(&mut self, enum_: &Enum) {
+        walk_enum(self, enum_)
+    }
+
+    /// Visits an enum value.
+    fn visit_enum_value(&mut self, value: &EnumValue) {
+        walk_enum_value(self, value)
+    }
+
+    /// Visits an extend block.
+    fn visit_extend(&mut self, extend: &Extend) {
+        walk_extend(self, extend)
+    }
+
+    /// Visits a service definition.
+    fn visit_service(&mut self, service: &Service) {
+        walk_service(self, service)
+    }
+
+    /// Visits a method of a service.
+    fn visit_method(&mut self, method: &Method) {
+        walk_method(self, method)
+    }
+
+    /// Visits the name and value of an option, from either an `option` statement or an option list.
+    fn visit_option(&mut self, option: &OptionBody) {
+        walk_option(self, option)
+    }
+
+    /// Visits the value of an option.
+    fn visit_option_value(&mut self, value: &OptionValue) {
+        walk_option_value(self, value)
+    }
+
+    /// Visits a message in the text format, such as the value of an aggregate option.
+    fn visit_text_format_message(&mut self, message: &text_format::Message) {
+        walk_text_format_message(self, message)
+    }
+
+    /// Visits a field of a text format message.
+    fn visit_text_format_field(&mut self, field: &text_format::Field) {
+        walk_text_format_field(self, field)
+    }
+
+    /// Visits the value of a field of a text format message.
+    fn visit_text_format_value(&mut self, value: &text_format::FieldValue) {
+        walk_text_format_value(self, value)
+    }
+}
+
+/// Visits the options and definitions of a file.
+pub fn walk_file<V: Visitor + ?Sized>(visitor: &mut V, file: &File) {
+    for option in &file.options {
+        visitor.visit_option(&option.body);
+    }
+    for item in &file.items {
+        match item {
+            FileItem::Enum(enum_) => visitor.visit_enum(enum_),
+            FileItem::Message(message) => visitor.visit_message(message),
+            FileItem::Extend(extend) => visitor.visit_extend(extend),
+            FileItem::Service(service) => visitor.visit_service(service),
+        }
+    }
+}
+
+/// Visits the options, fields and nested definitions of a message.
+pub fn walk_message<V: Visitor + ?Sized>(visitor: &mut V, message: &Message) {
+    walk_message_body(visitor, &message.body)
+}
+
+/// Visits the options of a field, and the contents of a group.
+pub fn walk_field<V: Visitor + ?Sized>(visitor: &mut V, field: &Field) {
+    walk_option_list(visitor, field.options.as_ref());
+    if let FieldKind::Group { body, .. } = &field.kind {
+        walk_message_body(visitor, body);
+    }
+}
+
+/// Visits the options and fields of a oneof.
+pub fn walk_oneof<V: Visitor + ?Sized>(visitor: &mut V, oneof: &Oneof) {
+    for option in &oneof.options {
+        visitor.visit_option(&option.body);
+    }
+    for field in &oneof.fields {
+        visitor.visit_field(field);
+    }
+}
+
+/// Visits the options and values of an enum.
+pub fn walk_enum<V: Visitor + ?Sized>(visitor: &mut V, enum_: &Enum) {
+    for option in &enum_.options {
+        visitor.visit_option(&option.body);
+    }
+    for value in &enum_.values {
+        visitor.visit_enum_value(value);
+    }
+}
+
+/// Visits the options of an enum value.
+pub fn walk_enum_value<V: Visitor + ?Sized>(visitor: &mut V, value: &EnumValue) {
+    walk_option_list(visitor, value.options.as_ref());
+}
+
+/// Visits the fields of an extend block.
+pub fn walk_extend<V: Visitor + ?Sized>(visitor: &mut V, extend: &Extend) {
+    for field in &extend.fields {
+        visitor.visit_field(field);
+    }
+}
+
+/// Visits the options and methods of a service.
+pub fn walk_service<V: Visitor + ?Sized>(visitor: &mut V, service: &Service) {
+    for option in &service.options {
+        visitor.visit_option(&option.body);
+    }
+    for method in &service.methods {
+        visitor.visit_method(method);
+    }
+}
+
+/// Visits the options of a method.
+pub fn walk_method<V: Visitor + ?Sized>(visitor: &mut V, method: &Method) {
+    for option in &method.options {
+        visitor.visit_option(&option.body);
+    }
+}
+
+/// Visits the value of an option.
+pub fn walk_option<V: Visitor + ?Sized>(visitor: &mut V, option: &OptionBody) {
+    visitor.visit_option_value(&option.value);
+}
+
+/// Visits the elements of a list value, or the message of an aggregate value.
+///
+/// Aggregate values are stored as text, so they are parsed again before being visited. The spans of the visited text
+/// format nodes are relative to the text of the value, rather than the source file.
+pub fn walk_option_value<V: Visitor + ?Sized>(visitor: &mut V, value: &OptionValue) {
+    match value {
+        OptionValue::Aggregate(text, _) => {
+            if let Ok(message) = crate::text_format::parse(text) {
+                visitor.visit_text_format_message(message.as_ast());
+            }
+        }
+        OptionValue::List(values, _) => {
+            for value in values {
+                visitor.visit_option_value(value);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Visits the fields of a text format message.
+pub fn walk_text_format_message<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    message: &text_format::Message,
+) {
+    for field in &message.fields {
+        visitor.visit_text_format_field(field);
+    }
+}
+
+/// Visits the value of a text format field.
+pub fn walk_text_format_field<V: Visitor + ?Sized>(visitor: &mut V, field: &text_format::Field) {
+    visitor.visit_text_format_value(&field.value);
+}
+
+/// Visits the messages contained in a text format field value.
+pub fn walk_text_format_value<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    value: &text_format::FieldValue,
+) {
+    match value {
+        text_format::FieldValue::Message(message) => visitor.visit_text_format_message(message),
+        text_format::FieldValue::MessageList(messages, _) => {
+            for message in messages {
+                visitor.visit_text_format_message(message);
+            }
+        }
+        text_format::FieldValue::Scalar(_) | text_format::FieldValue::ScalarList(..) => (),
+    }
+}
+
+fn walk_message_body<V: Visitor + ?Sized>(visitor: &mut V, body: &MessageBody) {
+    for option in &body.options {
+        visitor.visit_option(&option.body);
+    }
+    for item in &body.items {
+        match item {
+            MessageItem::Field(field) => visitor.visit_field(field),
+            MessageItem::Enum(enum_) => visitor.visit_enum(enum_),
+            MessageItem::Message(message) => visitor.visit_message(message),
+            MessageItem::Extend(extend) => visitor.visit_extend(extend),
+            MessageItem::Oneof(oneof) => visitor.visit_oneof(oneof),
+        }
+    }
+    for extensions in &body.extensions {
+        walk_option_list(visitor, extensions.options.as_ref());
+    }
+}
+
+fn walk_option_list<V: Visitor + ?Sized>(visitor: &mut V, options: Option<&OptionList>) {
+    for option in options.into_iter().flat_map(|list| &list.options) {
+        visitor.visit_option(option);
+    }
+}
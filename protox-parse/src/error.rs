@@ -4,7 +4,7 @@ use logos::Span;
 use miette::{Diagnostic, NamedSource, SourceCode};
 use thiserror::Error;
 
-use crate::MAX_MESSAGE_FIELD_NUMBER;
+use crate::{generate::LineResolver, lex::Token, recover::Repair, MAX_MESSAGE_FIELD_NUMBER};
 
 /// An error that may occur while parsing a protobuf source file.
 #[derive(Error, Diagnostic)]
@@ -16,6 +16,43 @@ pub struct ParseError {
     related: Vec<ParseErrorKind>,
     #[source_code]
     source_code: NamedSource<String>,
+    unrepaired: bool,
+}
+
+/// A non-fatal issue found while parsing a protobuf source file.
+///
+/// Warnings are returned by [`parse_with_warnings()`](crate::parse_with_warnings).
+#[derive(Clone, Error, Diagnostic)]
+#[error("{}", kind)]
+#[diagnostic(forward(kind))]
+pub struct Warning {
+    kind: WarningKind,
+    #[source_code]
+    source_code: NamedSource<String>,
+}
+
+#[derive(Clone, Error, Debug, Diagnostic, PartialEq)]
+pub(crate) enum WarningKind {
+    #[error("default values are ignored in proto3")]
+    #[diagnostic(severity(Warning))]
+    Proto3DefaultValue {
+        #[label("defined here")]
+        span: Span,
+    },
+    #[error("list option values are not supported by protoc")]
+    #[diagnostic(severity(Warning))]
+    ListOptionValue {
+        #[label("defined here")]
+        span: Span,
+    },
+    #[error(
+        "a method type named 'stream' is not supported by protoc, which parses it as a keyword"
+    )]
+    #[diagnostic(severity(Warning))]
+    StreamMethodType {
+        #[label("defined here")]
+        span: Span,
+    },
 }
 
 #[derive(Error, Debug, Diagnostic, PartialEq)]
@@ -108,13 +145,13 @@ pub(crate) enum ParseErrorKind {
     },
     #[error("expected {expected}, but found '{found}'")]
     UnexpectedToken {
-        expected: String,
+        expected: Expected,
         found: String,
         #[label("found here")]
         span: Span,
     },
     #[error("expected {expected}, but reached end of file")]
-    UnexpectedEof { expected: String },
+    UnexpectedEof { expected: Expected },
     #[error("identifiers may not be negative")]
     NegativeIdentOutsideDefault {
         #[label("found here")]
@@ -162,6 +199,12 @@ pub(crate) enum ParseErrorKind {
         #[label("defined here")]
         span: Span,
     },
+    #[error("repeated field '{field_name}' is not allowed in a oneof")]
+    RepeatedFieldInOneof {
+        field_name: String,
+        #[label("defined here")]
+        span: Span,
+    },
     #[error("fields must have a label with proto2 syntax (expected one of 'optional', 'repeated' or 'required')")]
     Proto2FieldMissingLabel {
         #[label("field defined here")]
@@ -172,6 +215,11 @@ pub(crate) enum ParseErrorKind {
         #[label("defined here")]
         span: Span,
     },
+    #[error("groups are not allowed in extensions in proto3 syntax")]
+    Proto3ExtendGroupField {
+        #[label("group defined here")]
+        span: Span,
+    },
     #[error("required fields are not allowed in proto3 syntax")]
     Proto3RequiredField {
         #[label("defined here")]
@@ -210,9 +258,117 @@ pub(crate) enum ParseErrorKind {
         #[label("defined here")]
         span: Span,
     },
+    #[error("option values cannot be lists")]
+    ListOptionValue {
+        #[label("defined here")]
+        span: Span,
+    },
+    #[error("a list option value must have at least one element")]
+    EmptyOptionList {
+        #[label("defined here")]
+        span: Span,
+    },
     #[error("file is too large")]
     #[diagnostic(help("the maximum file length is 2,147,483,647 bytes"))]
     FileTooLarge,
+    #[error("{original}")]
+    #[diagnostic(forward(original))]
+    Repaired { original: Box<ParseErrorKind> },
+}
+
+/// The tokens which the parser expected to find when it reported a syntax error, in the order they are described.
+#[derive(Clone, PartialEq)]
+pub(crate) struct Expected(pub(crate) Vec<ExpectedToken>);
+
+/// A token, or kind of token, which the parser expected to find.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExpectedToken {
+    /// A specific token, such as `';'`.
+    Token(Token<'static>),
+    /// Any identifier.
+    Ident,
+    /// The end of a block comment.
+    CommentTerminator,
+    /// A construct made of several tokens, such as "a message field".
+    Description(&'static str),
+}
+
+impl ExpectedToken {
+    pub(crate) const COMMA: Self = ExpectedToken::Token(Token::Comma);
+    pub(crate) const DOT: Self = ExpectedToken::Token(Token::Dot);
+    pub(crate) const EQUALS: Self = ExpectedToken::Token(Token::Equals);
+    pub(crate) const COLON: Self = ExpectedToken::Token(Token::Colon);
+    pub(crate) const SEMICOLON: Self = ExpectedToken::Token(Token::Semicolon);
+    pub(crate) const LEFT_PAREN: Self = ExpectedToken::Token(Token::LeftParen);
+    pub(crate) const RIGHT_PAREN: Self = ExpectedToken::Token(Token::RightParen);
+    pub(crate) const LEFT_BRACE: Self = ExpectedToken::Token(Token::LeftBrace);
+    pub(crate) const RIGHT_BRACE: Self = ExpectedToken::Token(Token::RightBrace);
+    pub(crate) const LEFT_BRACKET: Self = ExpectedToken::Token(Token::LeftBracket);
+    pub(crate) const RIGHT_BRACKET: Self = ExpectedToken::Token(Token::RightBracket);
+    pub(crate) const LEFT_ANGLE_BRACKET: Self = ExpectedToken::Token(Token::LeftAngleBracket);
+    pub(crate) const RIGHT_ANGLE_BRACKET: Self = ExpectedToken::Token(Token::RightAngleBracket);
+}
+
+impl Expected {
+    pub(crate) fn first(&self) -> Option<&ExpectedToken> {
+        self.0.first()
+    }
+
+    pub(crate) fn last(&self) -> Option<&ExpectedToken> {
+        self.0.last()
+    }
+}
+
+impl From<ExpectedToken> for Expected {
+    fn from(token: ExpectedToken) -> Self {
+        Expected(vec![token])
+    }
+}
+
+impl<const N: usize> From<[ExpectedToken; N]> for Expected {
+    fn from(tokens: [ExpectedToken; N]) -> Self {
+        Expected(tokens.into())
+    }
+}
+
+impl From<Vec<ExpectedToken>> for Expected {
+    fn from(tokens: Vec<ExpectedToken>) -> Self {
+        Expected(tokens)
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, token) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(if index == self.0.len() - 1 {
+                    " or "
+                } else {
+                    ", "
+                })?;
+            }
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
+// Formatted as the message text, so that errors are easy to read in test snapshots.
+impl fmt::Debug for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+impl fmt::Display for ExpectedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpectedToken::Token(token) => write!(f, "'{}'", token),
+            ExpectedToken::Ident => f.write_str("an identifier"),
+            ExpectedToken::CommentTerminator => f.write_str("comment terminator"),
+            ExpectedToken::Description(description) => f.write_str(description),
+        }
+    }
 }
 
 impl ParseError {
@@ -223,6 +379,7 @@ impl ParseError {
             kind: Box::new(kind),
             related,
             source_code: NamedSource::new(name, source),
+            unrepaired: false,
         }
     }
 
@@ -232,6 +389,16 @@ impl ParseError {
         self.related
     }
 
+    /// Replaces the source code attached to this error.
+    ///
+    /// The name of `source_code` is returned by [`file()`](ParseError::file), and its text is used when rendering
+    /// the spans of the error. The spans are not adjusted, so `source_code` should contain the same text that was
+    /// parsed.
+    pub fn with_source_code(mut self, source_code: NamedSource<String>) -> Self {
+        self.source_code = source_code;
+        self
+    }
+
     /// Gets the name of the file in which this error occurred.
     pub fn file(&self) -> &str {
         self.source_code.name()
@@ -239,63 +406,203 @@ impl ParseError {
 
     /// Gets the primary source code span associated with this error, if any.
     pub fn span(&self) -> Option<Range<usize>> {
-        match &*self.kind {
-            ParseErrorKind::InvalidToken { span } => Some(span.clone()),
-            ParseErrorKind::IntegerOutOfRange { span } => Some(span.clone()),
-            ParseErrorKind::InvalidStringCharacters { span } => Some(span.clone()),
-            ParseErrorKind::UnterminatedString { span } => Some(span.clone()),
-            ParseErrorKind::InvalidStringEscape { span } => Some(span.clone()),
-            ParseErrorKind::InvalidUtf8String { span } => Some(span.clone()),
-            ParseErrorKind::NestedBlockComment { span } => Some(span.clone()),
-            ParseErrorKind::UnknownSyntax { span, .. } => Some(span.clone()),
-            ParseErrorKind::InvalidIdentifier { span } => Some(span.clone()),
-            ParseErrorKind::InvalidGroupName { span } => Some(span.clone()),
-            ParseErrorKind::InvalidImport { span } => Some(span.clone()),
-            ParseErrorKind::DuplicatePackage { .. } => None,
-            ParseErrorKind::NoSpaceBetweenIntAndIdent { span } => Some(span.clone()),
-            ParseErrorKind::HashCommentOutsideTextFormat { span } => Some(span.clone()),
-            ParseErrorKind::FloatSuffixOutsideTextFormat { span } => Some(span.clone()),
-            ParseErrorKind::UnexpectedToken { span, .. } => Some(span.clone()),
+        self.primary_span().cloned()
+    }
+
+    /// Gets a reference to the primary source code span associated with this error, if any.
+    ///
+    /// For errors with several labels, such as a duplicate `package` statement, this is the span of the first
+    /// definition. Errors at the end of the file have no span.
+    pub fn primary_span(&self) -> Option<&Range<usize>> {
+        self.kind.primary_span()
+    }
+
+    /// Returns `true` if this error is a syntax mistake which [`parse_with_recovery()`](crate::parse_with_recovery)
+    /// is able to repair.
+    ///
+    /// Errors returned by [`parse_with_recovery()`](crate::parse_with_recovery) which were repaired also return
+    /// `true`.
+    ///
+    /// The final error returned by [`parse_with_recovery()`](crate::parse_with_recovery), which it failed to repair,
+    /// always returns `false`.
+    pub fn is_recoverable(&self) -> bool {
+        !self.unrepaired
+            && (matches!(*self.kind, ParseErrorKind::Repaired { .. })
+                || Repair::for_error(&self.kind).is_some())
+    }
+
+    pub(crate) fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Marks this error as repaired, attaching the original source code.
+    pub(crate) fn into_repaired(self, source: &str) -> Self {
+        ParseError {
+            source_code: NamedSource::new(self.file(), source.to_owned()),
+            kind: Box::new(ParseErrorKind::Repaired {
+                original: self.kind,
+            }),
+            related: Vec::new(),
+            unrepaired: false,
+        }
+    }
+
+    /// Marks this error as one which could not be repaired.
+    pub(crate) fn into_unrepaired(self) -> Self {
+        ParseError {
+            unrepaired: true,
+            ..self
+        }
+    }
+
+    /// Gets the line and column of the start of the primary span of this error, if any.
+    ///
+    /// Lines and columns are 1-indexed, with columns counted in bytes from the start of the line. The `source` should
+    /// contain the same text that was parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let source = "message Foo {\n    int32 1 = 1;\n}";
+    /// let err = protox_parse::parse("foo.proto", source).unwrap_err();
+    /// assert_eq!(err.line_col(source), Some((2, 11)));
+    /// assert_eq!(err.end_line_col(source), Some((2, 12)));
+    /// ```
+    pub fn line_col(&self, source: &str) -> Option<(u32, u32)> {
+        let span = self.span()?;
+        Some(resolve_line_col(source, span.start))
+    }
+
+    /// Gets the line and column of the end of the primary span of this error, if any.
+    ///
+    /// See [`line_col()`](ParseError::line_col) for details.
+    pub fn end_line_col(&self, source: &str) -> Option<(u32, u32)> {
+        let span = self.span()?;
+        Some(resolve_line_col(source, span.end))
+    }
+}
+
+impl ParseErrorKind {
+    fn primary_span(&self) -> Option<&Span> {
+        match self {
+            ParseErrorKind::InvalidToken { span } => Some(span),
+            ParseErrorKind::IntegerOutOfRange { span } => Some(span),
+            ParseErrorKind::InvalidStringCharacters { span } => Some(span),
+            ParseErrorKind::UnterminatedString { span } => Some(span),
+            ParseErrorKind::InvalidStringEscape { span } => Some(span),
+            ParseErrorKind::InvalidUtf8String { span } => Some(span),
+            ParseErrorKind::NestedBlockComment { span } => Some(span),
+            ParseErrorKind::UnknownSyntax { span, .. } => Some(span),
+            ParseErrorKind::InvalidIdentifier { span } => Some(span),
+            ParseErrorKind::InvalidGroupName { span } => Some(span),
+            ParseErrorKind::InvalidImport { span } => Some(span),
+            ParseErrorKind::DuplicatePackage { first, .. } => Some(first),
+            ParseErrorKind::NoSpaceBetweenIntAndIdent { span } => Some(span),
+            ParseErrorKind::HashCommentOutsideTextFormat { span } => Some(span),
+            ParseErrorKind::FloatSuffixOutsideTextFormat { span } => Some(span),
+            ParseErrorKind::UnexpectedToken { span, .. } => Some(span),
             ParseErrorKind::UnexpectedEof { .. } => None,
-            ParseErrorKind::NegativeIdentOutsideDefault { span } => Some(span.clone()),
-            ParseErrorKind::InvalidMessageNumber { span } => Some(span.clone()),
-            ParseErrorKind::InvalidEnumNumber { span } => Some(span.clone()),
-            ParseErrorKind::InvalidDefault { span, .. } => Some(span.clone()),
-            ParseErrorKind::Proto3DefaultValue { span } => Some(span.clone()),
-            ParseErrorKind::InvalidExtendFieldKind { span, .. } => Some(span.clone()),
-            ParseErrorKind::RequiredExtendField { span } => Some(span.clone()),
-            ParseErrorKind::MapFieldWithLabel { span } => Some(span.clone()),
-            ParseErrorKind::OneofFieldWithLabel { span } => Some(span.clone()),
-            ParseErrorKind::Proto2FieldMissingLabel { span } => Some(span.clone()),
-            ParseErrorKind::Proto3GroupField { span } => Some(span.clone()),
-            ParseErrorKind::Proto3RequiredField { span } => Some(span.clone()),
-            ParseErrorKind::InvalidOneofFieldKind { span, .. } => Some(span.clone()),
-            ParseErrorKind::InvalidMapFieldKeyType { span } => Some(span.clone()),
-            ParseErrorKind::ValueInvalidType { span, .. } => Some(span.clone()),
-            ParseErrorKind::IntegerValueOutOfRange { span, .. } => Some(span.clone()),
-            ParseErrorKind::EmptyOneof { span } => Some(span.clone()),
+            ParseErrorKind::NegativeIdentOutsideDefault { span } => Some(span),
+            ParseErrorKind::InvalidMessageNumber { span } => Some(span),
+            ParseErrorKind::InvalidEnumNumber { span } => Some(span),
+            ParseErrorKind::InvalidDefault { span, .. } => Some(span),
+            ParseErrorKind::Proto3DefaultValue { span } => Some(span),
+            ParseErrorKind::InvalidExtendFieldKind { span, .. } => Some(span),
+            ParseErrorKind::RequiredExtendField { span } => Some(span),
+            ParseErrorKind::MapFieldWithLabel { span } => Some(span),
+            ParseErrorKind::OneofFieldWithLabel { span } => Some(span),
+            ParseErrorKind::RepeatedFieldInOneof { span, .. } => Some(span),
+            ParseErrorKind::Proto2FieldMissingLabel { span } => Some(span),
+            ParseErrorKind::Proto3GroupField { span } => Some(span),
+            ParseErrorKind::Proto3ExtendGroupField { span } => Some(span),
+            ParseErrorKind::Proto3RequiredField { span } => Some(span),
+            ParseErrorKind::InvalidOneofFieldKind { span, .. } => Some(span),
+            ParseErrorKind::InvalidMapFieldKeyType { span } => Some(span),
+            ParseErrorKind::ValueInvalidType { span, .. } => Some(span),
+            ParseErrorKind::IntegerValueOutOfRange { span, .. } => Some(span),
+            ParseErrorKind::EmptyOneof { span } => Some(span),
+            ParseErrorKind::ListOptionValue { span } => Some(span),
+            ParseErrorKind::EmptyOptionList { span } => Some(span),
             ParseErrorKind::FileTooLarge => None,
+            ParseErrorKind::Repaired { original } => original.primary_span(),
         }
     }
 }
 
+fn resolve_line_col(source: &str, offset: usize) -> (u32, u32) {
+    let (line, col) = LineResolver::new(source).resolve(offset);
+    (line as u32 + 1, col as u32 + 1)
+}
+
 impl fmt::Debug for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(span) = self.span() {
-            if let Ok(span_contents) = self.source_code.read_span(&span.into(), 0, 0) {
-                if let Some(file_name) = span_contents.name() {
-                    write!(f, "{}:", file_name)?;
-                }
-
-                write!(
-                    f,
-                    "{}:{}: ",
-                    span_contents.line() + 1,
-                    span_contents.column() + 1
-                )?;
-            }
+        fmt_location(f, &self.source_code, self.span())?;
+        write!(f, "{}", self)
+    }
+}
+
+impl Warning {
+    pub(crate) fn new(kind: WarningKind, name: &str, source: String) -> Self {
+        Warning {
+            kind,
+            source_code: NamedSource::new(name, source),
         }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn into_inner(self) -> WarningKind {
+        self.kind
+    }
+
+    /// Gets the name of the file in which this warning occurred.
+    pub fn file(&self) -> &str {
+        self.source_code.name()
+    }
 
+    /// Gets the source code span associated with this warning.
+    pub fn span(&self) -> Range<usize> {
+        match &self.kind {
+            WarningKind::Proto3DefaultValue { span } => span.clone(),
+            WarningKind::ListOptionValue { span } => span.clone(),
+            WarningKind::StreamMethodType { span } => span.clone(),
+        }
+    }
+
+    /// Returns `true` if this warning is for an option with a list value, such as `option (foo) = [1, 2];`.
+    ///
+    /// These are only allowed if [`ParseOptions::allow_list_option_values`](crate::ParseOptions::allow_list_option_values)
+    /// is set.
+    pub fn is_list_option_value(&self) -> bool {
+        matches!(self.kind, WarningKind::ListOptionValue { .. })
+    }
+}
+
+impl fmt::Debug for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_location(f, &self.source_code, Some(self.span()))?;
         write!(f, "{}", self)
     }
 }
+
+fn fmt_location(
+    f: &mut fmt::Formatter<'_>,
+    source_code: &NamedSource<String>,
+    span: Option<Range<usize>>,
+) -> fmt::Result {
+    if let Some(span) = span {
+        if let Ok(span_contents) = source_code.read_span(&span.into(), 0, 0) {
+            if let Some(file_name) = span_contents.name() {
+                write!(f, "{}:", file_name)?;
+            }
+
+            write!(
+                f,
+                "{}:{}: ",
+                span_contents.line() + 1,
+                span_contents.column() + 1
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use prost::Message;
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorProto, UninterpretedOption};
+use sha2::{Digest, Sha256};
+
+/// Removes the parts of `file` which do not affect its meaning, so that equivalent files are encoded identically.
+///
+/// Source code info is cleared, and uninterpreted options are sorted by name. The sort is stable, so the order of
+/// options with the same name, which may set the elements of a repeated field, is kept.
+pub(crate) fn canonicalize(file: &mut FileDescriptorProto) {
+    file.source_code_info = None;
+
+    if let Some(options) = &mut file.options {
+        sort_options(&mut options.uninterpreted_option);
+    }
+    for message in &mut file.message_type {
+        canonicalize_message(message);
+    }
+    for enum_ in &mut file.enum_type {
+        canonicalize_enum(enum_);
+    }
+    for service in &mut file.service {
+        if let Some(options) = &mut service.options {
+            sort_options(&mut options.uninterpreted_option);
+        }
+        for method in &mut service.method {
+            if let Some(options) = &mut method.options {
+                sort_options(&mut options.uninterpreted_option);
+            }
+        }
+    }
+    for extension in &mut file.extension {
+        if let Some(options) = &mut extension.options {
+            sort_options(&mut options.uninterpreted_option);
+        }
+    }
+}
+
+fn canonicalize_message(message: &mut DescriptorProto) {
+    if let Some(options) = &mut message.options {
+        sort_options(&mut options.uninterpreted_option);
+    }
+    for field in message.field.iter_mut().chain(&mut message.extension) {
+        if let Some(options) = &mut field.options {
+            sort_options(&mut options.uninterpreted_option);
+        }
+    }
+    for oneof in &mut message.oneof_decl {
+        if let Some(options) = &mut oneof.options {
+            sort_options(&mut options.uninterpreted_option);
+        }
+    }
+    for range in &mut message.extension_range {
+        if let Some(options) = &mut range.options {
+            sort_options(&mut options.uninterpreted_option);
+        }
+    }
+    for nested in &mut message.nested_type {
+        canonicalize_message(nested);
+    }
+    for enum_ in &mut message.enum_type {
+        canonicalize_enum(enum_);
+    }
+}
+
+fn canonicalize_enum(enum_: &mut EnumDescriptorProto) {
+    if let Some(options) = &mut enum_.options {
+        sort_options(&mut options.uninterpreted_option);
+    }
+    for value in &mut enum_.value {
+        if let Some(options) = &mut value.options {
+            sort_options(&mut options.uninterpreted_option);
+        }
+    }
+}
+
+fn sort_options(options: &mut [UninterpretedOption]) {
+    options.sort_by_cached_key(|option| {
+        option
+            .name
+            .iter()
+            .map(|part| (part.name_part.clone(), part.is_extension))
+            .collect::<Vec<_>>()
+    });
+}
+
+/// Computes the fingerprint of a canonicalized file.
+pub(crate) fn hash(file: &FileDescriptorProto) -> [u8; 32] {
+    Sha256::digest(file.encode_to_vec()).into()
+}
@@ -8,9 +8,12 @@ use prost_types::{
     SourceCodeInfo, UninterpretedOption,
 };
 
-use self::lines::LineResolver;
+pub(crate) use self::lines::LineResolver;
 use crate::{
-    ast, case::to_pascal_case, error::ParseErrorKind, index_to_i32, tag, MAX_MESSAGE_FIELD_NUMBER,
+    ast,
+    case::to_pascal_case,
+    error::{ParseErrorKind, WarningKind},
+    index_to_i32, tag, ParseOptions, MAX_MESSAGE_FIELD_NUMBER,
 };
 
 mod lines;
@@ -20,10 +23,14 @@ pub(crate) fn generate_file(
     ast: ast::File,
     name: &str,
     source: &str,
-) -> Result<FileDescriptorProto, Vec<ParseErrorKind>> {
+    options: &ParseOptions,
+) -> Result<(FileDescriptorProto, Vec<WarningKind>), Vec<ParseErrorKind>> {
     let mut ctx = Context {
         syntax: ast.syntax,
+        allow_proto3_default_values: options.allow_proto3_default_values,
+        allow_list_option_values: options.allow_list_option_values,
         errors: vec![],
+        warnings: vec![],
         path: vec![],
         locations: vec![],
         lines: LineResolver::new(source),
@@ -34,12 +41,15 @@ pub(crate) fn generate_file(
     if ctx.errors.is_empty() {
         ctx.locations.sort_unstable_by(|l, r| l.path.cmp(&r.path));
 
-        Ok(FileDescriptorProto {
-            source_code_info: Some(SourceCodeInfo {
-                location: ctx.locations,
-            }),
-            ..file
-        })
+        Ok((
+            FileDescriptorProto {
+                source_code_info: Some(SourceCodeInfo {
+                    location: ctx.locations,
+                }),
+                ..file
+            },
+            ctx.warnings,
+        ))
     } else {
         Err(ctx.errors)
     }
@@ -47,12 +57,16 @@ pub(crate) fn generate_file(
 
 struct Context {
     syntax: ast::Syntax,
+    allow_proto3_default_values: bool,
+    allow_list_option_values: bool,
     errors: Vec<ParseErrorKind>,
+    warnings: Vec<WarningKind>,
     path: Vec<i32>,
     locations: Vec<Location>,
     lines: LineResolver,
 }
 
+#[derive(Clone, Copy)]
 enum FieldScope {
     Message,
     Oneof,
@@ -357,12 +371,15 @@ impl Context {
                 };
 
                 let oneof_index = index_to_i32(oneofs.len());
-                self.path.extend([oneof_tag, oneof_index]);
                 oneofs.push(OneofDescriptorProto {
                     name: Some(oneof_name),
                     options: None,
                 });
-                self.pop_path(2);
+
+                // The synthetic oneof is not in the source, so give it the span of its field.
+                let field_path = self.path.split_off(self.path.len() - 2);
+                self.add_span_for(&[oneof_tag, oneof_index], ast.span.clone());
+                self.path.extend(field_path);
 
                 (Some(true), Some(oneof_index))
             } else {
@@ -372,7 +389,7 @@ impl Context {
             (None, None)
         };
 
-        let default_value_option = take_option(&mut ast.options, "default");
+        let mut default_value_option = take_option(&mut ast.options, "default");
         let default_value_option_span = default_value_option.as_ref().map(|o| o.span());
 
         if let Some(span) = default_value_option_span {
@@ -385,8 +402,13 @@ impl Context {
                     span,
                 });
             } else if self.syntax != ast::Syntax::Proto2 {
-                self.errors
-                    .push(ParseErrorKind::Proto3DefaultValue { span });
+                if self.allow_proto3_default_values {
+                    self.warnings.push(WarningKind::Proto3DefaultValue { span });
+                    default_value_option = None;
+                } else {
+                    self.errors
+                        .push(ParseErrorKind::Proto3DefaultValue { span });
+                }
             }
         }
 
@@ -401,7 +423,7 @@ impl Context {
                 ..
             } => {
                 name = ast.name.value;
-                label = self.generate_field_label(ast.label, ast.span.clone(), scope);
+                label = self.generate_field_label(&name, ast.label, ast.span.clone(), scope);
                 r#type = None;
                 type_name = Some(ty.to_string());
 
@@ -414,7 +436,7 @@ impl Context {
             }
             ast::FieldKind::Normal { ty, ty_span } => {
                 name = ast.name.value;
-                label = self.generate_field_label(ast.label, ast.span.clone(), scope);
+                label = self.generate_field_label(&name, ast.label, ast.span.clone(), scope);
                 r#type = ty.proto_ty();
                 type_name = None;
 
@@ -428,14 +450,20 @@ impl Context {
             }
             ast::FieldKind::Group { ty_span, body } => {
                 name = ast.name.value.to_ascii_lowercase();
-                label = self.generate_field_label(ast.label, ast.span.clone(), scope);
+                label = self.generate_field_label(&name, ast.label, ast.span.clone(), scope);
                 r#type = Some(field_descriptor_proto::Type::Group);
                 type_name = Some(ast.name.value);
 
                 if self.syntax != ast::Syntax::Proto2 {
-                    self.errors.push(ParseErrorKind::Proto3GroupField {
-                        span: ast.span.clone(),
-                    });
+                    if matches!(scope, FieldScope::Extend) {
+                        self.errors.push(ParseErrorKind::Proto3ExtendGroupField {
+                            span: ty_span.clone(),
+                        });
+                    } else {
+                        self.errors.push(ParseErrorKind::Proto3GroupField {
+                            span: ast.span.clone(),
+                        });
+                    }
                 }
 
                 if let Some(o) = default_value_option {
@@ -577,6 +605,7 @@ impl Context {
 
     fn generate_field_label(
         &mut self,
+        name: &str,
         label: Option<(ast::FieldLabel, Span)>,
         field_span: Span,
         scope: FieldScope,
@@ -587,6 +616,13 @@ impl Context {
                     .push(ParseErrorKind::RequiredExtendField { span });
                 None
             }
+            (FieldScope::Oneof, Some((ast::FieldLabel::Repeated, span))) => {
+                self.errors.push(ParseErrorKind::RepeatedFieldInOneof {
+                    field_name: name.to_owned(),
+                    span,
+                });
+                None
+            }
             (FieldScope::Oneof, Some((_, span))) => {
                 self.errors
                     .push(ParseErrorKind::OneofFieldWithLabel { span });
@@ -628,6 +664,19 @@ impl Context {
         use field_descriptor_proto::Type;
 
         match (ty, value) {
+            (
+                Some(Type::Double | Type::Float),
+                ast::OptionValue::Ident {
+                    negative, ident, ..
+                },
+            ) if ident.value == "inf" || ident.value == "nan" => {
+                // Keep the sign of NaN, like protoc.
+                if negative {
+                    Some(format!("-{}", ident.value))
+                } else {
+                    Some(ident.value)
+                }
+            }
             (Some(Type::Double | Type::Float), value) => {
                 if let Some(float) = value.as_f64() {
                     let mut string = float.to_string();
@@ -733,6 +782,7 @@ impl Context {
                 }
             }
             (Some(Type::String), value) => self.generate_string_option_value(value),
+            // The string holds the unescaped bytes, which are C-escaped again by its `Display` implementation.
             (Some(Type::Bytes), ast::OptionValue::String(string)) => Some(string.to_string()),
             (Some(Type::Bytes), value) => {
                 self.errors.push(ParseErrorKind::ValueInvalidType {
@@ -1062,6 +1112,17 @@ impl Context {
         self.add_span_for(&[tag::method::NAME], ast.name.span);
         let name = Some(ast.name.value);
 
+        for (streaming, ty) in [
+            (&ast.client_streaming, &ast.input_ty),
+            (&ast.server_streaming, &ast.output_ty),
+        ] {
+            // protoc always parses `stream` as a keyword here, so it rejects a type with this name.
+            if streaming.is_none() && ty.leading_dot.is_none() && ty.name.to_string() == "stream" {
+                self.warnings
+                    .push(WarningKind::StreamMethodType { span: ty.span() });
+            }
+        }
+
         self.add_span_for(&[tag::method::INPUT_TYPE], ast.input_ty.span());
         let input_type = ast.input_ty.to_string();
 
@@ -1107,12 +1168,17 @@ impl Context {
 
         for option_ast in ast {
             self.add_span(option_ast.span.clone());
-            self.add_comments_for(
-                &[tag::UNINTERPRETED_OPTION, index_to_i32(options.len())],
-                option_ast.span,
-                option_ast.comments,
-            );
-            options.push(self.generate_option(option_ast.body));
+            let mut comments = Some(option_ast.comments);
+            for option in self.generate_option(option_ast.body) {
+                let path = [tag::UNINTERPRETED_OPTION, index_to_i32(options.len())];
+                match comments.take() {
+                    Some(comments) => {
+                        self.add_comments_for(&path, option_ast.span.clone(), comments)
+                    }
+                    None => self.add_span_for(&path, option_ast.span.clone()),
+                }
+                options.push(option);
+            }
         }
 
         if options.is_empty() {
@@ -1132,11 +1198,14 @@ impl Context {
             self.add_span(ast.span);
 
             for option_ast in ast.options {
-                self.add_span_for(
-                    &[tag::UNINTERPRETED_OPTION, index_to_i32(options.len())],
-                    option_ast.span(),
-                );
-                options.push(self.generate_option(option_ast));
+                let span = option_ast.span();
+                for option in self.generate_option(option_ast) {
+                    self.add_span_for(
+                        &[tag::UNINTERPRETED_OPTION, index_to_i32(options.len())],
+                        span.clone(),
+                    );
+                    options.push(option);
+                }
             }
         }
 
@@ -1147,7 +1216,9 @@ impl Context {
         }
     }
 
-    fn generate_option(&mut self, ast: ast::OptionBody) -> UninterpretedOption {
+    /// Generates the uninterpreted options for an option. If allowed, a list value generates an option for each
+    /// element, which are appended to the repeated field when the options are interpreted. Empty lists are an error.
+    fn generate_option(&mut self, ast: ast::OptionBody) -> Vec<UninterpretedOption> {
         let mut name = Vec::new();
         for part in ast.name {
             match part {
@@ -1165,6 +1236,34 @@ impl Context {
         }
 
         match ast.value {
+            ast::OptionValue::List(_, span) if !self.allow_list_option_values => {
+                self.errors.push(ParseErrorKind::ListOptionValue { span });
+                Vec::new()
+            }
+            ast::OptionValue::List(values, span) if values.is_empty() => {
+                // An empty list would generate no options, so the option name would never be checked.
+                self.errors.push(ParseErrorKind::EmptyOptionList { span });
+                Vec::new()
+            }
+            ast::OptionValue::List(values, span) => {
+                // The warning also records which options came from a list, so that lists for non-repeated fields can
+                // be rejected once the option names are resolved.
+                self.warnings.push(WarningKind::ListOptionValue { span });
+                values
+                    .into_iter()
+                    .map(|value| self.generate_option_value(name.clone(), value))
+                    .collect()
+            }
+            value => vec![self.generate_option_value(name, value)],
+        }
+    }
+
+    fn generate_option_value(
+        &mut self,
+        name: Vec<uninterpreted_option::NamePart>,
+        value: ast::OptionValue,
+    ) -> UninterpretedOption {
+        match value {
             ast::OptionValue::Ident {
                 negative: false,
                 ident,
@@ -1174,6 +1273,19 @@ impl Context {
                 identifier_value: Some(ident.value),
                 ..Default::default()
             },
+            ast::OptionValue::Ident {
+                negative: true,
+                ident,
+                ..
+            } if ident.value == "inf" || ident.value == "nan" => UninterpretedOption {
+                name,
+                double_value: Some(if ident.value == "inf" {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::NAN
+                }),
+                ..Default::default()
+            },
             ast::OptionValue::Ident {
                 negative: true,
                 span,
@@ -1224,6 +1336,7 @@ impl Context {
                 aggregate_value: Some(message),
                 ..Default::default()
             },
+            ast::OptionValue::List(..) => unreachable!("lists cannot be nested"),
         }
     }
 
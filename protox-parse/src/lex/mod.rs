@@ -1,11 +1,11 @@
 #[cfg(test)]
 mod tests;
 
-use std::{ascii, borrow::Cow, convert::TryInto, fmt, num::IntErrorKind};
+use std::{ascii, borrow::Cow, convert::TryInto, fmt, num::IntErrorKind, ops::Range};
 
 use logos::{Lexer, Logos};
 
-use super::error::ParseErrorKind;
+use super::error::{ExpectedToken, ParseErrorKind};
 
 #[derive(Debug, Clone, Logos, PartialEq, Eq)]
 #[logos(extras = TokenExtras)]
@@ -67,6 +67,86 @@ pub(crate) enum Token<'a> {
     Newline,
 }
 
+/// The category of a token, as returned by [`tokenize()`](crate::tokenize).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// A keyword, such as `message` or `int32`.
+    ///
+    /// Keywords are not reserved in protobuf, so depending on context they may also be used as identifiers.
+    Keyword,
+    /// An identifier, such as a type or field name.
+    Ident,
+    /// An integer literal.
+    IntLiteral,
+    /// A floating-point literal.
+    FloatLiteral,
+    /// A string literal, including quotes.
+    StringLiteral,
+    /// A punctuation character, such as `{`, `;` or `=`.
+    Punctuation,
+    /// A line or block comment, including delimiters.
+    Comment,
+    /// Text which is not a valid token.
+    Invalid,
+}
+
+const KEYWORDS: &[&str] = &[
+    "syntax",
+    "package",
+    "import",
+    "weak",
+    "public",
+    "enum",
+    "option",
+    "service",
+    "rpc",
+    "stream",
+    "returns",
+    "extend",
+    "message",
+    "optional",
+    "required",
+    "repeated",
+    "map",
+    "oneof",
+    "group",
+    "double",
+    "float",
+    "int32",
+    "int64",
+    "uint32",
+    "uint64",
+    "sint32",
+    "sint64",
+    "fixed32",
+    "fixed64",
+    "sfixed32",
+    "sfixed64",
+    "bool",
+    "string",
+    "bytes",
+    "reserved",
+    "extensions",
+    "to",
+    "max",
+];
+
+pub(crate) fn tokenize(source: &str) -> impl Iterator<Item = (TokenKind, Range<usize>)> + '_ {
+    Token::lexer(source)
+        .spanned()
+        .filter_map(move |(token, mut span)| {
+            let kind = match token {
+                Ok(token) => token.kind()?,
+                Err(()) => TokenKind::Invalid,
+            };
+            if kind == TokenKind::Comment && source[span.clone()].ends_with('\n') {
+                span.end -= 1;
+            }
+            Some((kind, span))
+        })
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) struct EqFloat(pub f64);
 
@@ -120,6 +200,37 @@ impl<'a> Token<'a> {
     pub const MAX: Token<'static> = Token::Ident("max");
 }
 
+impl<'a> Token<'a> {
+    /// Gets the category of this token, or `None` for newlines.
+    fn kind(&self) -> Option<TokenKind> {
+        match self {
+            Token::Ident(value) if KEYWORDS.contains(value) => Some(TokenKind::Keyword),
+            Token::Ident(_) => Some(TokenKind::Ident),
+            Token::IntLiteral(_) => Some(TokenKind::IntLiteral),
+            Token::FloatLiteral(_) => Some(TokenKind::FloatLiteral),
+            Token::StringLiteral(_) => Some(TokenKind::StringLiteral),
+            Token::LineComment(_) | Token::BlockComment(_) => Some(TokenKind::Comment),
+            Token::Newline => None,
+            Token::Dot
+            | Token::Minus
+            | Token::Plus
+            | Token::LeftParen
+            | Token::RightParen
+            | Token::LeftBrace
+            | Token::RightBrace
+            | Token::LeftBracket
+            | Token::RightBracket
+            | Token::LeftAngleBracket
+            | Token::RightAngleBracket
+            | Token::Comma
+            | Token::Equals
+            | Token::Colon
+            | Token::Semicolon
+            | Token::ForwardSlash => Some(TokenKind::Punctuation),
+        }
+    }
+}
+
 impl<'a> fmt::Display for Token<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -336,7 +447,7 @@ fn string<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Cow<'a, [u8]> {
             }
             None => {
                 lex.extras.errors.push(ParseErrorKind::UnexpectedEof {
-                    expected: "string terminator".to_owned(),
+                    expected: ExpectedToken::Description("string terminator").into(),
                 });
                 break;
             }
@@ -411,7 +522,7 @@ fn block_comment<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Cow<'a, str> {
                     break last_end;
                 } else {
                     lex.extras.errors.push(ParseErrorKind::UnexpectedEof {
-                        expected: "comment terminator".to_owned(),
+                        expected: ExpectedToken::CommentTerminator.into(),
                     });
                     break lex.remainder().len();
                 }
@@ -216,7 +216,7 @@ fn string_unterminated() {
     assert_eq!(
         lexer.extras.errors,
         vec![ParseErrorKind::UnexpectedEof {
-            expected: "string terminator".to_owned()
+            expected: ExpectedToken::Description("string terminator").into()
         }]
     );
 }
@@ -389,12 +389,17 @@ fn block_comment_unterminated() {
     let source = "foo /* bar\n quz";
     let mut lexer = Token::lexer(source);
 
-    for _ in &mut lexer {}
+    assert_eq!(lexer.next(), Some(Ok(Token::Ident("foo"))));
+    assert_eq!(
+        lexer.next(),
+        Some(Ok(Token::BlockComment(" bar\nquz".into())))
+    );
+    assert_eq!(lexer.next(), None);
 
     assert_eq!(
         lexer.extras.errors,
         vec![ParseErrorKind::UnexpectedEof {
-            expected: "comment terminator".to_owned()
+            expected: ExpectedToken::CommentTerminator.into()
         }]
     );
 }
@@ -5,20 +5,27 @@
 #![deny(unsafe_code)]
 #![doc(html_root_url = "https://docs.rs/protox-parse/0.7.0/")]
 
+use std::ops::Range;
+
 use logos::Span;
+use miette::NamedSource;
 use prost_types::FileDescriptorProto;
 
-pub use self::error::ParseError;
+pub use self::error::{ParseError, Warning};
+pub use self::lex::TokenKind;
 
-mod ast;
+pub mod ast;
 mod case;
 mod error;
+mod fingerprint;
 mod generate;
 mod lex;
 mod parse;
+mod recover;
 mod tag;
 #[cfg(test)]
 mod tests;
+pub mod text_format;
 
 const MAX_MESSAGE_FIELD_NUMBER: i32 = 536_870_911;
 
@@ -74,6 +81,51 @@ const MAX_MESSAGE_FIELD_NUMBER: i32 = 536_870_911;
 /// })
 /// ```
 pub fn parse(name: &str, source: &str) -> Result<FileDescriptorProto, ParseError> {
+    parse_with_warnings(name, source).map(|(file, _)| file)
+}
+
+/// Splits a protobuf source file into tokens, without parsing it.
+///
+/// Whitespace is skipped, but comments are included. Invalid input does not stop tokenization, but is returned as
+/// tokens with [`TokenKind::Invalid`]. This is useful for tools such as syntax highlighters, which need to handle
+/// incomplete or invalid source files.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{tokenize, TokenKind};
+/// let tokens: Vec<_> = tokenize("message Foo {} // comment").collect();
+/// assert_eq!(tokens, [
+///     (TokenKind::Keyword, 0..7),
+///     (TokenKind::Ident, 8..11),
+///     (TokenKind::Punctuation, 12..13),
+///     (TokenKind::Punctuation, 13..14),
+///     (TokenKind::Comment, 15..25),
+/// ]);
+/// ```
+pub fn tokenize(source: &str) -> impl Iterator<Item = (TokenKind, Range<usize>)> + '_ {
+    lex::tokenize(source)
+}
+
+/// Parses a single protobuf source file into an [`ast::File`].
+///
+/// Unlike [`parse()`], this only checks that the file is syntactically valid, and preserves the structure and spans
+/// of the source, which is useful for tools such as language servers.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{ast, parse_ast};
+/// let file = parse_ast("foo.proto", "message Foo { optional int32 bar = 1; }").unwrap();
+/// match &file.items[0] {
+///     ast::FileItem::Message(message) => {
+///         assert_eq!(message.name.value, "Foo");
+///         assert_eq!(message.name.span, 8..11);
+///     }
+///     _ => panic!("expected a message"),
+/// }
+/// ```
+pub fn parse_ast(name: &str, source: &str) -> Result<ast::File, ParseError> {
     if source.len() > MAX_FILE_LEN {
         return Err(ParseError::new(
             vec![error::ParseErrorKind::FileTooLarge],
@@ -82,14 +134,277 @@ pub fn parse(name: &str, source: &str) -> Result<FileDescriptorProto, ParseError
         ));
     }
 
-    let ast = parse::parse_file(source)
+    parse::parse_file(source).map_err(|errors| ParseError::new(errors, name, source.to_owned()))
+}
+
+/// Parses the value of an option, such as `"hello"`, `-42`, `true`, `[1, 2]` or `{ key: value }`.
+///
+/// The value is parsed in the same way as the right-hand side of an `option` statement, so aggregate values are
+/// returned as [`ast::OptionValue::Aggregate`] with their text format contents unparsed. Errors are reported against a
+/// source named `<option value>`.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{ast, parse_option_value};
+/// match parse_option_value("-42").unwrap() {
+///     ast::OptionValue::Int(int) => assert_eq!(int.as_i64(), Some(-42)),
+///     _ => panic!("expected an integer"),
+/// }
+/// match parse_option_value("{ key: 'value' }").unwrap() {
+///     ast::OptionValue::Aggregate(text, _) => assert_eq!(text, "key : \"value\""),
+///     _ => panic!("expected an aggregate"),
+/// }
+///
+/// let err = parse_option_value("true false").unwrap_err();
+/// assert_eq!(err.to_string(), "expected end of value, but found 'false'");
+/// ```
+pub fn parse_option_value(source: &str) -> Result<ast::OptionValue, ParseError> {
+    const SOURCE_NAME: &str = "<option value>";
+
+    if source.len() > MAX_FILE_LEN {
+        return Err(ParseError::new(
+            vec![error::ParseErrorKind::FileTooLarge],
+            SOURCE_NAME,
+            String::default(),
+        ));
+    }
+
+    parse::parse_option_value(source)
+        .map_err(|errors| ParseError::new(errors, SOURCE_NAME, source.to_owned()))
+}
+
+/// Options for [`parse_with_options()`], which allow some constructs that protoc rejects.
+///
+/// All options are disabled by default, in which case files are parsed the same way as by [`parse()`].
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    allow_proto3_default_values: bool,
+    allow_list_option_values: bool,
+}
+
+impl ParseOptions {
+    /// Creates a new `ParseOptions` with all options disabled.
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    /// If set, `default` options on fields in `proto3` files are ignored with a warning, instead of being reported
+    /// as an error.
+    ///
+    /// protoc rejects these files, so this should only be enabled by tools which need to accept them anyway.
+    pub fn allow_proto3_default_values(&mut self, allow: bool) -> &mut Self {
+        self.allow_proto3_default_values = allow;
+        self
+    }
+
+    /// If set, options may be given a list of values, such as `option (foo) = [1, 2];`, with a warning, instead of
+    /// this being reported as an error.
+    ///
+    /// Each element sets the option separately, so the option must be a repeated field. protoc does not support this
+    /// syntax.
+    pub fn allow_list_option_values(&mut self, allow: bool) -> &mut Self {
+        self.allow_list_option_values = allow;
+        self
+    }
+}
+
+/// Parses a single protobuf source file into a [`FileDescriptorProto`], returning any warnings found.
+///
+/// This behaves like [`parse()`], but additionally returns issues which do not prevent the file from being
+/// parsed. It is equivalent to calling [`parse_with_options()`] with the default options.
+pub fn parse_with_warnings(
+    name: &str,
+    source: &str,
+) -> Result<(FileDescriptorProto, Vec<Warning>), ParseError> {
+    parse_with_options(name, source, &ParseOptions::default())
+}
+
+/// Parses a single protobuf source file into a [`FileDescriptorProto`] using the given options, returning any
+/// warnings found.
+///
+/// Constructs which are allowed by `options`, but are rejected by protoc, are reported as warnings.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{parse_with_options, ParseOptions};
+/// let source = r#"
+///     syntax = "proto3";
+///
+///     message Foo {
+///         optional int32 bar = 1 [default = 5];
+///     }
+/// "#;
+/// let err = parse_with_options("foo.proto", source, &ParseOptions::new()).unwrap_err();
+/// assert_eq!(err.to_string(), "default values are not allowed in proto3");
+///
+/// let mut options = ParseOptions::new();
+/// options.allow_proto3_default_values(true);
+/// let (file_descriptor, warnings) = parse_with_options("foo.proto", source, &options).unwrap();
+/// assert_eq!(file_descriptor.message_type[0].field[0].default_value, None);
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(format!("{:?}", warnings[0]), "foo.proto:5:33: default values are ignored in proto3");
+/// ```
+pub fn parse_with_options(
+    name: &str,
+    source: &str,
+    options: &ParseOptions,
+) -> Result<(FileDescriptorProto, Vec<Warning>), ParseError> {
+    let ast = parse_ast(name, source)?;
+
+    let (file, warnings) = generate::generate_file(ast, name, source, options)
         .map_err(|errors| ParseError::new(errors, name, source.to_owned()))?;
 
-    generate::generate_file(ast, name, source)
-        .map_err(|errors| ParseError::new(errors, name, source.to_owned()))
+    let warnings = warnings
+        .into_iter()
+        .map(|kind| Warning::new(kind, name, source.to_owned()))
+        .collect();
+    Ok((file, warnings))
+}
+
+/// Parses a single protobuf source file into a [`FileDescriptorProto`], repairing common syntax mistakes.
+///
+/// This is intended for tools such as editors, which need to handle files that are still being written. When parsing
+/// fails, the following repairs are attempted before parsing again:
+///
+/// - a missing `}` or `;` at the end of the file is inserted.
+/// - a block comment which is still open at the end of the file is closed.
+/// - a missing `=` or `;` before an unexpected token is inserted, if the token is preceded by whitespace.
+/// - any other unexpected token is skipped.
+///
+/// Repairs do not move any of the existing source code, so source code info in the returned file, and the spans of
+/// returned errors, apply to the original source.
+///
+/// Each error which was repaired is returned with [`is_recoverable()`](ParseError::is_recoverable) set. If an error
+/// could not be repaired, or a repair did not change the error reported, it is returned last with
+/// [`is_recoverable()`](ParseError::is_recoverable) unset, along with an empty file descriptor.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::parse_with_recovery;
+/// let source = "syntax = 'proto3';\nmessage Foo {\n    int32 foo 1;\n    int32 bar = 2\n";
+/// let (file_descriptor, errors) = parse_with_recovery("foo.proto", source);
+/// assert_eq!(file_descriptor.message_type[0].field.len(), 2);
+/// assert_eq!(errors.len(), 3);
+/// assert!(errors.iter().all(|err| err.is_recoverable()));
+/// assert_eq!(format!("{:?}", errors[0]), "foo.proto:3:15: expected '=', but found '1'");
+/// ```
+pub fn parse_with_recovery(name: &str, source: &str) -> (FileDescriptorProto, Vec<ParseError>) {
+    let mut repaired = source.to_owned();
+    let mut errors: Vec<ParseError> = Vec::new();
+    loop {
+        let err = match parse(name, &repaired) {
+            Ok(file) => return (file, errors),
+            Err(err) => err,
+        };
+
+        // Stop if the last repair had no effect. Tokens appended on a new line at the end of the file always make
+        // progress, even if the error is the same, such as when closing nested blocks.
+        let unchanged = errors.last().is_some_and(|last| match last.kind() {
+            error::ParseErrorKind::Repaired { original } => {
+                **original == *err.kind()
+                    && !matches!(**original, error::ParseErrorKind::UnexpectedEof { .. })
+            }
+            _ => false,
+        });
+        if !unchanged && errors.len() < MAX_REPAIRS && recover::repair(&err, &mut repaired) {
+            errors.push(err.into_repaired(source));
+        } else {
+            errors.push(
+                err.with_source_code(NamedSource::new(name, source.to_owned()))
+                    .into_unrepaired(),
+            );
+            let file = FileDescriptorProto {
+                name: Some(name.to_owned()),
+                ..Default::default()
+            };
+            return (file, errors);
+        }
+    }
+}
+
+/// Computes a SHA-256 hash of the meaning of a [`FileDescriptorProto`], for detecting whether a file has changed.
+///
+/// The hash ignores the [`source_code_info`](FileDescriptorProto::source_code_info) of the file, so changes to
+/// comments or formatting do not affect it. It also ignores the order of uninterpreted options with different names.
+/// The hash is stable across platforms and runs, but may change between versions of this crate.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{fingerprint, parse};
+/// let file = parse("foo.proto", "message Foo { optional int32 a = 1 [deprecated = true, json_name = 'b']; }").unwrap();
+/// let reformatted = parse("foo.proto", "
+///     // A message.
+///     message Foo {
+///         optional int32 a = 1 [json_name = 'b', deprecated = true];
+///     }
+/// ").unwrap();
+/// let changed = parse("foo.proto", "message Foo { optional int32 a = 2; }").unwrap();
+///
+/// assert_eq!(fingerprint(&file), fingerprint(&reformatted));
+/// assert_ne!(fingerprint(&file), fingerprint(&changed));
+/// ```
+pub fn fingerprint(file: &FileDescriptorProto) -> [u8; 32] {
+    let mut file = file.clone();
+    fingerprint::canonicalize(&mut file);
+    fingerprint::hash(&file)
+}
+
+/// Gets the first comment block in a file, before any statements.
+///
+/// This is typically used for file-level notices, such as `// Code generated by protoc-gen-foo. DO NOT EDIT.`
+/// A comment directly attached to the first statement is only returned if that statement is the `syntax` or
+/// `package` declaration, so the documentation of a message or other definition is never returned. The comment is
+/// read from the [`source_code_info`](FileDescriptorProto::source_code_info) of the file, so this returns `None` if
+/// it has been removed.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::{leading_file_comment, parse};
+/// let source = r#"
+///     // Code generated by protoc-gen-foo. DO NOT EDIT.
+///
+///     syntax = "proto3";
+///
+///     // A message.
+///     message Foo {}
+/// "#;
+/// let file_descriptor = parse("foo.proto", source).unwrap();
+/// assert_eq!(
+///     leading_file_comment(&file_descriptor).as_deref(),
+///     Some(" Code generated by protoc-gen-foo. DO NOT EDIT.\n")
+/// );
+/// ```
+pub fn leading_file_comment(file: &FileDescriptorProto) -> Option<String> {
+    let first_statement = file
+        .source_code_info
+        .as_ref()?
+        .location
+        .iter()
+        .filter(|location| matches!(location.path.len(), 1 | 2))
+        .min_by_key(|location| (location.span.first(), location.span.get(1)))?;
+
+    let is_file_declaration = matches!(
+        first_statement.path.as_slice(),
+        [tag::file::SYNTAX | tag::file::PACKAGE]
+    );
+
+    first_statement
+        .leading_detached_comments
+        .first()
+        .or(first_statement
+            .leading_comments
+            .as_ref()
+            .filter(|_| is_file_declaration))
+        .cloned()
 }
 
 const MAX_FILE_LEN: usize = i32::MAX as usize;
+const MAX_REPAIRS: usize = 100;
 
 fn index_to_i32(index: usize) -> i32 {
     // We enforce that all files parsed are at most i32::MAX bytes long. Therefore the indices of any
@@ -1,8 +1,4 @@
-use std::{
-    fmt::{self, Write},
-    iter::once,
-    path::Path,
-};
+use std::{fmt::Write, iter::once, path::Path};
 
 use logos::{Lexer, Logos, Span};
 
@@ -10,7 +6,7 @@ use self::comments::Comments;
 use crate::{
     ast,
     case::{is_valid_group_name, is_valid_ident},
-    error::ParseErrorKind,
+    error::{Expected, ExpectedToken, ParseErrorKind},
     join_span,
     lex::{EqFloat, Token},
 };
@@ -18,6 +14,9 @@ use crate::{
 mod comments;
 #[cfg(test)]
 mod tests;
+mod text_format;
+
+pub(crate) use self::text_format::parse_text_format;
 
 pub(crate) fn parse_file(source: &str) -> Result<ast::File, Vec<ParseErrorKind>> {
     let mut parser = Parser::new(source);
@@ -27,18 +26,27 @@ pub(crate) fn parse_file(source: &str) -> Result<ast::File, Vec<ParseErrorKind>>
     }
 }
 
+pub(crate) fn parse_option_value(source: &str) -> Result<ast::OptionValue, Vec<ParseErrorKind>> {
+    let mut parser = Parser::new(source);
+    let result =
+        parser
+            .parse_option_body_value()
+            .and_then(|value| match parser.peek_skip_comments()? {
+                None => Ok(value),
+                Some(_) => parser.unexpected_token(ExpectedToken::Description("end of value")),
+            });
+    match result {
+        Ok(value) if parser.lexer.extras.errors.is_empty() => Ok(value),
+        _ => Err(parser.lexer.extras.errors),
+    }
+}
+
 struct Parser<'a> {
     lexer: Lexer<'a, Token<'a>>,
     peek: Option<Result<(Token<'a>, Span), ()>>,
     comments: Comments,
 }
 
-#[derive(Debug, Clone)]
-enum ExpectedToken {
-    Token(Token<'static>),
-    Ident,
-}
-
 enum Statement {
     Empty(Span),
     Package(ast::Package),
@@ -154,7 +162,7 @@ impl<'a> Parser<'a> {
                     }
                 }
             }
-            _ => self.unexpected_token("a string")?,
+            _ => self.unexpected_token(ExpectedToken::Description("a string"))?,
         };
 
         let end = self.expect_eq(Token::Semicolon)?;
@@ -178,9 +186,16 @@ impl<'a> Parser<'a> {
             Some((Token::ENUM, _)) => Ok(Some(Statement::Enum(self.parse_enum()?))),
             Some((Token::SERVICE, _)) => Ok(Some(Statement::Service(self.parse_service()?))),
             None => Ok(None),
-            _ => self.unexpected_token(
-                "'enum', 'extend', 'import', 'message', 'option', 'service', 'package' or ';'",
-            ),
+            _ => self.unexpected_token([
+                ExpectedToken::Token(Token::ENUM),
+                ExpectedToken::Token(Token::EXTEND),
+                ExpectedToken::Token(Token::IMPORT),
+                ExpectedToken::Token(Token::MESSAGE),
+                ExpectedToken::Token(Token::OPTION),
+                ExpectedToken::Token(Token::SERVICE),
+                ExpectedToken::Token(Token::PACKAGE),
+                ExpectedToken::SEMICOLON,
+            ]),
         }
     }
 
@@ -217,7 +232,11 @@ impl<'a> Parser<'a> {
                 Some((ast::ImportKind::Public, span))
             }
             Some((Token::StringLiteral(_), _)) => None,
-            _ => self.unexpected_token("a string literal, 'public' or 'weak'")?,
+            _ => self.unexpected_token([
+                ExpectedToken::Description("a string literal"),
+                ExpectedToken::Token(Token::PUBLIC),
+                ExpectedToken::Token(Token::WEAK),
+            ])?,
         };
 
         let (value, value_span) = self.parse_utf8_string()?;
@@ -287,9 +306,15 @@ impl<'a> Parser<'a> {
                     continue;
                 }
                 Some((Token::RightBrace, _)) => break self.bump(),
-                _ => self.unexpected_token(
-                    "a message field, oneof, reserved range, enum, message, option or '}'",
-                )?,
+                _ => self.unexpected_token([
+                    ExpectedToken::Description("a message field"),
+                    ExpectedToken::Description("oneof"),
+                    ExpectedToken::Description("reserved range"),
+                    ExpectedToken::Description("enum"),
+                    ExpectedToken::Description("message"),
+                    ExpectedToken::Description("option"),
+                    ExpectedToken::RIGHT_BRACE,
+                ])?,
             }
         };
 
@@ -321,7 +346,7 @@ impl<'a> Parser<'a> {
                 (Some((ast::FieldLabel::Repeated, span.clone())), span)
             }
             Some((Token::Dot | Token::Ident(_), span)) => (None, span),
-            _ => self.unexpected_token("a message field")?,
+            _ => self.unexpected_token(ExpectedToken::Description("a message field"))?,
         };
 
         match self.peek()? {
@@ -355,7 +380,7 @@ impl<'a> Parser<'a> {
         let options = match self.peek_skip_comments()? {
             Some((Token::LeftBracket, _)) => Some(self.parse_options_list()?),
             Some((Token::Semicolon, _)) => None,
-            _ => self.unexpected_token("';' or '['")?,
+            _ => self.unexpected_token([ExpectedToken::SEMICOLON, ExpectedToken::LEFT_BRACKET])?,
         };
 
         let end = self.expect_eq(Token::Semicolon)?;
@@ -400,7 +425,7 @@ impl<'a> Parser<'a> {
         let options = match self.peek_skip_comments()? {
             Some((Token::LeftBracket, _)) => Some(self.parse_options_list()?),
             Some((Token::LeftBrace, _)) => None,
-            _ => self.unexpected_token("'{' or '['")?,
+            _ => self.unexpected_token([ExpectedToken::LEFT_BRACE, ExpectedToken::LEFT_BRACKET])?,
         };
 
         self.expect_eq(Token::LeftBrace)?;
@@ -437,7 +462,7 @@ impl<'a> Parser<'a> {
         let options = match self.peek_skip_comments()? {
             Some((Token::LeftBracket, _)) => Some(self.parse_options_list()?),
             Some((Token::Semicolon, _)) => None,
-            _ => self.unexpected_token("';' or '['")?,
+            _ => self.unexpected_token([ExpectedToken::SEMICOLON, ExpectedToken::LEFT_BRACKET])?,
         };
 
         let end = self.expect_eq(Token::Semicolon)?;
@@ -473,7 +498,11 @@ impl<'a> Parser<'a> {
                 }
                 Some((Token::RightBrace, _)) => break self.bump(),
                 Some((Token::Dot | Token::Ident(_), _)) => fields.push(self.parse_field()?),
-                _ => self.unexpected_token("a message field, '}' or ';'")?,
+                _ => self.unexpected_token([
+                    ExpectedToken::Description("a message field"),
+                    ExpectedToken::RIGHT_BRACE,
+                    ExpectedToken::SEMICOLON,
+                ])?,
             }
         };
 
@@ -511,7 +540,12 @@ impl<'a> Parser<'a> {
                     continue;
                 }
                 Some((Token::RightBrace, _)) => break self.bump(),
-                _ => self.unexpected_token("'rpc', '}', 'option' or ';'")?,
+                _ => self.unexpected_token([
+                    ExpectedToken::Token(Token::RPC),
+                    ExpectedToken::RIGHT_BRACE,
+                    ExpectedToken::Token(Token::OPTION),
+                    ExpectedToken::SEMICOLON,
+                ])?,
             }
         };
 
@@ -533,25 +567,13 @@ impl<'a> Parser<'a> {
 
         self.expect_eq(Token::LeftParen)?;
 
-        let client_streaming = match self.peek_skip_comments()? {
-            Some((Token::STREAM, _)) => Some(self.bump()),
-            Some((Token::Dot | Token::Ident(_), _)) => None,
-            _ => self.unexpected_token("'stream' or a type name")?,
-        };
-
-        let input_ty = self.parse_type_name(&[ExpectedToken::RIGHT_PAREN])?;
+        let (client_streaming, input_ty) = self.parse_service_rpc_type()?;
 
         self.expect_eq(Token::RightParen)?;
         self.expect_eq(Token::RETURNS)?;
         self.expect_eq(Token::LeftParen)?;
 
-        let server_streaming = match self.peek_skip_comments()? {
-            Some((Token::STREAM, _)) => Some(self.bump()),
-            Some((Token::Dot | Token::Ident(_), _)) => None,
-            _ => self.unexpected_token("'stream' or a type name")?,
-        };
-
-        let output_ty = self.parse_type_name(&[ExpectedToken::RIGHT_PAREN])?;
+        let (server_streaming, output_ty) = self.parse_service_rpc_type()?;
 
         self.expect_eq(Token::RightParen)?;
 
@@ -573,11 +595,15 @@ impl<'a> Parser<'a> {
                             self.bump();
                             continue;
                         }
-                        _ => self.unexpected_token("'option', '}' or ';'")?,
+                        _ => self.unexpected_token([
+                            ExpectedToken::Token(Token::OPTION),
+                            ExpectedToken::RIGHT_BRACE,
+                            ExpectedToken::SEMICOLON,
+                        ])?,
                     }
                 }
             }
-            _ => self.unexpected_token("';' or '{'")?,
+            _ => self.unexpected_token([ExpectedToken::SEMICOLON, ExpectedToken::LEFT_BRACE])?,
         };
 
         let comments = self.parse_trailing_comment(leading_comments);
@@ -594,6 +620,36 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses the input or output type of a method, with an optional `stream` keyword. Since `stream` is also a valid
+    /// type name, it is not treated as a keyword if it is the whole type name. As with `protoc`, `stream.Foo` is
+    /// parsed as a streaming method of type `.Foo`.
+    fn parse_service_rpc_type(&mut self) -> Result<(Option<Span>, ast::TypeName), ()> {
+        match self.peek_skip_comments()? {
+            Some((Token::STREAM, _)) => {
+                let stream = self.parse_ident()?;
+                match self.peek_skip_comments()? {
+                    Some((Token::RightParen, _)) => Ok((
+                        None,
+                        ast::TypeName {
+                            name: stream.into(),
+                            leading_dot: None,
+                        },
+                    )),
+                    _ => Ok((
+                        Some(stream.span),
+                        self.parse_type_name(&[ExpectedToken::RIGHT_PAREN])?,
+                    )),
+                }
+            }
+            Some((Token::Dot | Token::Ident(_), _)) => {
+                Ok((None, self.parse_type_name(&[ExpectedToken::RIGHT_PAREN])?))
+            }
+            _ => self.unexpected_token([
+                ExpectedToken::Token(Token::STREAM),
+                ExpectedToken::Description("a type name"),
+            ]),
+        }
+    }
     fn parse_enum(&mut self) -> Result<ast::Enum, ()> {
         let leading_comments = self.parse_leading_comments();
 
@@ -624,7 +680,12 @@ impl<'a> Parser<'a> {
                     values.push(self.parse_enum_value()?);
                 }
                 Some((Token::RightBrace, _)) => break self.bump(),
-                _ => self.unexpected_token("an identifier, '}', 'reserved' or 'option'")?,
+                _ => self.unexpected_token([
+                    ExpectedToken::Ident,
+                    ExpectedToken::RIGHT_BRACE,
+                    ExpectedToken::Token(Token::RESERVED),
+                    ExpectedToken::Token(Token::OPTION),
+                ])?,
             };
         };
 
@@ -650,7 +711,7 @@ impl<'a> Parser<'a> {
         let options = match self.peek_skip_comments()? {
             Some((Token::LeftBracket, _)) => Some(self.parse_options_list()?),
             Some((Token::Semicolon, _)) => None,
-            _ => self.unexpected_token("';' or '['")?,
+            _ => self.unexpected_token([ExpectedToken::SEMICOLON, ExpectedToken::LEFT_BRACKET])?,
         };
 
         let end = self.expect_eq(Token::Semicolon)?;
@@ -686,7 +747,11 @@ impl<'a> Parser<'a> {
                 }
                 Some((Token::RightBrace, _)) => break self.bump(),
                 Some((Token::Dot | Token::Ident(_), _)) => fields.push(self.parse_field()?),
-                _ => self.unexpected_token("a message field, option or '}'")?,
+                _ => self.unexpected_token([
+                    ExpectedToken::Description("a message field"),
+                    ExpectedToken::Description("option"),
+                    ExpectedToken::RIGHT_BRACE,
+                ])?,
             }
         };
 
@@ -721,7 +786,7 @@ impl<'a> Parser<'a> {
                 let span = type_name.span();
                 return Ok((ast::Ty::Named(type_name), span));
             }
-            _ => self.unexpected_token("a field type")?,
+            _ => self.unexpected_token(ExpectedToken::Description("a field type"))?,
         };
 
         self.bump();
@@ -752,7 +817,10 @@ impl<'a> Parser<'a> {
                     span: join_span(start, end),
                 })
             }
-            _ => self.unexpected_token("a positive integer or string"),
+            _ => self.unexpected_token([
+                ExpectedToken::Description("a positive integer"),
+                ExpectedToken::Description("string"),
+            ]),
         }
     }
 
@@ -766,7 +834,7 @@ impl<'a> Parser<'a> {
         let options = match self.peek_skip_comments()? {
             Some((Token::LeftBracket, _)) => Some(self.parse_options_list()?),
             Some((Token::Semicolon, _)) => None,
-            _ => self.unexpected_token("';' or '['")?,
+            _ => self.unexpected_token([ExpectedToken::SEMICOLON, ExpectedToken::LEFT_BRACKET])?,
         };
 
         let end = self.expect_eq(Token::Semicolon)?;
@@ -791,7 +859,7 @@ impl<'a> Parser<'a> {
                     names.push(self.parse_ident_string()?);
                 }
                 Some((Token::Semicolon, _)) => break self.bump(),
-                _ => self.unexpected_token("',' or ';'")?,
+                _ => self.unexpected_token([ExpectedToken::COMMA, ExpectedToken::SEMICOLON])?,
             }
         };
 
@@ -820,9 +888,11 @@ impl<'a> Parser<'a> {
                     continue;
                 }
                 Some((tok, _)) if terminators.iter().any(|e| e.matches(&tok)) => break,
-                _ => self.unexpected_token(fmt_expected(
-                    once(ExpectedToken::Token(Token::Comma)).chain(terminators.iter().cloned()),
-                ))?,
+                _ => self.unexpected_token(
+                    once(ExpectedToken::COMMA)
+                        .chain(terminators.iter().cloned())
+                        .collect::<Vec<_>>(),
+                )?,
             }
         }
 
@@ -843,11 +913,18 @@ impl<'a> Parser<'a> {
                         self.bump();
                         ast::ReservedRangeEnd::Max(span)
                     }
-                    _ => self.unexpected_token("an integer or 'max'")?,
+                    _ => self.unexpected_token([
+                        ExpectedToken::Description("an integer"),
+                        ExpectedToken::Token(Token::MAX),
+                    ])?,
                 }
             }
             Some((Token::Comma | Token::Semicolon, _)) => ast::ReservedRangeEnd::None,
-            _ => self.unexpected_token("'to', ',' or ';'")?,
+            _ => self.unexpected_token([
+                ExpectedToken::Token(Token::TO),
+                ExpectedToken::COMMA,
+                ExpectedToken::SEMICOLON,
+            ])?,
         };
 
         Ok(ast::ReservedRange { start, end })
@@ -864,7 +941,7 @@ impl<'a> Parser<'a> {
                     options.push(self.parse_option_body()?);
                 }
                 Some((Token::RightBracket, _)) => break self.bump(),
-                _ => self.unexpected_token("',' or ']'")?,
+                _ => self.unexpected_token([ExpectedToken::COMMA, ExpectedToken::RIGHT_BRACKET])?,
             }
         };
 
@@ -903,11 +980,49 @@ impl<'a> Parser<'a> {
                     self.bump();
                     break;
                 }
-                _ => self.unexpected_token("'=' or '.'")?,
+                _ => self.unexpected_token([ExpectedToken::EQUALS, ExpectedToken::DOT])?,
             }
         }
 
-        let value = match self.peek_skip_comments()? {
+        let value = self.parse_option_body_value()?;
+
+        Ok(ast::OptionBody { name, value })
+    }
+
+    /// Parses the value of an option, which may be a list.
+    fn parse_option_body_value(&mut self) -> Result<ast::OptionValue, ()> {
+        match self.peek_skip_comments()? {
+            Some((Token::LeftBracket, _)) => self.parse_option_list_value(),
+            _ => self.parse_option_value(),
+        }
+    }
+
+    fn parse_option_list_value(&mut self) -> Result<ast::OptionValue, ()> {
+        let start = self.expect_eq(Token::LeftBracket)?;
+
+        let mut values = Vec::new();
+        if let Some((Token::RightBracket, end)) = self.peek_skip_comments()? {
+            self.bump();
+            return Ok(ast::OptionValue::List(values, join_span(start, end)));
+        }
+
+        values.push(self.parse_option_value()?);
+        let end = loop {
+            match self.peek_skip_comments()? {
+                Some((Token::Comma, _)) => {
+                    self.bump();
+                    values.push(self.parse_option_value()?);
+                }
+                Some((Token::RightBracket, _)) => break self.bump(),
+                _ => self.unexpected_token([ExpectedToken::COMMA, ExpectedToken::RIGHT_BRACKET])?,
+            }
+        };
+
+        Ok(ast::OptionValue::List(values, join_span(start, end)))
+    }
+
+    fn parse_option_value(&mut self) -> Result<ast::OptionValue, ()> {
+        Ok(match self.peek_skip_comments()? {
             Some((Token::Minus, start)) => {
                 self.bump();
                 match self.peek_skip_comments()? {
@@ -931,7 +1046,7 @@ impl<'a> Parser<'a> {
                             span: join_span(start, end),
                         })
                     }
-                    _ => self.unexpected_token("a numeric literal")?,
+                    _ => self.unexpected_token(ExpectedToken::Description("a numeric literal"))?,
                 }
             }
             Some((Token::Ident(_), span)) => ast::OptionValue::Ident {
@@ -958,10 +1073,8 @@ impl<'a> Parser<'a> {
                 let end = self.expect_eq(Token::RightBrace)?;
                 ast::OptionValue::Aggregate(value, join_span(start, end))
             }
-            _ => self.unexpected_token("a constant")?,
-        };
-
-        Ok(ast::OptionBody { name, value })
+            _ => self.unexpected_token(ExpectedToken::Description("a constant"))?,
+        })
     }
 
     fn parse_option_name_part(&mut self) -> Result<ast::OptionNamePart, ()> {
@@ -973,7 +1086,7 @@ impl<'a> Parser<'a> {
                 let end = self.expect_eq(Token::RightParen)?;
                 Ok(ast::OptionNamePart::Extension(name, join_span(start, end)))
             }
-            _ => self.unexpected_token("an identifier or '('"),
+            _ => self.unexpected_token([ExpectedToken::Ident, ExpectedToken::LEFT_PAREN]),
         }
     }
 
@@ -1012,7 +1125,7 @@ impl<'a> Parser<'a> {
                 Some(span)
             }
             Some((Token::Ident(_), _)) => None,
-            _ => self.unexpected_token("a type name")?,
+            _ => self.unexpected_token(ExpectedToken::Description("a type name"))?,
         };
 
         let name = self.parse_full_ident(terminators)?;
@@ -1031,9 +1144,11 @@ impl<'a> Parser<'a> {
                 Some((tok, _)) if terminators.iter().any(|e| e.matches(&tok)) => {
                     return Ok(result.into());
                 }
-                _ => self.unexpected_token(fmt_expected(
-                    once(ExpectedToken::Token(Token::Dot)).chain(terminators.iter().cloned()),
-                ))?,
+                _ => self.unexpected_token(
+                    once(ExpectedToken::DOT)
+                        .chain(terminators.iter().cloned())
+                        .collect::<Vec<_>>(),
+                )?,
             }
 
             result.push(self.parse_ident()?);
@@ -1046,7 +1161,7 @@ impl<'a> Parser<'a> {
                 Token::Ident(value) => Some(ast::Ident::new(value, span)),
                 _ => None,
             },
-            "an identifier",
+            ExpectedToken::Ident,
         )
     }
 
@@ -1074,7 +1189,7 @@ impl<'a> Parser<'a> {
                     span,
                 })
             }
-            _ => self.unexpected_token("an integer"),
+            _ => self.unexpected_token(ExpectedToken::Description("an integer")),
         }
     }
 
@@ -1104,7 +1219,7 @@ impl<'a> Parser<'a> {
                     span,
                 })
             }
-            _ => self.unexpected_token("a string literal")?,
+            _ => self.unexpected_token(ExpectedToken::Description("a string literal"))?,
         }?;
 
         while let Some((Token::StringLiteral(value), span)) = self.peek_skip_comments()? {
@@ -1135,17 +1250,17 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn expect_eq(&mut self, t: Token) -> Result<Span, ()> {
+    fn expect_eq(&mut self, t: Token<'static>) -> Result<Span, ()> {
         match self.peek_skip_comments()? {
             Some((tok, _)) if tok == t => Ok(self.bump()),
-            _ => self.unexpected_token(format!("'{}'", t))?,
+            _ => self.unexpected_token(ExpectedToken::Token(t))?,
         }
     }
 
     fn expect<T>(
         &mut self,
         mut f: impl FnMut(Token, Span) -> Option<T>,
-        expected: impl ToString,
+        expected: impl Into<Expected>,
     ) -> Result<T, ()> {
         if let Some((tok, span)) = self.peek_skip_comments()? {
             if let Some(value) = f(tok, span) {
@@ -1272,11 +1387,11 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn unexpected_token<T>(&mut self, expected: impl ToString) -> Result<T, ()> {
+    fn unexpected_token<T>(&mut self, expected: impl Into<Expected>) -> Result<T, ()> {
         match self.peek_skip_comments()? {
             Some((found, span)) => {
                 self.add_error(ParseErrorKind::UnexpectedToken {
-                    expected: expected.to_string(),
+                    expected: expected.into(),
                     found: found.to_string(),
                     span,
                 });
@@ -1289,9 +1404,9 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn eof(&mut self, expected: impl ToString) {
+    fn eof(&mut self, expected: impl Into<Expected>) {
         self.add_error(ParseErrorKind::UnexpectedEof {
-            expected: expected.to_string(),
+            expected: expected.into(),
         });
     }
 
@@ -1301,26 +1416,11 @@ impl<'a> Parser<'a> {
 }
 
 impl ExpectedToken {
-    const COMMA: Self = ExpectedToken::Token(Token::Comma);
-    const SEMICOLON: Self = ExpectedToken::Token(Token::Semicolon);
-    const LEFT_BRACE: Self = ExpectedToken::Token(Token::LeftBrace);
-    const LEFT_BRACKET: Self = ExpectedToken::Token(Token::LeftBracket);
-    const RIGHT_PAREN: Self = ExpectedToken::Token(Token::RightParen);
-    const RIGHT_ANGLE_BRACKET: Self = ExpectedToken::Token(Token::RightAngleBracket);
-
     fn matches(&self, t: &Token) -> bool {
         match self {
             ExpectedToken::Token(e) => e == t,
             ExpectedToken::Ident => matches!(t, Token::Ident(_)),
-        }
-    }
-}
-
-impl fmt::Display for ExpectedToken {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ExpectedToken::Token(e) => write!(f, "'{}'", e),
-            ExpectedToken::Ident => write!(f, "an identifier"),
+            ExpectedToken::CommentTerminator | ExpectedToken::Description(_) => false,
         }
     }
 }
@@ -1340,22 +1440,6 @@ impl Statement {
     }
 }
 
-fn fmt_expected(ts: impl Iterator<Item = ExpectedToken>) -> String {
-    let ts: Vec<_> = ts.collect();
-
-    let mut s = String::with_capacity(32);
-    write!(s, "{}", ts[0]).unwrap();
-    if ts.len() > 1 {
-        for t in &ts[1..][..ts.len() - 2] {
-            s.push_str(", ");
-            write!(s, "{}", t).unwrap();
-        }
-        s.push_str(" or ");
-        write!(s, "{}", ts[ts.len() - 1]).unwrap();
-    }
-    s
-}
-
 fn is_valid_import(s: &str) -> bool {
     for component in s.split('/') {
         if component.is_empty() || component.contains('\\') || component == "." || component == ".."
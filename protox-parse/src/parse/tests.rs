@@ -45,6 +45,10 @@ pub fn parse_option() {
     case!(parse_option("option foo = -'a';"));
     case!(parse_option("option foo = { } }"));
     case!(parse_option("option foo = {"));
+    case!(parse_option("option foo = [FOO, -1, 'bar', { x: 1 }];"));
+    case!(parse_option("option foo = [];"));
+    case!(parse_option("option foo = [1 2];"));
+    case!(parse_option("option foo = [[1]];"));
 }
 
 #[test]
@@ -158,6 +162,12 @@ fn parse_service() {
             option foo = bar;
         }"
     ));
+    case!(parse_service(
+        "service Foo { rpc bar(stream) returns (stream stream); }"
+    ));
+    case!(parse_service(
+        "service Foo { rpc bar(stream stream) returns (.pkg.stream); }"
+    ));
 }
 
 #[test]
@@ -260,6 +270,8 @@ pub fn parse_field() {
     case!(parse_field("double double = 1 [default = -nan];"));
     case!(parse_field("optional int32 name = 5 [(ext) = \"foo\"];"));
     case!(parse_field("{"));
+    case!(parse_field("Stream stream_field = 1;"));
+    case!(parse_field("optional stream.Foo stream = 1;"));
 }
 
 #[test]
@@ -320,6 +332,8 @@ pub fn parse_message() {
     case!(parse_message("message Foo { reserved 'a' }"));
     case!(parse_message("message Foo { extensions 5 to }"));
     case!(parse_message("message Foo { optional .a.b, }"));
+    case!(parse_message("message Stream {}"));
+    case!(parse_message("message stream { stream stream = 1; }"));
 }
 
 #[test]
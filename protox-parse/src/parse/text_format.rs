@@ -0,0 +1,223 @@
+use logos::Span;
+
+use super::Parser;
+use crate::{
+    ast::{self, text_format},
+    error::{ExpectedToken, ParseErrorKind},
+    join_span,
+    lex::{EqFloat, Token},
+};
+
+pub(crate) fn parse_text_format(source: &str) -> Result<text_format::Message, Vec<ParseErrorKind>> {
+    let mut parser = Parser::new(source);
+    parser.lexer.extras.text_format_mode = true;
+    match parser.parse_text_format_fields(None) {
+        Ok(fields) if parser.lexer.extras.errors.is_empty() => Ok(text_format::Message {
+            fields,
+            span: 0..source.len(),
+        }),
+        _ => Err(parser.lexer.extras.errors),
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Parses fields until the given terminator, or the end of the file if `terminator` is `None`. The terminator
+    /// is not consumed.
+    fn parse_text_format_fields(
+        &mut self,
+        terminator: Option<Token<'static>>,
+    ) -> Result<Vec<text_format::Field>, ()> {
+        let mut fields = Vec::new();
+        loop {
+            match (self.peek_skip_comments()?, &terminator) {
+                (None, None) => break,
+                (Some((tok, _)), Some(terminator)) if tok == *terminator => break,
+                (None, Some(terminator)) => {
+                    self.unexpected_token(ExpectedToken::Token(terminator.clone()))?
+                }
+                _ => fields.push(self.parse_text_format_field()?),
+            }
+        }
+        Ok(fields)
+    }
+
+    fn parse_text_format_field(&mut self) -> Result<text_format::Field, ()> {
+        let name = self.parse_text_format_field_name()?;
+
+        let has_colon = match self.peek_skip_comments()? {
+            Some((Token::Colon, _)) => {
+                self.bump();
+                true
+            }
+            _ => false,
+        };
+
+        let value = match self.peek_skip_comments()? {
+            Some((Token::LeftBrace | Token::LeftAngleBracket, _)) => {
+                text_format::FieldValue::Message(self.parse_text_format_message_value()?)
+            }
+            Some((Token::LeftBracket, start)) if has_colon => {
+                self.bump();
+                match self.peek_skip_comments()? {
+                    Some((Token::LeftBrace | Token::LeftAngleBracket, _)) => {
+                        let (values, end) =
+                            self.parse_text_format_list(Self::parse_text_format_message_value)?;
+                        text_format::FieldValue::MessageList(values, join_span(start, end))
+                    }
+                    _ => {
+                        let (values, end) =
+                            self.parse_text_format_list(Self::parse_text_format_scalar)?;
+                        text_format::FieldValue::ScalarList(values, join_span(start, end))
+                    }
+                }
+            }
+            _ if has_colon => text_format::FieldValue::Scalar(self.parse_text_format_scalar()?),
+            _ => self.unexpected_token([
+                ExpectedToken::COLON,
+                ExpectedToken::LEFT_BRACE,
+                ExpectedToken::LEFT_ANGLE_BRACKET,
+            ])?,
+        };
+
+        let mut span = join_span(name.span(), value.span());
+        if let Some((Token::Comma | Token::Semicolon, end)) = self.peek_skip_comments()? {
+            self.bump();
+            span = join_span(span, end);
+        }
+
+        Ok(text_format::Field { name, value, span })
+    }
+
+    fn parse_text_format_field_name(&mut self) -> Result<text_format::FieldName, ()> {
+        match self.peek_skip_comments()? {
+            Some((Token::Ident(_), _)) => Ok(text_format::FieldName::Ident(self.parse_ident()?)),
+            Some((Token::LeftBracket, start)) => {
+                self.bump();
+
+                let terminators = [
+                    ExpectedToken::Token(Token::ForwardSlash),
+                    ExpectedToken::Token(Token::RightBracket),
+                ];
+                let mut name = self.parse_full_ident(&terminators)?;
+                let mut domain = None;
+                while let Some((Token::ForwardSlash, _)) = self.peek_skip_comments()? {
+                    self.bump();
+                    let prefix = domain.get_or_insert_with(String::new);
+                    if !prefix.is_empty() {
+                        prefix.push('/');
+                    }
+                    prefix.push_str(&name.to_string());
+                    name = self.parse_full_ident(&terminators)?;
+                }
+
+                let end = self.expect_eq(Token::RightBracket)?;
+                let span = join_span(start, end);
+                Ok(match domain {
+                    Some(domain) => text_format::FieldName::Any {
+                        domain,
+                        type_name: name,
+                        span,
+                    },
+                    None => text_format::FieldName::Extension(name, span),
+                })
+            }
+            _ => self.unexpected_token(ExpectedToken::Description("a field name")),
+        }
+    }
+
+    fn parse_text_format_message_value(&mut self) -> Result<text_format::Message, ()> {
+        let (start, terminator) = match self.peek_skip_comments()? {
+            Some((Token::LeftBrace, span)) => (span, Token::RightBrace),
+            Some((Token::LeftAngleBracket, span)) => (span, Token::RightAngleBracket),
+            _ => self
+                .unexpected_token([ExpectedToken::LEFT_BRACE, ExpectedToken::LEFT_ANGLE_BRACKET])?,
+        };
+        self.bump();
+
+        let fields = self.parse_text_format_fields(Some(terminator.clone()))?;
+        let end = self.expect_eq(terminator)?;
+
+        Ok(text_format::Message {
+            fields,
+            span: join_span(start, end),
+        })
+    }
+
+    /// Parses the items of a list, after the opening `[`, returning the span of the closing `]`.
+    fn parse_text_format_list<T>(
+        &mut self,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T, ()>,
+    ) -> Result<(Vec<T>, Span), ()> {
+        let mut values = Vec::new();
+        if let Some((Token::RightBracket, end)) = self.peek_skip_comments()? {
+            self.bump();
+            return Ok((values, end));
+        }
+
+        loop {
+            values.push(parse_item(self)?);
+            match self.peek_skip_comments()? {
+                Some((Token::Comma, _)) => {
+                    self.bump();
+                }
+                Some((Token::RightBracket, end)) => {
+                    self.bump();
+                    return Ok((values, end));
+                }
+                _ => self.unexpected_token([ExpectedToken::COMMA, ExpectedToken::RIGHT_BRACKET])?,
+            }
+        }
+    }
+
+    fn parse_text_format_scalar(&mut self) -> Result<text_format::Scalar, ()> {
+        match self.peek_skip_comments()? {
+            Some((Token::Minus, start)) => {
+                self.bump();
+                match self.peek_skip_comments()? {
+                    Some((Token::Ident(_), end)) => Ok(text_format::Scalar::Ident {
+                        negative: true,
+                        ident: self.parse_ident()?,
+                        span: join_span(start, end),
+                    }),
+                    Some((Token::IntLiteral(value), end)) => {
+                        self.bump();
+                        Ok(text_format::Scalar::Int(ast::Int {
+                            negative: true,
+                            value,
+                            span: join_span(start, end),
+                        }))
+                    }
+                    Some((Token::FloatLiteral(EqFloat(value)), end)) => {
+                        self.bump();
+                        Ok(text_format::Scalar::Float(ast::Float {
+                            value: -value,
+                            span: join_span(start, end),
+                        }))
+                    }
+                    _ => self.unexpected_token(ExpectedToken::Description("a numeric literal")),
+                }
+            }
+            Some((Token::Ident(_), span)) => Ok(text_format::Scalar::Ident {
+                negative: false,
+                ident: self.parse_ident()?,
+                span,
+            }),
+            Some((Token::IntLiteral(value), span)) => {
+                self.bump();
+                Ok(text_format::Scalar::Int(ast::Int {
+                    negative: false,
+                    value,
+                    span,
+                }))
+            }
+            Some((Token::FloatLiteral(EqFloat(value)), span)) => {
+                self.bump();
+                Ok(text_format::Scalar::Float(ast::Float { value, span }))
+            }
+            Some((Token::StringLiteral(_), _)) => {
+                Ok(text_format::Scalar::String(self.parse_string()?))
+            }
+            _ => self.unexpected_token(ExpectedToken::Description("a value")),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::{iter, ops::Range};
+
+use crate::error::{ExpectedToken, ParseError, ParseErrorKind};
+
+/// A change to the source code which may fix a syntax error.
+///
+/// Repairs never change the byte offsets of existing text, so spans in the repaired source can be reported against
+/// the original.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Repair {
+    /// Append a missing closing token at the end of the file, on a new line in case the file ends with a comment.
+    Append(&'static str),
+    /// Insert a missing token before the unexpected token, replacing preceding whitespace.
+    Insert(char, Range<usize>),
+    /// Replace an unexpected token with whitespace.
+    Skip(Range<usize>),
+}
+
+impl Repair {
+    pub(crate) fn for_error(kind: &ParseErrorKind) -> Option<Self> {
+        match kind {
+            ParseErrorKind::UnexpectedEof { expected } => {
+                if expected.last() == Some(&ExpectedToken::RIGHT_BRACE) {
+                    Some(Repair::Append("\n}"))
+                } else if expected.first() == Some(&ExpectedToken::SEMICOLON) {
+                    Some(Repair::Append("\n;"))
+                } else if expected.first() == Some(&ExpectedToken::CommentTerminator) {
+                    Some(Repair::Append("*/"))
+                } else {
+                    None
+                }
+            }
+            ParseErrorKind::UnexpectedToken { expected, span, .. } => {
+                if expected.0 == [ExpectedToken::EQUALS] {
+                    Some(Repair::Insert('=', span.clone()))
+                } else if expected.first() == Some(&ExpectedToken::SEMICOLON) {
+                    Some(Repair::Insert(';', span.clone()))
+                } else {
+                    Some(Repair::Skip(span.clone()))
+                }
+            }
+            ParseErrorKind::InvalidToken { span } => Some(Repair::Skip(span.clone())),
+            _ => None,
+        }
+    }
+
+    fn apply(self, source: &mut String) {
+        match self {
+            Repair::Append(token) => source.push_str(token),
+            Repair::Insert(ch, span) => match source[..span.start].chars().next_back() {
+                Some(prev) if prev == ' ' || prev == '\t' => {
+                    let start = span.start - prev.len_utf8();
+                    source.replace_range(start..span.start, ch.encode_utf8(&mut [0; 4]));
+                }
+                _ => Repair::Skip(span).apply(source),
+            },
+            Repair::Skip(span) => {
+                let mut blank = String::with_capacity(span.len());
+                for ch in source[span.clone()].chars() {
+                    match ch {
+                        '\n' | '\r' => blank.push(ch),
+                        _ => blank.extend(iter::repeat(' ').take(ch.len_utf8())),
+                    }
+                }
+                source.replace_range(span, &blank);
+            }
+        }
+    }
+}
+
+/// Attempts to repair the primary error in `err`, returning `true` if `source` was changed.
+pub(crate) fn repair(err: &ParseError, source: &mut String) -> bool {
+    match Repair::for_error(err.kind()) {
+        Some(repair) => {
+            repair.apply(source);
+            true
+        }
+        None => false,
+    }
+}
@@ -1,12 +1,25 @@
 use insta::assert_debug_snapshot;
+use miette::{Diagnostic, Severity};
 use prost_types::FileDescriptorProto;
 
-use crate::error::ParseErrorKind::{self, *};
+use crate::error::{
+    ExpectedToken,
+    ParseErrorKind::{self, *},
+    WarningKind,
+};
 
 fn parse(source: &str) -> Result<FileDescriptorProto, Vec<ParseErrorKind>> {
     crate::parse("test.proto", source).map_err(|err| err.into_inner())
 }
 
+fn parse_with_lists(source: &str) -> Result<FileDescriptorProto, Vec<ParseErrorKind>> {
+    let mut options = crate::ParseOptions::new();
+    options.allow_list_option_values(true);
+    crate::parse_with_options("test.proto", source, &options)
+        .map(|(file, _)| file)
+        .map_err(|err| err.into_inner())
+}
+
 #[test]
 fn error_fmt_debug() {
     let error = crate::parse("foo.proto", "message {}").unwrap_err();
@@ -142,17 +155,52 @@ fn invalid_message_number() {
 
 #[test]
 fn proto3_default_value() {
-    assert_eq!(
-        parse(
-            r#"
+    let source = r#"
             syntax = 'proto3';
 
             message Message {
                 optional int32 foo = 1 [default = -0];
-            }"#
-        ),
+            }"#;
+    assert_eq!(
+        parse(source),
         Err(vec![Proto3DefaultValue { span: 103..115 }]),
     );
+
+    let mut options = crate::ParseOptions::new();
+    options.allow_proto3_default_values(true);
+    let (file, warnings) = crate::parse_with_options("test.proto", source, &options).unwrap();
+
+    assert_eq!(file.message_type[0].field[0].default_value, None);
+    assert_eq!(warnings[0].severity(), Some(Severity::Warning));
+    assert_eq!(
+        warnings
+            .into_iter()
+            .map(|w| w.into_inner())
+            .collect::<Vec<_>>(),
+        vec![WarningKind::Proto3DefaultValue { span: 103..115 }],
+    );
+}
+
+#[test]
+fn stream_method_type() {
+    let (file, warnings) = crate::parse_with_warnings(
+        "test.proto",
+        "service Foo { rpc Bar(stream) returns (stream stream); rpc Baz(.stream) returns (stream); }",
+    )
+    .unwrap();
+
+    assert_eq!(file.service[0].method[0].input_type(), "stream");
+    assert_eq!(file.service[0].method[0].output_type(), "stream");
+    assert_eq!(
+        warnings
+            .into_iter()
+            .map(|w| w.into_inner())
+            .collect::<Vec<_>>(),
+        vec![
+            WarningKind::StreamMethodType { span: 22..28 },
+            WarningKind::StreamMethodType { span: 81..87 },
+        ],
+    );
 }
 
 #[test]
@@ -256,6 +304,18 @@ fn proto3_group_field() {
         ),
         Err(vec![Proto3GroupField { span: 79..104 }]),
     );
+    assert_eq!(
+        parse(
+            r#"
+            syntax = 'proto3';
+
+            extend Message {
+                optional group Foo = 1 {};
+            }
+            "#
+        ),
+        Err(vec![Proto3ExtendGroupField { span: 87..92 }]),
+    );
 }
 
 #[test]
@@ -306,6 +366,23 @@ fn oneof_field_with_label() {
         ),
         Err(vec![OneofFieldWithLabel { span: 111..119 }]),
     );
+    assert_eq!(
+        parse(
+            r#"
+            syntax = 'proto3';
+
+            message Message {
+                oneof foo {
+                    repeated int32 bar = 1;
+                }
+            }
+            "#
+        ),
+        Err(vec![RepeatedFieldInOneof {
+            field_name: "bar".to_owned(),
+            span: 111..119,
+        }]),
+    );
 }
 
 #[test]
@@ -710,6 +787,23 @@ fn field_default_invalid_type() {
     );
 }
 
+#[test]
+fn bytes_default_value() {
+    let default_value = |value: &str| {
+        let file = parse(&format!(
+            "message Message {{ optional bytes foo = 1 [default = {}]; }}",
+            value
+        ))
+        .unwrap();
+        file.message_type[0].field[0].default_value().to_owned()
+    };
+
+    // Escapes are decoded to raw bytes, then C-escaped in the descriptor as protoc does.
+    assert_eq!(default_value(r#""\x00\x01""#), r"\000\001");
+    assert_eq!(default_value(r#""\xff" '\0a'"#), r"\377\000a");
+    assert_eq!(default_value(r#""é\n\"""#), r#"\303\251\n\""#);
+}
+
 #[test]
 fn syntax() {
     assert_debug_snapshot!(parse(""));
@@ -752,6 +846,43 @@ fn options() {
     assert_debug_snapshot!(parse("option opt = 1.1;"));
     assert_debug_snapshot!(parse("option opt = 'foo';"));
     assert_debug_snapshot!(parse("option opt = { foo: 1 };"));
+    assert_debug_snapshot!(parse_with_lists("option opt = [FOO, BAR];"));
+    assert_eq!(
+        parse_with_lists("option opt = [-ident];"),
+        Err(vec![NegativeIdentOutsideDefault { span: 14..20 }]),
+    );
+    assert_eq!(
+        parse_with_lists("option opt = [];"),
+        Err(vec![EmptyOptionList { span: 13..15 }]),
+    );
+    assert_eq!(
+        parse_with_lists("message Foo { optional int32 a = 1 [bogus = []]; }"),
+        Err(vec![EmptyOptionList { span: 44..46 }]),
+    );
+    assert_eq!(
+        parse("option opt = [FOO, BAR];"),
+        Err(vec![ListOptionValue { span: 13..23 }]),
+    );
+    assert_eq!(
+        parse("message Foo { optional int32 a = 1 [opt = [1], opt2 = []]; }"),
+        Err(vec![
+            ListOptionValue { span: 42..45 },
+            ListOptionValue { span: 54..56 },
+        ]),
+    );
+
+    let mut options = crate::ParseOptions::new();
+    options.allow_list_option_values(true);
+    let (_, warnings) =
+        crate::parse_with_options("test.proto", "option opt = [1];", &options).unwrap();
+    assert!(warnings[0].is_list_option_value());
+    assert_eq!(
+        warnings
+            .into_iter()
+            .map(|w| w.into_inner())
+            .collect::<Vec<_>>(),
+        vec![WarningKind::ListOptionValue { span: 13..16 }],
+    );
     assert_eq!(
         parse("option opt = -ident;"),
         Err(vec![NegativeIdentOutsideDefault { span: 13..19 }]),
@@ -766,4 +897,454 @@ fn options() {
             max: "18446744073709551615".to_owned(),
         }]),
     );
+
+    let double_value = |source: &str| {
+        parse(source).unwrap().options.unwrap().uninterpreted_option[0]
+            .double_value
+            .unwrap()
+    };
+    assert_eq!(double_value("option opt = -inf;"), f64::NEG_INFINITY);
+    assert!(double_value("option opt = -nan;").is_nan());
+}
+
+#[test]
+fn float_default_special_values() {
+    let default_values = |source: &str| {
+        parse(source).unwrap().message_type[0]
+            .field
+            .iter()
+            .map(|field| field.default_value().to_owned())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        default_values(
+            r#"
+            message Message {
+                optional double a = 1 [default = inf];
+                optional double b = 2 [default = -inf];
+                optional float c = 3 [default = nan];
+                optional float d = 4 [default = -nan];
+            }"#
+        ),
+        ["inf", "-inf", "nan", "-nan"],
+    );
+}
+
+#[test]
+fn leading_file_comment() {
+    let leading_file_comment =
+        |source: &str| crate::leading_file_comment(&crate::parse("test.proto", source).unwrap());
+
+    assert_eq!(
+        leading_file_comment("// generated\n\n// detached\n\nsyntax = 'proto3';").as_deref(),
+        Some(" generated\n")
+    );
+    assert_eq!(
+        leading_file_comment("/* generated */\nsyntax = 'proto3';").as_deref(),
+        Some(" generated ")
+    );
+    assert_eq!(
+        leading_file_comment("// generated\n\npackage foo;\nimport 'bar.proto';").as_deref(),
+        Some(" generated\n")
+    );
+    assert_eq!(
+        leading_file_comment("syntax = 'proto3'; // trailing").as_deref(),
+        None
+    );
+    assert_eq!(
+        leading_file_comment("// A message.\nmessage Foo {}").as_deref(),
+        None
+    );
+    assert_eq!(
+        leading_file_comment("// generated\n\n// A message.\nmessage Foo {}").as_deref(),
+        Some(" generated\n")
+    );
+    assert_eq!(
+        leading_file_comment("// An import.\nimport 'bar.proto';").as_deref(),
+        None
+    );
+    assert_eq!(leading_file_comment("").as_deref(), None);
+}
+
+#[test]
+fn text_format() {
+    let roundtrip = |source: &str| {
+        let message = crate::text_format::parse(source).unwrap();
+        let formatted = message.to_string();
+        assert_eq!(
+            crate::text_format::parse(&formatted).unwrap().to_string(),
+            formatted
+        );
+        formatted
+    };
+
+    assert_eq!(roundtrip(""), "");
+    assert_eq!(
+        roundtrip("foo: 1 bar: -2.5f baz: -inf"),
+        "foo: 1 bar: -2.5 baz: -inf"
+    );
+    assert_eq!(roundtrip("foo: 'a' \"b\"\n# comment\n"), "foo: \"ab\"");
+    assert_eq!(roundtrip("foo: '\\n\\x01'"), "foo: \"\\n\\001\"");
+    assert_eq!(
+        roundtrip("foo { bar: 1; }, baz: <>"),
+        "foo { bar: 1 } baz {}"
+    );
+    assert_eq!(roundtrip("foo: [1, 2] bar: []"), "foo: [1, 2] bar: []");
+    assert_eq!(
+        roundtrip("foo: [{ a: 1 }, <b: 2>]"),
+        "foo: [{ a: 1 }, { b: 2 }]"
+    );
+    assert_eq!(roundtrip("[foo.ext]: ENUM"), "[foo.ext]: ENUM");
+    assert_eq!(
+        roundtrip("any { [type.googleapis.com/foo.Bar] { x: 1 } }"),
+        "any { [type.googleapis.com/foo.Bar] { x: 1 } }"
+    );
+    assert_eq!(
+        roundtrip("[example.com/a/b/foo.Bar] {}"),
+        "[example.com/a/b/foo.Bar] {}"
+    );
+
+    assert_debug_snapshot!(crate::text_format::parse("foo: 1 bar { baz: [x, -y] }")
+        .unwrap()
+        .into_ast());
+
+    let err = |source: &str| format!("{:?}", crate::text_format::parse(source).unwrap_err());
+    assert_eq!(
+        err("foo 1"),
+        "<text format>:1:5: expected ':', '{' or '<', but found '1'"
+    );
+    assert_eq!(
+        err("foo: [{}, 1]"),
+        "<text format>:1:11: expected '{' or '<', but found '1'"
+    );
+    assert_eq!(err("foo { bar: 1"), "expected '}', but reached end of file");
+    assert_eq!(
+        err("foo { bar: 1 >"),
+        "<text format>:1:14: expected a field name, but found '>'"
+    );
+    assert_eq!(
+        err("foo: }"),
+        "<text format>:1:6: expected a value, but found '}'"
+    );
+}
+
+#[test]
+fn tokenize() {
+    let tokenize = |source: &'static str| {
+        crate::tokenize(source)
+            .map(|(kind, span)| (kind, &source[span]))
+            .collect::<Vec<_>>()
+    };
+
+    use crate::TokenKind::*;
+    assert_eq!(
+        tokenize("/* block */\noptional int32 foo = -1 [default = 1.5];\n"),
+        [
+            (Comment, "/* block */"),
+            (Keyword, "optional"),
+            (Keyword, "int32"),
+            (Ident, "foo"),
+            (Punctuation, "="),
+            (Punctuation, "-"),
+            (IntLiteral, "1"),
+            (Punctuation, "["),
+            (Ident, "default"),
+            (Punctuation, "="),
+            (FloatLiteral, "1.5"),
+            (Punctuation, "]"),
+            (Punctuation, ";"),
+        ]
+    );
+    assert_eq!(
+        tokenize("option (a.b) = 'str' \"str\"; // trailing\n"),
+        [
+            (Keyword, "option"),
+            (Punctuation, "("),
+            (Ident, "a"),
+            (Punctuation, "."),
+            (Ident, "b"),
+            (Punctuation, ")"),
+            (Punctuation, "="),
+            (StringLiteral, "'str'"),
+            (StringLiteral, "\"str\""),
+            (Punctuation, ";"),
+            (Comment, "// trailing"),
+        ]
+    );
+    assert_eq!(
+        tokenize("message Foo { @ }"),
+        [
+            (Keyword, "message"),
+            (Ident, "Foo"),
+            (Punctuation, "{"),
+            (Invalid, "@"),
+            (Punctuation, "}"),
+        ]
+    );
+}
+
+#[test]
+fn error_line_col() {
+    let source = "// é\nmessage Foo {\n  optional int32 x = 99999999999;\n}";
+    let err = crate::parse("foo.proto", source).unwrap_err();
+    assert_eq!(err.line_col(source), Some((3, 22)));
+    assert_eq!(err.end_line_col(source), Some((3, 33)));
+
+    let source = "message Foo {";
+    let err = crate::parse("foo.proto", source).unwrap_err();
+    assert_eq!(err.line_col(source), None);
+    assert_eq!(err.end_line_col(source), None);
+}
+
+#[test]
+fn error_primary_span() {
+    let err = crate::parse("foo.proto", "message {}").unwrap_err();
+    assert_eq!(err.primary_span(), Some(&(8..9)));
+
+    let err = crate::parse("foo.proto", "package foo;\npackage bar;").unwrap_err();
+    assert_eq!(err.primary_span(), Some(&(0..12)));
+    assert_eq!(
+        format!("{:?}", err),
+        "foo.proto:1:1: multiple package names specified"
+    );
+
+    let err = crate::parse("foo.proto", "message Foo {").unwrap_err();
+    assert_eq!(err.primary_span(), None);
+}
+
+#[test]
+fn parse_with_recovery() {
+    let source = "syntax = 'proto3' message Foo {\n    int32 foo 1;\n    ) int32 bar = 2 }\n)";
+    let (file, errors) = crate::parse_with_recovery("foo.proto", source);
+
+    assert_eq!(file.message_type[0].name(), "Foo");
+    assert_eq!(file.message_type[0].field.len(), 2);
+    assert_eq!(file.message_type[0].field[1].name(), "bar");
+
+    let errors: Vec<_> = errors
+        .into_iter()
+        .map(|err| {
+            assert!(err.is_recoverable());
+            err.into_inner().remove(0)
+        })
+        .collect();
+    assert_eq!(errors.len(), 5);
+    assert_eq!(
+        errors[..4],
+        [
+            ParseErrorKind::Repaired {
+                original: Box::new(ParseErrorKind::UnexpectedToken {
+                    expected: ExpectedToken::SEMICOLON.into(),
+                    found: "message".to_owned(),
+                    span: 18..25,
+                }),
+            },
+            ParseErrorKind::Repaired {
+                original: Box::new(ParseErrorKind::UnexpectedToken {
+                    expected: ExpectedToken::EQUALS.into(),
+                    found: "1".to_owned(),
+                    span: 46..47,
+                }),
+            },
+            ParseErrorKind::Repaired {
+                original: Box::new(ParseErrorKind::UnexpectedToken {
+                    expected: [
+                        ExpectedToken::Description("a message field"),
+                        ExpectedToken::Description("oneof"),
+                        ExpectedToken::Description("reserved range"),
+                        ExpectedToken::Description("enum"),
+                        ExpectedToken::Description("message"),
+                        ExpectedToken::Description("option"),
+                        ExpectedToken::RIGHT_BRACE,
+                    ]
+                    .into(),
+                    found: ")".to_owned(),
+                    span: 53..54,
+                }),
+            },
+            ParseErrorKind::Repaired {
+                original: Box::new(ParseErrorKind::UnexpectedToken {
+                    expected: [ExpectedToken::SEMICOLON, ExpectedToken::LEFT_BRACKET].into(),
+                    found: "}".to_owned(),
+                    span: 69..70,
+                }),
+            },
+        ]
+    );
+
+    let (file, errors) = crate::parse_with_recovery(
+        "foo.proto",
+        "message Foo {}\nmessage Bar {\n    /* unterminated",
+    );
+    assert_eq!(file.message_type.len(), 2);
+    assert_eq!(
+        errors
+            .into_iter()
+            .map(|err| err.into_inner().remove(0))
+            .collect::<Vec<_>>(),
+        [
+            ParseErrorKind::Repaired {
+                original: Box::new(ParseErrorKind::UnexpectedEof {
+                    expected: ExpectedToken::CommentTerminator.into(),
+                }),
+            },
+            ParseErrorKind::Repaired {
+                original: Box::new(ParseErrorKind::UnexpectedEof {
+                    expected: [
+                        ExpectedToken::Description("a message field"),
+                        ExpectedToken::Description("oneof"),
+                        ExpectedToken::Description("reserved range"),
+                        ExpectedToken::Description("enum"),
+                        ExpectedToken::Description("message"),
+                        ExpectedToken::Description("option"),
+                        ExpectedToken::RIGHT_BRACE,
+                    ]
+                    .into(),
+                }),
+            },
+        ]
+    );
+
+    let (file, errors) =
+        crate::parse_with_recovery("foo.proto", "import 'foo.proto; message Foo {}");
+    assert!(file.message_type.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert!(!errors[0].is_recoverable());
+
+    let (file, errors) = crate::parse_with_recovery(
+        "foo.proto",
+        "message Foo {\n optional int32 a = 1;\n // trailing",
+    );
+    assert_eq!(file.message_type[0].field.len(), 1);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].is_recoverable());
+
+    let (file, errors) =
+        crate::parse_with_recovery("foo.proto", "message Foo { optional int32 a = 1 }");
+    assert_eq!(file.message_type[0].field.len(), 1);
+    assert_eq!(errors.len(), 1);
+
+    let (file, errors) = crate::parse_with_recovery("foo.proto", "message Foo { message Bar {");
+    assert_eq!(file.message_type[0].nested_type.len(), 1);
+    assert_eq!(errors.len(), 2);
+
+    let (file, errors) = crate::parse_with_recovery("foo.proto", "message Foo { optional }");
+    assert!(file.message_type.is_empty());
+    assert!(errors.len() < 3);
+    assert!(!errors.last().unwrap().is_recoverable());
+}
+
+#[test]
+fn fingerprint() {
+    let fingerprint = |source: &str| crate::fingerprint(&parse_with_lists(source).unwrap());
+    assert_eq!(
+        fingerprint("option java_package = 'foo'; option (ext) = 1;"),
+        fingerprint("// comment\noption (ext) = 1;\n\noption java_package = 'foo';"),
+    );
+    assert_ne!(
+        fingerprint("option (ext) = [1, 2];"),
+        fingerprint("option (ext) = [2, 1];"),
+    );
+    assert_ne!(fingerprint("message Foo {}"), fingerprint("message Bar {}"),);
+}
+
+#[test]
+fn synthetic_oneof_location() {
+    let file = parse(
+        "syntax = 'proto3';\nmessage Foo {\n  oneof a { int32 b = 1; }\n  optional int32 c = 2;\n}",
+    )
+    .unwrap();
+    let locations = &file.source_code_info.unwrap().location;
+
+    let field = locations.iter().find(|l| l.path == [4, 0, 2, 1]).unwrap();
+    let oneof = locations.iter().find(|l| l.path == [4, 0, 8, 1]).unwrap();
+    assert_eq!(oneof.span, field.span);
+    assert_eq!(oneof.span, [3, 2, 23]);
+}
+
+#[test]
+fn visit_option_values() {
+    use crate::ast::{self, Visitor};
+
+    #[derive(Default)]
+    struct OptionVisitor {
+        values: Vec<std::string::String>,
+        text_format_fields: Vec<std::string::String>,
+    }
+
+    impl Visitor for OptionVisitor {
+        fn visit_option_value(&mut self, value: &ast::OptionValue) {
+            self.values.push(value.to_string());
+            ast::walk_option_value(self, value);
+        }
+
+        fn visit_text_format_field(&mut self, field: &ast::text_format::Field) {
+            self.text_format_fields.push(field.name.to_string());
+            ast::walk_text_format_field(self, field);
+        }
+    }
+
+    let file = crate::parse_ast(
+        "test.proto",
+        r#"
+        option a = 1;
+        message Foo {
+            optional group Bar = 1 [b = 2] {
+                optional int32 c = 1 [c = { x: 1 y: [{ z: 2 }] }];
+            }
+            oneof d {
+                option d = 3;
+                int32 e = 2;
+            }
+        }
+        enum Baz { option f = [4, 5]; ZERO = 0 [g = 6]; }
+        service Svc { rpc Method(Foo) returns (Foo) { option h = 7; } }
+        "#,
+    )
+    .unwrap();
+
+    let mut visitor = OptionVisitor::default();
+    visitor.visit_file(&file);
+    assert_eq!(
+        visitor.values,
+        [
+            "1",
+            "2",
+            "x : 1 y : [ { z : 2 } ]",
+            "3",
+            "[4, 5]",
+            "4",
+            "5",
+            "6",
+            "7"
+        ]
+    );
+    assert_eq!(visitor.text_format_fields, ["x", "y", "z"]);
+}
+
+#[test]
+fn parse_option_value() {
+    let value = |source: &str| crate::parse_option_value(source).map(|value| value.to_string());
+
+    assert_eq!(value("\"hello\"").unwrap(), "hello");
+    assert_eq!(value(" true // comment").unwrap(), "true");
+    assert_eq!(value("-inf").unwrap(), "-inf");
+    assert_eq!(value("[1, 2.5]").unwrap(), "[1, 2.5]");
+
+    let err = crate::parse_option_value("").unwrap_err();
+    assert_eq!(err.file(), "<option value>");
+    assert_eq!(
+        err.into_inner(),
+        [UnexpectedEof {
+            expected: ExpectedToken::Description("a constant").into(),
+        }]
+    );
+    assert_eq!(
+        crate::parse_option_value("foo;").unwrap_err().into_inner(),
+        [UnexpectedToken {
+            expected: ExpectedToken::Description("end of value").into(),
+            found: ";".to_owned(),
+            span: 3..4,
+        }]
+    );
 }
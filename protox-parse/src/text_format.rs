@@ -0,0 +1,62 @@
+//! Parsing of messages in the protobuf text format.
+//!
+//! Text format messages are parsed without a message definition, so field names and values are not checked. To
+//! decode a message against its definition, see [`prost_reflect::DynamicMessage::parse_text_format`](https://docs.rs/prost-reflect/latest/prost_reflect/struct.DynamicMessage.html#method.parse_text_format).
+
+use std::fmt;
+
+use crate::{ast::text_format::Message, error::ParseErrorKind, ParseError, MAX_FILE_LEN};
+
+const SOURCE_NAME: &str = "<text format>";
+
+/// A message parsed from the protobuf text format, returned by [`parse()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextFormatMessage {
+    message: Message,
+}
+
+/// Parses a message in the protobuf text format, such as `foo: 1 bar { baz: "quz" }`.
+///
+/// # Examples
+///
+/// ```
+/// # use protox_parse::text_format;
+/// let message = text_format::parse("foo: 1, bar < baz: 'quz' >").unwrap();
+/// assert_eq!(message.as_ast().fields.len(), 2);
+/// assert_eq!(message.to_string(), "foo: 1 bar { baz: \"quz\" }");
+///
+/// let err = text_format::parse("foo: ").unwrap_err();
+/// assert_eq!(err.to_string(), "expected a value, but reached end of file");
+/// ```
+pub fn parse(source: &str) -> Result<TextFormatMessage, ParseError> {
+    if source.len() > MAX_FILE_LEN {
+        return Err(ParseError::new(
+            vec![ParseErrorKind::FileTooLarge],
+            SOURCE_NAME,
+            String::default(),
+        ));
+    }
+
+    crate::parse::parse_text_format(source)
+        .map(|message| TextFormatMessage { message })
+        .map_err(|errors| ParseError::new(errors, SOURCE_NAME, source.to_owned()))
+}
+
+impl TextFormatMessage {
+    /// Gets a reference to the syntax tree of the message.
+    pub fn as_ast(&self) -> &Message {
+        &self.message
+    }
+
+    /// Converts this message into its syntax tree.
+    pub fn into_ast(self) -> Message {
+        self.message
+    }
+}
+
+impl fmt::Display for TextFormatMessage {
+    /// Formats the message in the text format on a single line, such that it can be parsed again with [`parse()`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
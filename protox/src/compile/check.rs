@@ -0,0 +1,1680 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use miette::NamedSource;
+use prost_reflect::{
+    Cardinality, DescriptorError, DescriptorPool, ExtensionDescriptor, FieldDescriptor,
+    FileDescriptor, Kind, MessageDescriptor,
+};
+use prost_types::{
+    field_descriptor_proto::Label, DescriptorProto, EnumDescriptorProto, FieldDescriptorProto,
+    FileDescriptorProto, UninterpretedOption,
+};
+use protox_parse::ast::text_format;
+
+use crate::{
+    error::{import_span, location_span, Error, ErrorKind, FirstDefinition},
+    inversion_list::InversionList,
+    print::to_json_name,
+};
+
+/// Returns an error if `file` was parsed from source without a `syntax` statement.
+///
+/// An explicit `syntax = "proto2";` statement is not recorded in the descriptor, so this uses the source code info
+/// generated by the parser instead.
+pub(super) fn check_syntax_statement(file: &FileDescriptorProto) -> Result<(), Error> {
+    const SYNTAX_TAG: i32 = 12;
+
+    let has_syntax_statement = file.syntax.is_some()
+        || file.source_code_info.as_ref().is_some_and(|info| {
+            info.location
+                .iter()
+                .any(|location| location.path == [SYNTAX_TAG])
+        });
+    if has_syntax_statement {
+        Ok(())
+    } else {
+        Err(Error::from_kind(ErrorKind::MissingSyntaxDeclaration {
+            name: file.name().to_owned(),
+        }))
+    }
+}
+
+/// Returns an error if `file` does not have a package.
+pub(super) fn check_package_declaration(file: &FileDescriptorProto) -> Result<(), Error> {
+    if file.package().is_empty() {
+        Err(Error::from_kind(ErrorKind::MissingPackageDeclaration {
+            name: file.name().to_owned(),
+        }))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns an error if any non-public import of `file` does not define a symbol referenced by it.
+///
+/// This runs before `file` is added to the pool, so its imports must already be in `pool`. If a name cannot be
+/// resolved, no error is returned, and adding the file to the pool reports it instead. The options of files decoded
+/// from a descriptor set are already interpreted, so any import which extends an options message is assumed to be
+/// used by them.
+pub(super) fn check_unused_imports(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+    has_interpreted_options: bool,
+) -> Result<(), Error> {
+    let resolver = NameResolver::new(pool, file);
+    let mut used = UsedFiles::default();
+    let mut resolved = true;
+    let mut add = |scope: &str, name: Option<&str>| {
+        if let Some(name) = name {
+            match resolver.resolve(scope, name) {
+                Some((_, Some(file))) => {
+                    used.files.insert(file.name().to_owned());
+                }
+                Some((_, None)) => (),
+                None => resolved = false,
+            }
+        }
+    };
+
+    let _ = visit_messages(file, &mut |name, _, message| {
+        for field in message.field.iter().chain(&message.extension) {
+            add(name, field.type_name.as_deref());
+            add(name, field.extendee.as_deref());
+        }
+        Ok(())
+    });
+    for extension in &file.extension {
+        add(file.package(), extension.type_name.as_deref());
+        add(file.package(), extension.extendee.as_deref());
+    }
+    for service in &file.service {
+        let scope = join_name(file.package(), service.name());
+        for method in &service.method {
+            add(&scope, method.input_type.as_deref());
+            add(&scope, method.output_type.as_deref());
+        }
+    }
+    visit_options(file, &mut |_, scope, _, options| {
+        for option in options {
+            for part in option.name.iter().filter(|part| part.is_extension) {
+                add(scope, Some(&part.name_part));
+            }
+        }
+    });
+    if !resolved {
+        return Ok(());
+    }
+
+    let mut errors = Vec::new();
+    for (index, dependency) in file.dependency.iter().enumerate() {
+        if file.public_dependency.contains(&(index as i32)) {
+            continue;
+        }
+        let Some(dependency) = pool.get_file_by_name(dependency) else {
+            continue;
+        };
+
+        let is_used = used.is_used(&dependency)
+            || (has_interpreted_options && UsedFiles::extends_options(&dependency));
+        if !is_used {
+            errors.push(ErrorKind::UnusedImport {
+                span: source.and_then(|source| import_span(file, source, index)),
+                source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+                name: dependency.name().to_owned(),
+                related: Vec::new(),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        let mut first = errors.remove(0);
+        if let ErrorKind::UnusedImport { related, .. } = &mut first {
+            *related = errors;
+        }
+        Err(Error::from_kind(first))
+    }
+}
+
+/// Resolves names referenced by a file which has not been added to the pool yet, such as field types.
+///
+/// As in [`resolve_extension`], a relative name is looked up in each enclosing scope in turn, starting with the
+/// innermost.
+struct NameResolver<'a> {
+    pool: &'a DescriptorPool,
+    /// The full names of all messages, enums, services and extensions in the file.
+    definitions: HashSet<String>,
+    /// The full names of all synthetic map entry messages in the file.
+    map_entries: HashSet<String>,
+}
+
+impl<'a> NameResolver<'a> {
+    fn new(pool: &'a DescriptorPool, file: &FileDescriptorProto) -> Self {
+        let mut definitions = HashSet::new();
+        let _ = visit_definitions(file, &mut |name, _| {
+            definitions.insert(name);
+            Ok(())
+        });
+        let mut map_entries = HashSet::new();
+        let _ = visit_messages(file, &mut |name, _, message| {
+            definitions.extend(
+                message
+                    .extension
+                    .iter()
+                    .map(|extension| join_name(name, extension.name())),
+            );
+            if message.options.as_ref().is_some_and(|o| o.map_entry()) {
+                map_entries.insert(name.to_owned());
+            }
+            Ok(())
+        });
+        NameResolver {
+            pool,
+            definitions,
+            map_entries,
+        }
+    }
+
+    /// Returns `true` if `full_name` is a synthetic map entry message, in the file or the pool.
+    fn is_map_entry(&self, full_name: &str) -> bool {
+        self.map_entries.contains(full_name)
+            || self
+                .pool
+                .get_message_by_name(full_name)
+                .is_some_and(|message| message.is_map_entry())
+    }
+
+    /// Gets the full name of the definition `name` referenced from `scope`, and the file in the pool which defines it,
+    /// or `None` if it is defined in the file itself.
+    fn resolve(&self, scope: &str, name: &str) -> Option<(String, Option<FileDescriptor>)> {
+        let lookup = |full_name: String| {
+            if self.definitions.contains(&full_name) {
+                Some((full_name, None))
+            } else {
+                let (file, _) = find_pool_definition(self.pool, &full_name)?;
+                Some((full_name, Some(file)))
+            }
+        };
+
+        if let Some(full_name) = name.strip_prefix('.') {
+            return lookup(full_name.to_owned());
+        }
+
+        let mut scope = scope;
+        loop {
+            if let Some(resolved) = lookup(join_name(scope, name)) {
+                return Some(resolved);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map_or("", |(parent, _)| parent);
+        }
+    }
+}
+
+/// Returns an error if `file` defines a message, enum, service or extension with the same fully-qualified name as a
+/// definition in another file already added to the pool.
+///
+/// The pool would also reject such files, but could not point to the original definition. `sources` gets the source
+/// code of previously added files, if available.
+pub(super) fn check_duplicate_names<'a>(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+    sources: impl Fn(&str) -> Option<&'a str>,
+) -> Result<(), Error> {
+    let check = |name: String, path: &[i32]| -> Result<(), Error> {
+        let Some((first_file, first_path)) = find_pool_definition(pool, &name) else {
+            return Ok(());
+        };
+        if first_file.name() == file.name() {
+            return Ok(());
+        }
+
+        let first = sources(first_file.name()).map(|first_source| FirstDefinition {
+            span: location_span(
+                first_file.file_descriptor_proto(),
+                first_source,
+                &[first_path.as_slice(), &[1]].concat(),
+            ),
+            source_code: NamedSource::new(first_file.name(), first_source.to_owned()),
+        });
+        Err(Error::from_kind(ErrorKind::DuplicateName {
+            name,
+            first_file: first_file.name().to_owned(),
+            span: source.and_then(|source| location_span(file, source, &[path, &[1]].concat())),
+            source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+            first,
+        }))
+    };
+
+    visit_definitions(file, &mut |name, path| check(name, path))
+}
+
+/// Returns an error if the package of `file`, or any of its parent packages, has the same name as a definition in
+/// another file in the pool, or if a definition in `file` has the same name as a package in the pool.
+///
+/// Files are named in the error instead of spans, since packages have no source location in the pool.
+pub(super) fn check_package_conflicts(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+) -> Result<(), Error> {
+    let error = |name: &str, package_file: &str, definition_file: &str| {
+        Error::from_kind(ErrorKind::DuplicatePackage {
+            name: name.to_owned(),
+            file: file.name().to_owned(),
+            package_file: package_file.to_owned(),
+            definition_file: definition_file.to_owned(),
+        })
+    };
+
+    for package in parent_packages(file.package()) {
+        if let Some((definition_file, _)) = find_pool_definition(pool, package) {
+            if definition_file.name() != file.name() {
+                return Err(error(package, file.name(), definition_file.name()));
+            }
+        }
+    }
+
+    // Definitions in `file` are all scoped to its package, so they can only conflict with packages nested inside it.
+    let is_nested_package = |package: &str| {
+        package.len() > file.package().len()
+            && (file.package().is_empty()
+                || package
+                    .strip_prefix(file.package())
+                    .is_some_and(|rest| rest.starts_with('.')))
+    };
+    let mut packages = HashMap::new();
+    for pool_file in pool.files() {
+        if pool_file.name() != file.name() && is_nested_package(pool_file.package_name()) {
+            for package in
+                parent_packages(pool_file.package_name()).filter(|p| is_nested_package(p))
+            {
+                packages
+                    .entry(package.to_owned())
+                    .or_insert(pool_file.name().to_owned());
+            }
+        }
+    }
+    if packages.is_empty() {
+        return Ok(());
+    }
+
+    visit_definitions(file, &mut |name, _| match packages.get(&name) {
+        Some(package_file) => Err(error(&name, package_file, file.name())),
+        None => Ok(()),
+    })
+}
+
+/// Gets a package name and the names of all its parent packages, such as `foo` and `foo.bar` for `foo.bar`.
+fn parent_packages(package: &str) -> impl Iterator<Item = &str> {
+    package
+        .match_indices('.')
+        .map(|(index, _)| &package[..index])
+        .chain((!package.is_empty()).then_some(package))
+}
+
+/// Returns an error if any field in `file` uses a name reserved by its containing message.
+///
+/// Reserved field numbers are checked when the file is added to the pool.
+pub(super) fn check_reserved_names(
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Result<(), Error> {
+    visit_messages(file, &mut |_, path, message| {
+        for (field_index, field) in message.field.iter().enumerate() {
+            let Some(reserved_index) = message
+                .reserved_name
+                .iter()
+                .position(|name| name == field.name())
+            else {
+                continue;
+            };
+
+            return Err(Error::from_kind(ErrorKind::FieldNameReserved {
+                name: field.name().to_owned(),
+                span: source.and_then(|source| {
+                    location_span(file, source, &[path, &[2, field_index as i32, 1]].concat())
+                }),
+                reserved_span: source.and_then(|source| {
+                    location_span(file, source, &[path, &[10, reserved_index as i32]].concat())
+                }),
+                source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+            }));
+        }
+        Ok(())
+    })
+}
+
+/// Returns an error if any message in `file` has two extension ranges which overlap.
+pub(super) fn check_extension_range_overlap(
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Result<(), Error> {
+    visit_messages(file, &mut |name, path, message| {
+        for (second_index, second) in message.extension_range.iter().enumerate() {
+            let Some(first_index) = message.extension_range[..second_index]
+                .iter()
+                .position(|first| first.start() < second.end() && second.start() < first.end())
+            else {
+                continue;
+            };
+            let first = &message.extension_range[first_index];
+
+            return Err(Error::from_kind(ErrorKind::OverlappingExtensionRanges {
+                message: name.to_owned(),
+                first: (first.start(), first.end() - 1),
+                second: (second.start(), second.end() - 1),
+                span: source.and_then(|source| {
+                    location_span(file, source, &[path, &[5, second_index as i32]].concat())
+                }),
+                first_span: source.and_then(|source| {
+                    location_span(file, source, &[path, &[5, first_index as i32]].concat())
+                }),
+                source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+            }));
+        }
+        Ok(())
+    })
+}
+
+/// Returns an error if the name of a synthetic oneof generated for a proto3 `optional` field conflicts with
+/// another definition in the same message.
+///
+/// The pool would also reject such files, but could not point to the field responsible for the conflict.
+pub(super) fn check_synthetic_oneof_names(
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Result<(), Error> {
+    visit_messages(file, &mut |scope, path, message| {
+        for (field_index, field) in message.field.iter().enumerate() {
+            if !field.proto3_optional() {
+                continue;
+            }
+            let Some(oneof) = field
+                .oneof_index
+                .and_then(|index| message.oneof_decl.get(index as usize))
+            else {
+                continue;
+            };
+
+            let Some([tag, index]) = find_definition(message, oneof.name(), field.oneof_index)
+            else {
+                continue;
+            };
+
+            return Err(Error::from_kind(ErrorKind::SyntheticOneofNameConflict {
+                name: join_name(scope, oneof.name()),
+                field_name: field.name().to_owned(),
+                span: source.and_then(|source| {
+                    location_span(file, source, &[path, &[tag, index, 1]].concat())
+                }),
+                field_span: source.and_then(|source| {
+                    location_span(file, source, &[path, &[2, field_index as i32, 1]].concat())
+                }),
+                source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+            }));
+        }
+        Ok(())
+    })
+}
+
+/// Returns an error if any field or extension in `file` has a synthetic map entry message as its type, other than the
+/// map field the entry was generated for.
+///
+/// This runs before `file` is added to the pool, so its imports must already be in `pool`. Fields whose type cannot be
+/// resolved are skipped, and reported when the file is added to the pool.
+pub(super) fn check_map_entry_fields(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Result<(), Error> {
+    let resolver = NameResolver::new(pool, file);
+    let map_entry_type = |scope: &str, field: &FieldDescriptorProto| {
+        let (entry, _) = resolver.resolve(scope, field.type_name.as_deref()?)?;
+        resolver.is_map_entry(&entry).then_some(entry)
+    };
+    let error = |entry: String, field_name: String, type_path: Option<&[i32]>| {
+        Error::from_kind(ErrorKind::MapEntryFieldType {
+            name: entry,
+            field_name,
+            span: source
+                .zip(type_path)
+                .and_then(|(source, path)| location_span(file, source, path)),
+            source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+        })
+    };
+
+    // Find the map field each entry was generated for. The value field of a map entry has no source location, so
+    // errors for it point to the type of the map field instead.
+    let mut map_fields = HashMap::new();
+    let _ = visit_messages(file, &mut |name, path, message| {
+        for (index, field) in message.field.iter().enumerate() {
+            match map_entry_type(name, field) {
+                Some(entry)
+                    if field.label() == Label::Repeated
+                        && entry.rsplit_once('.').map_or("", |(parent, _)| parent) == name =>
+                {
+                    map_fields.insert(entry, [path, &[2, index as i32, 6]].concat());
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    });
+
+    visit_messages(file, &mut |name, path, message| {
+        let is_map_entry = message.options.as_ref().is_some_and(|o| o.map_entry());
+        for (index, field) in message.field.iter().enumerate() {
+            let Some(entry) = map_entry_type(name, field) else {
+                continue;
+            };
+            let type_path = [path, &[2, index as i32, 6]].concat();
+            if map_fields.get(&entry) == Some(&type_path) {
+                continue;
+            }
+
+            let type_path = match is_map_entry {
+                true => map_fields.get(name),
+                false => Some(&type_path),
+            };
+            return Err(error(
+                entry,
+                join_name(name, field.name()),
+                type_path.map(Vec::as_slice),
+            ));
+        }
+        for (index, extension) in message.extension.iter().enumerate() {
+            if let Some(entry) = map_entry_type(name, extension) {
+                return Err(error(
+                    entry,
+                    join_name(name, extension.name()),
+                    Some(&[path, &[6, index as i32, 6]].concat()),
+                ));
+            }
+        }
+        Ok(())
+    })?;
+
+    for (index, extension) in file.extension.iter().enumerate() {
+        if let Some(entry) = map_entry_type(file.package(), extension) {
+            return Err(error(
+                entry,
+                join_name(file.package(), extension.name()),
+                Some(&[7, index as i32, 6]),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns an error if `validator` rejects the JSON name of any field or extension in `file`.
+pub(super) fn check_json_names(
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+    validator: &dyn Fn(&str, &str) -> Option<String>,
+) -> Result<(), Error> {
+    let check = |scope: &str, field: &FieldDescriptorProto, path: &[i32]| {
+        let json_name = match &field.json_name {
+            Some(json_name) => json_name.clone(),
+            None => to_json_name(field.name()),
+        };
+        let Some(error) = validator(field.name(), &json_name) else {
+            return Ok(());
+        };
+
+        // Point to the json_name option if given explicitly, or the field name otherwise.
+        let span = source.and_then(|source| {
+            location_span(file, source, &[path, &[10]].concat())
+                .or_else(|| location_span(file, source, &[path, &[1]].concat()))
+        });
+        Err(Error::from_kind(ErrorKind::InvalidJsonName {
+            name: join_name(scope, field.name()),
+            json_name,
+            message: error,
+            span,
+            source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+        }))
+    };
+
+    visit_messages(file, &mut |name, path, message| {
+        for (index, field) in message.field.iter().enumerate() {
+            check(name, field, &[path, &[2, index as i32]].concat())?;
+        }
+        for (index, extension) in message.extension.iter().enumerate() {
+            check(name, extension, &[path, &[6, index as i32]].concat())?;
+        }
+        Ok(())
+    })?;
+    for (index, extension) in file.extension.iter().enumerate() {
+        check(file.package(), extension, &[7, index as i32])?;
+    }
+    Ok(())
+}
+
+/// Returns a more specific error if `err` was caused by the input or output type of a method in `file` not being
+/// defined.
+///
+/// This is only used to improve the error message when the file fails to be added to the pool, so `None` is returned
+/// if the type name may refer to an existing definition.
+pub(super) fn check_method_types(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+    err: &DescriptorError,
+) -> Option<Error> {
+    let &[6, service_index, 2, method_index, type_tag] = err.path()? else {
+        return None;
+    };
+    let service = file.service.get(usize::try_from(service_index).ok()?)?;
+    let method = service.method.get(usize::try_from(method_index).ok()?)?;
+    let (kind, type_name) = match type_tag {
+        2 => ("input", method.input_type()),
+        3 => ("output", method.output_type()),
+        _ => return None,
+    };
+
+    let is_defined =
+        |name: &str| find_pool_definition(pool, name).is_some() || file_defines(file, name);
+    if scoped_names(file.package(), type_name).any(|name| is_defined(&name)) {
+        return None;
+    }
+
+    Some(Error::from_kind(ErrorKind::MethodTypeNotFound {
+        method_name: join_name(&join_name(file.package(), service.name()), method.name()),
+        kind,
+        type_name: type_name.to_owned(),
+        span: source.and_then(|source| location_span(file, source, err.path()?)),
+        source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+    }))
+}
+
+/// Returns an error if any extend block in `file` extends a synthetic map entry message, or has an extension whose
+/// number is not in an extension range of the extended message.
+///
+/// This is only used to improve the error message when the file fails to be added to the pool, so `None` is returned
+/// if the extended message cannot be found.
+pub(super) fn check_extendees(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Option<Error> {
+    // Returns `Some(None)` if the name is defined, but is not a message.
+    let find_message = |name: &str| {
+        if let Some(message) = pool.get_message_by_name(name) {
+            return Some(Some(message.descriptor_proto().clone()));
+        }
+        let mut found = None;
+        let _ = visit_messages(file, &mut |message_name, _, message| {
+            if message_name == name {
+                found = Some(message.clone());
+            }
+            Ok(())
+        });
+        match found {
+            Some(message) => Some(Some(message)),
+            None => file_defines(file, name).then_some(None),
+        }
+    };
+
+    let mut result = None;
+    let mut check_extensions = |scope: &str, path: &[i32], extensions: &[FieldDescriptorProto]| {
+        for (index, extension) in extensions.iter().enumerate() {
+            if result.is_some() {
+                return;
+            }
+            let Some((name, Some(message))) = scoped_names(scope, extension.extendee())
+                .find_map(|name| Some((name.clone(), find_message(&name)?)))
+            else {
+                continue;
+            };
+            let source_code = NamedSource::new(file.name(), source.unwrap_or_default().to_owned());
+
+            if message.options.as_ref().is_some_and(|o| o.map_entry()) {
+                let extendee_path = [path, &[index as i32, 2]].concat();
+                result = Some(Error::from_kind(ErrorKind::ExtendingMapEntry {
+                    message_name: name,
+                    span: source.and_then(|source| location_span(file, source, &extendee_path)),
+                    source_code,
+                }));
+                continue;
+            }
+
+            let ranges = InversionList::new(
+                message
+                    .extension_range
+                    .iter()
+                    .map(|range| range.start()..=range.end().saturating_sub(1)),
+            );
+            if !ranges.contains(extension.number()) {
+                let number_path = [path, &[index as i32, 3]].concat();
+                result = Some(Error::from_kind(ErrorKind::InvalidExtensionNumber {
+                    message_name: name,
+                    number: extension.number(),
+                    span: source.and_then(|source| location_span(file, source, &number_path)),
+                    help: ranges
+                        .closest_miss(extension.number())
+                        .map(|number| format!("did you mean field number {}?", number)),
+                    source_code,
+                }));
+            }
+        }
+    };
+
+    check_extensions(file.package(), &[7], &file.extension);
+    let _ = visit_messages(file, &mut |name, path, message| {
+        check_extensions(name, &[path, &[6]].concat(), &message.extension);
+        Ok(())
+    });
+    result
+}
+
+/// Returns the fully-qualified names a type name may refer to, in order of precedence.
+///
+/// Relative names are resolved in `scope`, then each parent scope.
+fn scoped_names<'a>(scope: &'a str, type_name: &'a str) -> impl Iterator<Item = String> + 'a {
+    let mut scope = match type_name.strip_prefix('.') {
+        Some(_) => None,
+        None => Some(scope),
+    };
+    let full_name = type_name.strip_prefix('.').map(str::to_owned);
+    full_name.into_iter().chain(std::iter::from_fn(move || {
+        let current = scope?;
+        scope = if current.is_empty() {
+            None
+        } else {
+            Some(current.rsplit_once('.').map_or("", |(parent, _)| parent))
+        };
+        Some(join_name(current, type_name))
+    }))
+}
+
+/// Returns whether `file` defines a message, enum, service or extension with the fully-qualified `name`.
+fn file_defines(file: &FileDescriptorProto, name: &str) -> bool {
+    let mut defined = false;
+    let _ = visit_messages(file, &mut |message_name, _, message| {
+        defined |= message_name == name
+            || message
+                .enum_type
+                .iter()
+                .any(|e| join_name(message_name, e.name()) == name)
+            || message
+                .extension
+                .iter()
+                .any(|e| join_name(message_name, e.name()) == name);
+        Ok(())
+    });
+    defined
+        || file
+            .enum_type
+            .iter()
+            .any(|e| join_name(file.package(), e.name()) == name)
+        || file
+            .service
+            .iter()
+            .any(|s| join_name(file.package(), s.name()) == name)
+        || file
+            .extension
+            .iter()
+            .any(|e| join_name(file.package(), e.name()) == name)
+}
+
+/// Adds a warning to `warnings` for each extension range of a message in `file`, if none of the message's extension
+/// ranges are used by an extension in the pool.
+pub(super) fn check_unused_extension_ranges(
+    file: &FileDescriptor,
+    source: Option<&str>,
+    warnings: &mut Vec<Error>,
+) {
+    let _ = visit_messages(file.file_descriptor_proto(), &mut |name, path, message| {
+        if message.extension_range.is_empty() {
+            return Ok(());
+        }
+        let descriptor = file
+            .parent_pool()
+            .get_message_by_name(name)
+            .expect("message not found in pool");
+        if descriptor.extensions().next().is_some() {
+            return Ok(());
+        }
+
+        for (index, range) in message.extension_range.iter().enumerate() {
+            warnings.push(Error::from_kind(ErrorKind::UnusedExtensionRange {
+                message: name.to_owned(),
+                range: (range.start(), range.end() - 1),
+                span: source.and_then(|source| {
+                    location_span(
+                        file.file_descriptor_proto(),
+                        source,
+                        &[path, &[5, index as i32]].concat(),
+                    )
+                }),
+                source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+            }));
+        }
+        Ok(())
+    });
+}
+
+/// Returns a warning for each field or extension in `file` whose type is a deprecated message, unless the field, or a
+/// message or file containing it, is also deprecated.
+///
+/// For map fields, the type of the map value is checked.
+pub(super) fn check_deprecated_types(file: &FileDescriptor, source: Option<&str>) -> Vec<Error> {
+    fn is_deprecated(message: &MessageDescriptor) -> bool {
+        let proto = message.descriptor_proto();
+        proto.options.as_ref().is_some_and(|o| o.deprecated())
+    }
+
+    fn check_field(
+        file: &FileDescriptor,
+        source: Option<&str>,
+        (field_name, kind, path, deprecated): (&str, Kind, &[i32], bool),
+        warnings: &mut Vec<Error>,
+    ) {
+        let Kind::Message(mut message) = kind else {
+            return;
+        };
+        if message.is_map_entry() {
+            let Kind::Message(value) = message.map_entry_value_field().kind() else {
+                return;
+            };
+            message = value;
+        }
+        if deprecated || !is_deprecated(&message) {
+            return;
+        }
+
+        warnings.push(Error::from_kind(ErrorKind::DeprecatedTypeUsed {
+            field_name: field_name.to_owned(),
+            type_name: message.full_name().to_owned(),
+            span: source.and_then(|source| {
+                location_span(file.file_descriptor_proto(), source, &[path, &[6]].concat())
+            }),
+            source_code: NamedSource::new(file.name(), source.unwrap_or_default().to_owned()),
+        }));
+    }
+
+    fn extension_field(
+        extension: &ExtensionDescriptor,
+        deprecated: bool,
+    ) -> (&str, Kind, &[i32], bool) {
+        let proto = extension.field_descriptor_proto();
+        (
+            extension.full_name(),
+            extension.kind(),
+            extension.path(),
+            deprecated || proto.options.as_ref().is_some_and(|o| o.deprecated()),
+        )
+    }
+
+    fn check_message(
+        file: &FileDescriptor,
+        source: Option<&str>,
+        message: &MessageDescriptor,
+        deprecated: bool,
+        warnings: &mut Vec<Error>,
+    ) {
+        let deprecated = deprecated || is_deprecated(message);
+        for field in message.fields() {
+            let proto = field.field_descriptor_proto();
+            let field_deprecated =
+                deprecated || proto.options.as_ref().is_some_and(|o| o.deprecated());
+            check_field(
+                file,
+                source,
+                (
+                    field.full_name(),
+                    field.kind(),
+                    field.path(),
+                    field_deprecated,
+                ),
+                warnings,
+            );
+        }
+        for extension in message.child_extensions() {
+            check_field(
+                file,
+                source,
+                extension_field(&extension, deprecated),
+                warnings,
+            );
+        }
+        for nested in message.child_messages() {
+            if !nested.is_map_entry() {
+                check_message(file, source, &nested, deprecated, warnings);
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let proto = file.file_descriptor_proto();
+    let deprecated = proto.options.as_ref().is_some_and(|o| o.deprecated());
+    for message in file.messages() {
+        check_message(file, source, &message, deprecated, &mut warnings);
+    }
+    for extension in file.extensions() {
+        check_field(
+            file,
+            source,
+            extension_field(&extension, deprecated),
+            &mut warnings,
+        );
+    }
+    warnings
+}
+
+/// Returns an error if any message in `file` has a chain of non-repeated message fields longer than `max_depth`, or
+/// contains itself through `required` message fields.
+pub(super) fn check_message_nesting_depth(
+    file: &FileDescriptor,
+    source: Option<&str>,
+    max_depth: u32,
+) -> Result<(), Error> {
+    /// Gets the length of the longest chain of non-repeated message fields starting at `message`, or `None` if the
+    /// chain is unbounded because of recursion.
+    ///
+    /// `stack` holds the messages currently being visited, along with whether the field being followed from each one
+    /// is `required`.
+    fn nesting_depth(
+        message: &MessageDescriptor,
+        stack: &mut Vec<(String, bool)>,
+        depths: &mut HashMap<String, Option<u32>>,
+    ) -> Option<u32> {
+        if let Some(&depth) = depths.get(message.full_name()) {
+            return depth;
+        }
+
+        stack.push((message.full_name().to_owned(), false));
+        let mut depth = Some(0);
+        for field in message.fields() {
+            if field.is_list() || field.is_map() {
+                continue;
+            }
+            if let Kind::Message(field_message) = field.kind() {
+                stack.last_mut().unwrap().1 = field.cardinality() == Cardinality::Required;
+                let field_depth = match stack
+                    .iter()
+                    .position(|(name, _)| name == field_message.full_name())
+                {
+                    // A message which is still being visited is part of a cycle, which is only unbounded if every
+                    // field in it is required. Otherwise, the field ends the chain.
+                    Some(start) if stack[start..].iter().all(|&(_, required)| required) => None,
+                    Some(_) => continue,
+                    None => nesting_depth(&field_message, stack, depths),
+                };
+                depth = depth
+                    .zip(field_depth)
+                    .map(|(d, f)| d.max(f.saturating_add(1)));
+            }
+        }
+        stack.pop();
+        depths.insert(message.full_name().to_owned(), depth);
+        depth
+    }
+
+    let mut stack = Vec::new();
+    let mut depths = HashMap::new();
+    visit_messages(file.file_descriptor_proto(), &mut |name, path, _| {
+        let message = file
+            .parent_pool()
+            .get_message_by_name(name)
+            .expect("message not found in pool");
+        let depth = nesting_depth(&message, &mut stack, &mut depths);
+        if depth.is_some_and(|depth| depth <= max_depth) {
+            return Ok(());
+        }
+
+        let span = source.and_then(|source| {
+            location_span(file.file_descriptor_proto(), source, &[path, &[1]].concat())
+        });
+        let source_code = NamedSource::new(file.name(), source.unwrap_or_default().to_owned());
+        let name = name.to_owned();
+        Err(Error::from_kind(match depth {
+            Some(depth) => ErrorKind::MessageNestingTooDeep {
+                name,
+                depth,
+                max_depth,
+                span,
+                source_code,
+            },
+            None => ErrorKind::RecursiveMessage {
+                name,
+                span,
+                source_code,
+            },
+        }))
+    })
+}
+
+/// Gets the file and source path of the message, enum, service or extension named `name` in the pool.
+fn find_pool_definition(pool: &DescriptorPool, name: &str) -> Option<(FileDescriptor, Vec<i32>)> {
+    if let Some(message) = pool.get_message_by_name(name) {
+        Some((message.parent_file(), message.path().to_vec()))
+    } else if let Some(enum_) = pool.get_enum_by_name(name) {
+        Some((enum_.parent_file(), enum_.path().to_vec()))
+    } else if let Some(service) = pool.get_service_by_name(name) {
+        Some((service.parent_file(), service.path().to_vec()))
+    } else {
+        pool.get_extension_by_name(name)
+            .map(|extension| (extension.parent_file(), extension.path().to_vec()))
+    }
+}
+
+/// Gets the source path, relative to the message, of the definition named `name`, ignoring the oneof at `skip_oneof`.
+fn find_definition(
+    message: &DescriptorProto,
+    name: &str,
+    skip_oneof: Option<i32>,
+) -> Option<[i32; 2]> {
+    fn position<'a>(mut names: impl Iterator<Item = &'a str>, name: &str) -> Option<i32> {
+        names.position(|n| n == name).map(|index| index as i32)
+    }
+
+    if let Some(index) = position(message.field.iter().map(|f| f.name()), name) {
+        return Some([2, index]);
+    }
+    if let Some(index) = message
+        .oneof_decl
+        .iter()
+        .enumerate()
+        .position(|(index, o)| Some(index as i32) != skip_oneof && o.name() == name)
+    {
+        return Some([8, index as i32]);
+    }
+    if let Some(index) = position(message.nested_type.iter().map(|m| m.name()), name) {
+        return Some([3, index]);
+    }
+    if let Some(index) = position(message.enum_type.iter().map(|e| e.name()), name) {
+        return Some([4, index]);
+    }
+    None
+}
+
+/// Calls `f` with the fully-qualified name and source path of each message, enum, service and extension in `file`.
+fn visit_definitions(
+    file: &FileDescriptorProto,
+    f: &mut impl FnMut(String, &[i32]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    visit_messages(file, &mut |name, path, message| {
+        f(name.to_owned(), path)?;
+        for (index, enum_) in message.enum_type.iter().enumerate() {
+            f(
+                join_name(name, enum_.name()),
+                &[path, &[4, index as i32]].concat(),
+            )?;
+        }
+        Ok(())
+    })?;
+    for (index, enum_) in file.enum_type.iter().enumerate() {
+        f(join_name(file.package(), enum_.name()), &[5, index as i32])?;
+    }
+    for (index, service) in file.service.iter().enumerate() {
+        f(
+            join_name(file.package(), service.name()),
+            &[6, index as i32],
+        )?;
+    }
+    for (index, extension) in file.extension.iter().enumerate() {
+        f(
+            join_name(file.package(), extension.name()),
+            &[7, index as i32],
+        )?;
+    }
+    Ok(())
+}
+
+/// Calls `f` with the fully-qualified name and source path of each message in the file, including nested messages.
+fn visit_messages(
+    file: &FileDescriptorProto,
+    f: &mut impl FnMut(&str, &[i32], &DescriptorProto) -> Result<(), Error>,
+) -> Result<(), Error> {
+    fn visit_message(
+        message: &DescriptorProto,
+        name: &str,
+        path: &mut Vec<i32>,
+        f: &mut impl FnMut(&str, &[i32], &DescriptorProto) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        f(name, path, message)?;
+        for (index, nested) in message.nested_type.iter().enumerate() {
+            path.extend([3, index as i32]);
+            visit_message(nested, &join_name(name, nested.name()), path, f)?;
+            path.truncate(path.len() - 2);
+        }
+        Ok(())
+    }
+
+    let mut path = Vec::new();
+    for (index, message) in file.message_type.iter().enumerate() {
+        path.extend([4, index as i32]);
+        visit_message(
+            message,
+            &join_name(file.package(), message.name()),
+            &mut path,
+            f,
+        )?;
+        path.truncate(0);
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct UsedFiles {
+    files: HashSet<String>,
+}
+
+impl UsedFiles {
+    fn is_used(&self, file: &FileDescriptor) -> bool {
+        self.files.contains(file.name())
+            || file
+                .public_dependencies()
+                .any(|dependency| self.is_used(&dependency))
+    }
+
+    /// Returns `true` if `file`, or any file it publicly imports, extends one of the `google.protobuf` options
+    /// messages.
+    fn extends_options(file: &FileDescriptor) -> bool {
+        file.extensions().any(|extension| {
+            let extendee = extension.containing_message();
+            extendee.package_name() == "google.protobuf" && extendee.name().ends_with("Options")
+        }) || file
+            .public_dependencies()
+            .any(|dependency| UsedFiles::extends_options(&dependency))
+    }
+}
+
+/// Returns `true` if any options in the file have an aggregate (text format) value.
+pub(super) fn has_aggregate_options(file: &FileDescriptorProto) -> bool {
+    let mut found = false;
+    visit_options(file, &mut |_, _, _, options| {
+        found |= options.iter().any(|o| o.aggregate_value.is_some());
+    });
+    found
+}
+
+/// Returns `true` if the file contains any extend blocks.
+pub(super) fn has_extensions(file: &FileDescriptorProto) -> bool {
+    let mut found = !file.extension.is_empty();
+    let _ = visit_messages(file, &mut |_, _, message| {
+        found |= !message.extension.is_empty();
+        Ok(())
+    });
+    found
+}
+
+/// Looks for fields in aggregate option values which are not defined by the option's message type.
+///
+/// This is only used to improve the error message when the file fails to be added to the pool, so any errors
+/// unrelated to aggregate options are ignored.
+pub(super) fn check_aggregate_options(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Option<Error> {
+    // Add the file without its aggregate options, so that extensions defined in the same file can be resolved.
+    let mut pool = pool.clone();
+    let mut stripped = file.clone();
+    visit_options_mut(&mut stripped, &mut |options| {
+        options.retain(|o| o.aggregate_value.is_none());
+    });
+    pool.add_file_descriptor_proto(stripped).ok()?;
+
+    let mut error = None;
+    visit_options(file, &mut |path, scope, options_name, options| {
+        if error.is_some() {
+            return;
+        }
+
+        for (index, option) in options.iter().enumerate() {
+            let Some(value) = &option.aggregate_value else {
+                continue;
+            };
+            let Some(message) = resolve_option_message(&pool, scope, options_name, option) else {
+                continue;
+            };
+
+            if let Some((field_name, message_name)) = find_unknown_field(&message, value) {
+                let mut option_path = path.to_vec();
+                option_path.extend([UNINTERPRETED_OPTION_TAG, index as i32]);
+                error = Some(Error::from_kind(ErrorKind::OptionUnknownField {
+                    span: source.and_then(|source| location_span(file, source, &option_path)),
+                    source_code: NamedSource::new(
+                        file.name(),
+                        source.unwrap_or_default().to_owned(),
+                    ),
+                    option_name: fmt_option_name(option),
+                    field_name,
+                    message_name,
+                }));
+                return;
+            }
+        }
+    });
+    error
+}
+
+/// Returns `true` if any options in the file have a negative integer value.
+pub(super) fn has_negative_int_options(file: &FileDescriptorProto) -> bool {
+    let mut found = false;
+    visit_options(file, &mut |_, _, _, options| {
+        found |= options.iter().any(|o| o.negative_int_value.is_some());
+    });
+    found
+}
+
+/// Looks for options with a negative integer value, which set an unsigned field.
+///
+/// This is only used to improve the error message when the file fails to be added to the pool, so any errors
+/// unrelated to negative option values are ignored.
+pub(super) fn check_negative_unsigned_options(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+) -> Option<Error> {
+    // Add the file without its negative options, so that extensions defined in the same file can be resolved.
+    let mut pool = pool.clone();
+    let mut stripped = file.clone();
+    visit_options_mut(&mut stripped, &mut |options| {
+        options.retain(|o| o.negative_int_value.is_none());
+    });
+    pool.add_file_descriptor_proto(stripped).ok()?;
+
+    let mut error = None;
+    visit_options(file, &mut |path, scope, options_name, options| {
+        if error.is_some() {
+            return;
+        }
+
+        for (index, option) in options.iter().enumerate() {
+            let Some(value) = option.negative_int_value else {
+                continue;
+            };
+            let Some((field_name, kind, _)) =
+                resolve_option_field(&pool, scope, options_name, option)
+            else {
+                continue;
+            };
+            let max = match kind {
+                Kind::Uint32 | Kind::Fixed32 => u64::from(u32::MAX),
+                Kind::Uint64 | Kind::Fixed64 => u64::MAX,
+                _ => continue,
+            };
+
+            let mut option_path = path.to_vec();
+            option_path.extend([UNINTERPRETED_OPTION_TAG, index as i32]);
+            error = Some(Error::from_kind(
+                ErrorKind::OptionValueNegativeForUnsigned {
+                    field_name,
+                    value,
+                    max,
+                    span: source.and_then(|source| location_span(file, source, &option_path)),
+                    source_code: NamedSource::new(
+                        file.name(),
+                        source.unwrap_or_default().to_owned(),
+                    ),
+                },
+            ));
+            return;
+        }
+    });
+    error
+}
+
+/// Returns an error if an option which sets a non-repeated field has a list value, such as `[true]`.
+///
+/// Each element of a list generates a separate uninterpreted option, so the options generated from a list are found
+/// using the spans of the lists reported by the parser, which lie within the source location of each option.
+pub(super) fn check_list_options(
+    pool: &DescriptorPool,
+    file: &FileDescriptorProto,
+    source: Option<&str>,
+    lists: &[Range<usize>],
+) -> Result<(), Error> {
+    let (Some(source), false) = (source, lists.is_empty()) else {
+        return Ok(());
+    };
+    let option_path = |path: &[i32], index: usize| {
+        let mut option_path = path.to_vec();
+        option_path.extend([UNINTERPRETED_OPTION_TAG, index as i32]);
+        option_path
+    };
+    let is_list = |path: &[i32], index: usize| {
+        location_span(file, source, &option_path(path, index)).is_some_and(|span| {
+            let span = span.offset()..span.offset() + span.len();
+            lists
+                .iter()
+                .any(|list| span.start <= list.start && list.end <= span.end)
+        })
+    };
+
+    // Extensions defined in this file may shadow those in the pool, so the file must be added to a copy of the pool to
+    // resolve option names. The options are removed first, since they have not been checked yet. If adding the file
+    // fails, the error is reported when the whole file is added.
+    let stripped_pool;
+    let pool = if has_extensions(file) {
+        let mut stripped = file.clone();
+        visit_options_mut(&mut stripped, &mut |options| options.clear());
+        let mut pool = pool.clone();
+        if pool.add_file_descriptor_proto(stripped).is_err() {
+            return Ok(());
+        }
+        stripped_pool = pool;
+        &stripped_pool
+    } else {
+        pool
+    };
+
+    let mut result = Ok(());
+    visit_options(file, &mut |path, scope, options_name, options| {
+        if result.is_err() {
+            return;
+        }
+
+        for (index, option) in options.iter().enumerate() {
+            if !is_list(path, index) {
+                continue;
+            }
+            let Some((field_name, _, false)) =
+                resolve_option_field(pool, scope, options_name, option)
+            else {
+                continue;
+            };
+
+            result = Err(Error::from_kind(
+                ErrorKind::ListOptionValueForSingularField {
+                    field_name,
+                    span: location_span(file, source, &option_path(path, index)),
+                    source_code: NamedSource::new(file.name(), source.to_owned()),
+                },
+            ));
+            return;
+        }
+    });
+    result
+}
+
+const UNINTERPRETED_OPTION_TAG: i32 = 999;
+
+/// Calls `f` with the path to the options of each definition in the file, along with the scope used to resolve
+/// extension names, the name of the options message, and the uninterpreted options.
+fn visit_options(
+    file: &FileDescriptorProto,
+    f: &mut impl FnMut(&[i32], &str, &str, &[UninterpretedOption]),
+) {
+    let package = file.package();
+    let mut path = Vec::new();
+
+    if let Some(options) = &file.options {
+        f(
+            &[8],
+            package,
+            "google.protobuf.FileOptions",
+            &options.uninterpreted_option,
+        );
+    }
+    for (i, message) in file.message_type.iter().enumerate() {
+        path.extend([4, i as i32]);
+        visit_message_options(message, &join_name(package, message.name()), &mut path, f);
+        path.truncate(0);
+    }
+    for (i, enum_) in file.enum_type.iter().enumerate() {
+        path.extend([5, i as i32]);
+        visit_enum_options(enum_, &join_name(package, enum_.name()), &mut path, f);
+        path.truncate(0);
+    }
+    for (i, extension) in file.extension.iter().enumerate() {
+        if let Some(options) = &extension.options {
+            f(
+                &[7, i as i32, 8],
+                package,
+                "google.protobuf.FieldOptions",
+                &options.uninterpreted_option,
+            );
+        }
+    }
+    for (i, service) in file.service.iter().enumerate() {
+        let scope = join_name(package, service.name());
+        if let Some(options) = &service.options {
+            f(
+                &[6, i as i32, 3],
+                &scope,
+                "google.protobuf.ServiceOptions",
+                &options.uninterpreted_option,
+            );
+        }
+        for (j, method) in service.method.iter().enumerate() {
+            if let Some(options) = &method.options {
+                f(
+                    &[6, i as i32, 2, j as i32, 4],
+                    &scope,
+                    "google.protobuf.MethodOptions",
+                    &options.uninterpreted_option,
+                );
+            }
+        }
+    }
+}
+
+fn visit_message_options(
+    message: &DescriptorProto,
+    scope: &str,
+    path: &mut Vec<i32>,
+    f: &mut impl FnMut(&[i32], &str, &str, &[UninterpretedOption]),
+) {
+    let mut visit =
+        |path: &mut Vec<i32>, suffix: &[i32], name: &str, options: &[UninterpretedOption]| {
+            let len = path.len();
+            path.extend(suffix);
+            f(path, scope, name, options);
+            path.truncate(len);
+        };
+
+    if let Some(options) = &message.options {
+        visit(
+            path,
+            &[7],
+            "google.protobuf.MessageOptions",
+            &options.uninterpreted_option,
+        );
+    }
+    for (i, field) in message.field.iter().enumerate() {
+        if let Some(options) = &field.options {
+            visit(
+                path,
+                &[2, i as i32, 8],
+                "google.protobuf.FieldOptions",
+                &options.uninterpreted_option,
+            );
+        }
+    }
+    for (i, extension) in message.extension.iter().enumerate() {
+        if let Some(options) = &extension.options {
+            visit(
+                path,
+                &[6, i as i32, 8],
+                "google.protobuf.FieldOptions",
+                &options.uninterpreted_option,
+            );
+        }
+    }
+    for (i, oneof) in message.oneof_decl.iter().enumerate() {
+        if let Some(options) = &oneof.options {
+            visit(
+                path,
+                &[8, i as i32, 2],
+                "google.protobuf.OneofOptions",
+                &options.uninterpreted_option,
+            );
+        }
+    }
+    for (i, range) in message.extension_range.iter().enumerate() {
+        if let Some(options) = &range.options {
+            visit(
+                path,
+                &[5, i as i32, 3],
+                "google.protobuf.ExtensionRangeOptions",
+                &options.uninterpreted_option,
+            );
+        }
+    }
+    for (i, nested) in message.nested_type.iter().enumerate() {
+        let len = path.len();
+        path.extend([3, i as i32]);
+        visit_message_options(nested, &join_name(scope, nested.name()), path, f);
+        path.truncate(len);
+    }
+    for (i, nested) in message.enum_type.iter().enumerate() {
+        let len = path.len();
+        path.extend([4, i as i32]);
+        visit_enum_options(nested, &join_name(scope, nested.name()), path, f);
+        path.truncate(len);
+    }
+}
+
+fn visit_enum_options(
+    enum_: &EnumDescriptorProto,
+    scope: &str,
+    path: &mut Vec<i32>,
+    f: &mut impl FnMut(&[i32], &str, &str, &[UninterpretedOption]),
+) {
+    let len = path.len();
+    if let Some(options) = &enum_.options {
+        path.push(3);
+        f(
+            path,
+            scope,
+            "google.protobuf.EnumOptions",
+            &options.uninterpreted_option,
+        );
+        path.truncate(len);
+    }
+    for (i, value) in enum_.value.iter().enumerate() {
+        if let Some(options) = &value.options {
+            path.extend([2, i as i32, 3]);
+            f(
+                path,
+                scope,
+                "google.protobuf.EnumValueOptions",
+                &options.uninterpreted_option,
+            );
+            path.truncate(len);
+        }
+    }
+}
+
+fn visit_options_mut(
+    file: &mut FileDescriptorProto,
+    f: &mut impl FnMut(&mut Vec<UninterpretedOption>),
+) {
+    fn visit_message(
+        message: &mut DescriptorProto,
+        f: &mut impl FnMut(&mut Vec<UninterpretedOption>),
+    ) {
+        if let Some(options) = &mut message.options {
+            f(&mut options.uninterpreted_option);
+        }
+        for field in message.field.iter_mut().chain(&mut message.extension) {
+            if let Some(options) = &mut field.options {
+                f(&mut options.uninterpreted_option);
+            }
+        }
+        for oneof in &mut message.oneof_decl {
+            if let Some(options) = &mut oneof.options {
+                f(&mut options.uninterpreted_option);
+            }
+        }
+        for range in &mut message.extension_range {
+            if let Some(options) = &mut range.options {
+                f(&mut options.uninterpreted_option);
+            }
+        }
+        for nested in &mut message.nested_type {
+            visit_message(nested, f);
+        }
+        for nested in &mut message.enum_type {
+            visit_enum(nested, f);
+        }
+    }
+
+    fn visit_enum(
+        enum_: &mut EnumDescriptorProto,
+        f: &mut impl FnMut(&mut Vec<UninterpretedOption>),
+    ) {
+        if let Some(options) = &mut enum_.options {
+            f(&mut options.uninterpreted_option);
+        }
+        for value in &mut enum_.value {
+            if let Some(options) = &mut value.options {
+                f(&mut options.uninterpreted_option);
+            }
+        }
+    }
+
+    if let Some(options) = &mut file.options {
+        f(&mut options.uninterpreted_option);
+    }
+    for message in &mut file.message_type {
+        visit_message(message, f);
+    }
+    for enum_ in &mut file.enum_type {
+        visit_enum(enum_, f);
+    }
+    for extension in &mut file.extension {
+        if let Some(options) = &mut extension.options {
+            f(&mut options.uninterpreted_option);
+        }
+    }
+    for service in &mut file.service {
+        if let Some(options) = &mut service.options {
+            f(&mut options.uninterpreted_option);
+        }
+        for method in &mut service.method {
+            if let Some(options) = &mut method.options {
+                f(&mut options.uninterpreted_option);
+            }
+        }
+    }
+}
+
+fn join_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// Gets the message type of the value of an option, if it can be resolved.
+fn resolve_option_message(
+    pool: &DescriptorPool,
+    scope: &str,
+    options_name: &str,
+    option: &UninterpretedOption,
+) -> Option<MessageDescriptor> {
+    let (_, kind, _) = resolve_option_field(pool, scope, options_name, option)?;
+    kind.as_message().cloned()
+}
+
+/// Gets the full name and type of the field set by an option, and whether it is repeated.
+fn resolve_option_field(
+    pool: &DescriptorPool,
+    scope: &str,
+    options_name: &str,
+    option: &UninterpretedOption,
+) -> Option<(String, Kind, bool)> {
+    // The options messages are known even if the file does not import `descriptor.proto`.
+    let mut message = match pool.get_message_by_name(options_name) {
+        Some(message) => message,
+        None => DescriptorPool::global().get_message_by_name(options_name)?,
+    };
+    let mut field: Option<(String, Kind, bool)> = None;
+    for part in &option.name {
+        if let Some((_, kind, _)) = &field {
+            message = kind.as_message()?.clone();
+        }
+        field = Some(if part.is_extension {
+            let extension = resolve_extension(pool, scope, &part.name_part)?;
+            (
+                extension.full_name().to_owned(),
+                extension.kind(),
+                extension.is_list(),
+            )
+        } else {
+            let field = message.get_field_by_name(&part.name_part)?;
+            (field.full_name().to_owned(), field.kind(), field.is_list())
+        });
+    }
+    field
+}
+
+fn resolve_extension(
+    pool: &DescriptorPool,
+    scope: &str,
+    name: &str,
+) -> Option<ExtensionDescriptor> {
+    if let Some(full_name) = name.strip_prefix('.') {
+        return pool.get_extension_by_name(full_name);
+    }
+
+    let mut scope = scope;
+    loop {
+        if let Some(extension) = pool.get_extension_by_name(&join_name(scope, name)) {
+            return Some(extension);
+        }
+        if scope.is_empty() {
+            return None;
+        }
+        scope = scope.rsplit_once('.').map_or("", |(parent, _)| parent);
+    }
+}
+
+fn fmt_option_name(option: &UninterpretedOption) -> String {
+    let mut name = String::new();
+    for part in &option.name {
+        if !name.is_empty() {
+            name.push('.');
+        }
+        if part.is_extension {
+            name.push('(');
+            name.push_str(&part.name_part);
+            name.push(')');
+        } else {
+            name.push_str(&part.name_part);
+        }
+    }
+    name
+}
+
+/// Finds the first field in a text format message which is not defined in the given message type, returning the
+/// name of the field and the message.
+///
+/// Values which are not valid text format are ignored, since they are reported when the option is interpreted.
+fn find_unknown_field(message: &MessageDescriptor, value: &str) -> Option<(String, String)> {
+    let value = protox_parse::text_format::parse(value).ok()?;
+    find_unknown_field_in_message(message, value.as_ast())
+}
+
+fn find_unknown_field_in_message(
+    message: &MessageDescriptor,
+    value: &text_format::Message,
+) -> Option<(String, String)> {
+    for field in &value.fields {
+        let text_format::FieldName::Ident(name) = &field.name else {
+            // Extension names and `Any` type URLs are validated when the option is interpreted.
+            continue;
+        };
+        let Some(descriptor) = message
+            .get_field_by_name(&name.value)
+            .or_else(|| find_group_field(message, &name.value))
+        else {
+            return Some((name.value.clone(), message.full_name().to_owned()));
+        };
+
+        let Some(field_message) = descriptor.kind().as_message().cloned() else {
+            continue;
+        };
+        let values = match &field.value {
+            text_format::FieldValue::Message(value) => std::slice::from_ref(value),
+            text_format::FieldValue::MessageList(values, _) => values.as_slice(),
+            _ => continue,
+        };
+        for value in values {
+            if let Some(err) = find_unknown_field_in_message(&field_message, value) {
+                return Some(err);
+            }
+        }
+    }
+    None
+}
+
+fn find_group_field(message: &MessageDescriptor, name: &str) -> Option<FieldDescriptor> {
+    message.fields().find(|field| {
+        field.is_group() && field.kind().as_message().is_some_and(|m| m.name() == name)
+    })
+}
@@ -1,21 +1,41 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    env,
     fmt::{self, Write},
-    path::{Path, PathBuf},
+    fs, io, mem,
+    path::{Component, Path, PathBuf},
 };
 
+use bytes::Bytes;
 use prost::Message;
-use prost_reflect::{DescriptorPool, DynamicMessage, ReflectMessage, Value};
-use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, EnumDescriptor, FileDescriptor, MessageDescriptor,
+    ReflectMessage, ServiceDescriptor, Value,
+};
+use prost_types::{FileDescriptorProto, FileDescriptorSet, SourceCodeInfo};
+use protox_parse::ParseOptions;
 
+#[cfg(feature = "file-cache")]
+use crate::file::FileCache;
 use crate::{
     error::{Error, ErrorKind},
-    file::{check_shadow, path_to_file_name, File, FileMetadata, FileResolver},
+    file::{
+        check_shadow, path_to_file_name, ChainFileResolver, DescriptorSetFileResolver, File,
+        FileMetadata, FileParseOptions, FileReadLimit, FileResolver,
+    },
 };
 
+mod check;
+mod shared;
+#[cfg(feature = "timing")]
+mod stats;
 #[cfg(test)]
 mod tests;
 
+pub use self::shared::SharedDescriptorPool;
+#[cfg(feature = "timing")]
+pub use self::stats::{CompilationStats, FileDuration};
+
 /// Options for compiling protobuf files.
 ///
 /// # Examples
@@ -48,11 +68,48 @@ mod tests;
 pub struct Compiler {
     pool: DescriptorPool,
     resolver: Box<dyn FileResolver>,
+    include_paths: Vec<Option<PathBuf>>,
     files: HashMap<String, FileMetadata>,
     include_imports: bool,
     include_source_info: bool,
+    error_on_unused_import: bool,
+    warning_as_error: bool,
+    protoc_compatibility: bool,
+    require_syntax_statement: bool,
+    require_package_declaration: bool,
+    check_extension_range_overlap: bool,
+    deprecation_check: bool,
+    json_name_validator: Option<Box<JsonNameValidator>>,
+    max_message_nesting_depth: Option<u32>,
+    name_prefix_strip: Option<String>,
+    allowed_google_imports: Option<HashSet<String>>,
+    #[cfg(feature = "file-cache")]
+    file_cache: FileCache,
+    file_read_limit: FileReadLimit,
+    parse_options: FileParseOptions,
+    shadow_policy: ShadowPolicy,
+    warnings: Vec<Error>,
+    #[cfg(feature = "timing")]
+    stats: Option<CompilationStats>,
+}
+
+/// How a [`Compiler`] handles a file which is shadowed by another file with the same name in an earlier include path.
+///
+/// See [`Compiler::with_shadow_policy()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShadowPolicy {
+    /// Return an error. This is the default.
+    #[default]
+    Error,
+    /// Compile the shadowing file, and add a warning to [`Compiler::warnings()`].
+    Warn,
+    /// Compile the shadowing file silently.
+    Allow,
 }
 
+type JsonNameValidator = dyn Fn(&str, &str) -> Option<String>;
+
 impl Compiler {
     /// Creates a new [`Compiler`] with default options and the given set of include paths.
     ///
@@ -63,17 +120,52 @@ impl Compiler {
         I: IntoIterator<Item = P>,
         P: AsRef<Path>,
     {
-        use crate::file::{ChainFileResolver, GoogleFileResolver, IncludeFileResolver};
+        use crate::file::{GoogleFileResolver, IncludeFileResolver};
 
         let mut resolver = ChainFileResolver::new();
+        let mut include_paths = Vec::new();
+        #[cfg(feature = "file-cache")]
+        let file_cache = FileCache::default();
+        let file_read_limit = FileReadLimit::default();
+        let parse_options = FileParseOptions::default();
 
         for include in includes {
-            resolver.add(IncludeFileResolver::new(include.as_ref().to_owned()));
+            let include_resolver = IncludeFileResolver::new(include.as_ref().to_owned())
+                .with_read_limit(file_read_limit.clone())
+                .with_parse_options(parse_options.clone());
+            #[cfg(feature = "file-cache")]
+            let include_resolver = include_resolver.with_cache(file_cache.clone());
+            resolver.add(include_resolver);
+            include_paths.push(Some(include.as_ref().to_owned()));
         }
 
         resolver.add(GoogleFileResolver::new());
+        include_paths.push(None);
 
-        Ok(Compiler::with_file_resolver(resolver))
+        let mut compiler = Compiler::with_file_resolver(resolver);
+        compiler.include_paths = include_paths;
+        #[cfg(feature = "file-cache")]
+        {
+            compiler.file_cache = file_cache;
+        }
+        compiler.file_read_limit = file_read_limit;
+        compiler.parse_options = parse_options;
+        Ok(compiler)
+    }
+
+    /// Creates a new [`Compiler`] with default options, using the include paths in the `PROTO_PATH` environment variable.
+    ///
+    /// Paths are separated by `:` on Unix and `;` on Windows. If the variable is not set, only standard files like
+    /// `google/protobuf/descriptor.proto` can be imported.
+    pub fn with_proto_path_env() -> Result<Self, Error> {
+        let includes = match env::var_os("PROTO_PATH") {
+            Some(paths) => env::split_paths(&paths)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Compiler::new(includes)
     }
 
     /// Creates a new [`Compiler`] with a custom [`FileResolver`] for looking up imported files.
@@ -84,12 +176,88 @@ impl Compiler {
         Compiler {
             pool: DescriptorPool::new(),
             resolver: Box::new(resolver),
+            include_paths: vec![None],
             files: HashMap::new(),
             include_imports: false,
             include_source_info: false,
+            error_on_unused_import: false,
+            warning_as_error: false,
+            protoc_compatibility: false,
+            require_syntax_statement: false,
+            require_package_declaration: false,
+            check_extension_range_overlap: false,
+            deprecation_check: false,
+            json_name_validator: None,
+            max_message_nesting_depth: None,
+            name_prefix_strip: None,
+            allowed_google_imports: None,
+            #[cfg(feature = "file-cache")]
+            file_cache: FileCache::default(),
+            file_read_limit: FileReadLimit::default(),
+            parse_options: FileParseOptions::default(),
+            shadow_policy: ShadowPolicy::Error,
+            warnings: Vec::new(),
+            #[cfg(feature = "timing")]
+            stats: None,
         }
     }
 
+    /// Creates a new [`Compiler`] with default options, which reuses the files in a [`SharedDescriptorPool`].
+    ///
+    /// Files in the shared pool are treated as already-compiled imports: they are not resolved or checked again, and
+    /// are only included in the output if [`include_imports()`](Compiler::include_imports) is set. Any other files are
+    /// added to a local copy of the pool, so the shared pool itself is unchanged.
+    ///
+    /// Like [`Compiler::new`], the returned compiler can import standard files like
+    /// `google/protobuf/descriptor.proto`. Further resolvers can be added using
+    /// [`prepend_resolver()`](Compiler::prepend_resolver).
+    pub fn with_shared_pool(pool: SharedDescriptorPool) -> Self {
+        let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+        compiler.files = pool
+            .files()
+            .map(|file| {
+                let name = file.name().to_owned();
+                let metadata = FileMetadata {
+                    name: name.clone(),
+                    path: None,
+                    source: None,
+                    is_import: true,
+                };
+                (name, metadata)
+            })
+            .collect();
+        compiler.pool = DescriptorPool::clone(&pool);
+        compiler
+    }
+
+    /// Adds a [`FileResolver`] which is searched for imported files before the existing resolvers.
+    ///
+    /// Files which have already been added to this `Compiler` are not affected.
+    pub fn prepend_resolver<R>(&mut self, resolver: R) -> &mut Self
+    where
+        R: FileResolver + 'static,
+    {
+        let mut chain = self.take_resolver();
+        chain.prepend(resolver);
+        self.resolver = Box::new(chain);
+        self.include_paths.insert(0, None);
+        self
+    }
+
+    /// Adds a [`FileResolver`] which is searched for imported files after the existing resolvers.
+    ///
+    /// Files which have already been added to this `Compiler` are not affected.
+    pub fn append_resolver<R>(&mut self, resolver: R) -> &mut Self
+    where
+        R: FileResolver + 'static,
+    {
+        let mut chain = self.take_resolver();
+        chain.append(resolver);
+        self.resolver = Box::new(chain);
+        self.include_paths.push(None);
+        self
+    }
+
     /// Sets whether the output `FileDescriptorSet` should include source info.
     ///
     /// If set, the file descriptors returned by [`file_descriptor_set`](Compiler::file_descriptor_set) will have
@@ -109,13 +277,447 @@ impl Compiler {
         self
     }
 
+    /// Sets whether to return an error for imports which are not used.
+    ///
+    /// If set, [`open_file`](Compiler::open_file) will fail if the file has an `import` statement for a file which does not
+    /// define any of the types, extensions or options it references. Public imports are never considered unused.
+    pub fn with_error_on_unused_import(&mut self, yes: bool) -> &mut Self {
+        self.error_on_unused_import = yes;
+        self
+    }
+
+    /// Sets whether to return an error for files which have warnings.
+    ///
+    /// If set, [`open_file`](Compiler::open_file) will fail if the file, or any file it imports, has warnings such as
+    /// a `default` value for a field in a `proto3` file. Warnings for a file can be inspected with
+    /// [`File::warnings()`]. Warnings found after parsing, such as for
+    /// [`with_max_message_nesting_depth()`](Compiler::with_max_message_nesting_depth), are also returned as errors
+    /// instead of being added to [`warnings()`](Compiler::warnings).
+    pub fn with_warning_as_error(&mut self, yes: bool) -> &mut Self {
+        self.warning_as_error = yes;
+        self
+    }
+
+    /// Sets whether to reject files which protoc would reject, but which are accepted by default.
+    ///
+    /// If set, the following are reported as errors:
+    ///
+    /// - Any parse warning, such as for a `default` value on a field in a `proto3` file parsed with
+    ///   [`allow_proto3_default_values()`](protox_parse::ParseOptions::allow_proto3_default_values), a list option
+    ///   value parsed with [`allow_list_option_values()`](protox_parse::ParseOptions::allow_list_option_values), or a
+    ///   method input or output type named `stream`.
+    /// - Files shadowed by an earlier include path, regardless of [`with_shadow_policy()`](Compiler::with_shadow_policy).
+    /// - Overlapping extension ranges, as with
+    ///   [`with_extension_number_reservation_check()`](Compiler::with_extension_number_reservation_check).
+    /// - Missing `weak` imports.
+    ///
+    /// Checks which protoc does not perform, such as
+    /// [`with_max_message_nesting_depth()`](Compiler::with_max_message_nesting_depth), are unaffected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.with_protoc_compatibility_mode(true);
+    ///
+    /// let err = compiler
+    ///     .add_file_with_content("foo.proto", "message Foo { extensions 100 to 199; extensions 150 to 250; }")
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "extension range 150 to 250 of message 'Foo' overlaps with range 100 to 199",
+    /// );
+    /// ```
+    pub fn with_protoc_compatibility_mode(&mut self, yes: bool) -> &mut Self {
+        self.protoc_compatibility = yes;
+        self
+    }
+
+    /// Sets whether to return an error for files without a `syntax` statement.
+    ///
+    /// Files without a `syntax` statement are treated as `proto2`, but many style guides require the syntax to be
+    /// given explicitly. Only files parsed from source are checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.with_require_syntax_statement(true);
+    ///
+    /// compiler.add_file_with_content("foo.proto", "syntax = 'proto2'; message Foo {}").unwrap();
+    /// let err = compiler.add_file_with_content("bar.proto", "message Bar {}").unwrap_err();
+    /// assert_eq!(err.to_string(), "file 'bar.proto' does not have a syntax statement");
+    /// ```
+    pub fn with_require_syntax_statement(&mut self, yes: bool) -> &mut Self {
+        self.require_syntax_statement = yes;
+        self
+    }
+
+    /// Sets whether to return an error for files without a `package` statement.
+    ///
+    /// Definitions in files without a package are placed in the global namespace, where they may conflict with
+    /// definitions in unrelated files. The check applies to every file added to the compiler, including imports and
+    /// files added from descriptors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.with_require_package_declaration(true);
+    ///
+    /// compiler.add_file_with_content("foo.proto", "package foo; message Foo {}").unwrap();
+    /// let err = compiler.add_file_with_content("bar.proto", "message Bar {}").unwrap_err();
+    /// assert_eq!(err.to_string(), "file 'bar.proto' does not have a package statement");
+    /// ```
+    pub fn with_require_package_declaration(&mut self, yes: bool) -> &mut Self {
+        self.require_package_declaration = yes;
+        self
+    }
+
+    /// Sets whether to return an error for messages with overlapping extension ranges, such as
+    /// `extensions 100 to 199; extensions 150 to 250;`.
+    ///
+    /// This is always checked if [`with_protoc_compatibility_mode()`](Compiler::with_protoc_compatibility_mode) is
+    /// set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.with_extension_number_reservation_check(true);
+    ///
+    /// let err = compiler
+    ///     .add_file_with_content("foo.proto", "message Foo { extensions 100 to 199; extensions 150 to 250; }")
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "extension range 150 to 250 of message 'Foo' overlaps with range 100 to 199",
+    /// );
+    /// ```
+    pub fn with_extension_number_reservation_check(&mut self, yes: bool) -> &mut Self {
+        self.check_extension_range_overlap = yes;
+        self
+    }
+
+    /// Sets whether to warn about fields in files added by [`open_file`](Compiler::open_file) whose type is a message
+    /// marked with `option deprecated = true;`.
+    ///
+    /// Fields which are themselves deprecated, or which are declared in a deprecated message or file, are not
+    /// reported. For map fields, the type of the map value is checked. Warnings are added to
+    /// [`warnings()`](Compiler::warnings), unless [`with_warning_as_error()`](Compiler::with_warning_as_error) is
+    /// set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler
+    ///     .with_deprecation_check(true)
+    ///     .add_file_with_content("foo.proto", "
+    ///         message Old { option deprecated = true; }
+    ///         message New { optional Old old = 1; }
+    ///     ")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     compiler.warnings()[0].to_string(),
+    ///     "field 'New.old' uses deprecated message type 'Old'",
+    /// );
+    /// ```
+    pub fn with_deprecation_check(&mut self, yes: bool) -> &mut Self {
+        self.deprecation_check = yes;
+        self
+    }
+
+    /// Sets the maximum length of a chain of non-repeated message fields, such as `a.b.c`, allowed in the messages of
+    /// files added by [`open_file`](Compiler::open_file). By default, the length is not checked.
+    ///
+    /// Messages which exceed the limit, or which contain themselves through `required` fields, may cause deep or
+    /// unbounded recursion in some generated code. They are still valid, so a warning is added to
+    /// [`warnings()`](Compiler::warnings), unless [`with_warning_as_error()`](Compiler::with_warning_as_error) is
+    /// set. Recursion through `optional` fields is common, such as in linked lists, so it is not reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler
+    ///     .with_max_message_nesting_depth(1)
+    ///     .add_file_with_content("foo.proto", "
+    ///         message A { optional B b = 1; }
+    ///         message B { optional C c = 1; }
+    ///         message C {}
+    ///     ")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     compiler.warnings()[0].to_string(),
+    ///     "message 'A' has a chain of 2 nested non-repeated message fields, which exceeds the maximum of 1",
+    /// );
+    /// ```
+    pub fn with_max_message_nesting_depth(&mut self, depth: u32) -> &mut Self {
+        self.max_message_nesting_depth = Some(depth);
+        self
+    }
+
+    /// Sets a prefix to remove from the names of files in the output file descriptor set.
+    ///
+    /// Files are still resolved and imported using their full names, but the `name` and `dependency` fields of each
+    /// file in the output of [`file_descriptor_set()`](Compiler::file_descriptor_set) and
+    /// [`encode_file_descriptor_set()`](Compiler::encode_file_descriptor_set) have the prefix removed. The prefix is
+    /// only removed from names which start with it followed by a path separator, so `myorg/proto` strips
+    /// `myorg/proto/foo.proto` but not `myorg/protobuf/foo.proto`. Other names are unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if two files which have already been added have the same name after removing the prefix.
+    /// Adding a file whose stripped name conflicts with an existing file also fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.with_name_prefix_strip("myorg/proto/").unwrap();
+    /// compiler.add_file_with_content("myorg/proto/bar.proto", "").unwrap();
+    /// compiler.add_file_with_content("myorg/proto/foo.proto", "import 'myorg/proto/bar.proto';").unwrap();
+    ///
+    /// let file_descriptor_set = compiler.file_descriptor_set();
+    /// assert_eq!(file_descriptor_set.file[1].name(), "foo.proto");
+    /// assert_eq!(file_descriptor_set.file[1].dependency, ["bar.proto"]);
+    /// ```
+    pub fn with_name_prefix_strip(&mut self, prefix: &str) -> Result<&mut Self, Error> {
+        let prefix = prefix.trim_end_matches('/');
+        self.name_prefix_strip = (!prefix.is_empty()).then(|| prefix.to_owned());
+        let mut names: Vec<&str> = self.files.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        for name in names {
+            self.check_stripped_name(name)?;
+        }
+        Ok(self)
+    }
+
+    /// Sets a directory in which to cache parsed files between runs.
+    ///
+    /// When a file is opened from one of the include paths given to [`Compiler::new`], its parsed descriptor is stored
+    /// in `dir`, under the SHA-256 hash of its source. Subsequent compilations which open a file with identical
+    /// contents load the descriptor from the cache instead of parsing it again. The directory is created if it does not
+    /// exist, and any errors reading or writing the cache are ignored.
+    ///
+    /// Files opened by a custom [`FileResolver`] are not cached.
+    ///
+    /// This method requires the `file-cache` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("foo.proto", "message Foo {}").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."]).unwrap();
+    /// compiler.with_file_cache_dir("cache".as_ref());
+    /// compiler.open_file("foo.proto").unwrap();
+    ///
+    /// assert_eq!(fs::read_dir("cache").unwrap().count(), 1);
+    /// ```
+    #[cfg(feature = "file-cache")]
+    pub fn with_file_cache_dir(&mut self, dir: &Path) -> &mut Self {
+        self.file_cache.set_dir(Some(dir.to_owned()));
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of source files read from the include paths. Defaults to 64 MiB.
+    ///
+    /// Larger files are rejected with an error before being read into memory. Sizes above 2,147,483,647 bytes are
+    /// never supported. Only the include paths passed to [`Compiler::new`] are affected, not any custom
+    /// [`FileResolver`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("foo.proto", "message Foo {}").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."]).unwrap();
+    /// compiler.with_file_read_limit(10);
+    ///
+    /// let err = compiler.open_file("foo.proto").unwrap_err();
+    /// assert_eq!(err.to_string(), "file 'foo.proto' is too large");
+    /// ```
+    pub fn with_file_read_limit(&mut self, bytes: u64) -> &mut Self {
+        self.file_read_limit.set(bytes);
+        self
+    }
+
+    /// Sets the options used to parse source files, which allow some constructs that protoc rejects.
+    ///
+    /// Constructs allowed by these options are reported as [`File::warnings()`], so they are still rejected if
+    /// [`with_warning_as_error()`](Compiler::with_warning_as_error) or
+    /// [`with_protoc_compatibility_mode()`](Compiler::with_protoc_compatibility_mode) is set. Only files passed to
+    /// [`add_file_with_content()`](Compiler::add_file_with_content), or read from the include paths passed to
+    /// [`Compiler::new`], are affected, not any custom [`FileResolver`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// # use protox_parse::ParseOptions;
+    /// let source = "
+    ///     import 'google/protobuf/descriptor.proto';
+    ///     extend google.protobuf.MessageOptions { repeated int32 tags = 1001; }
+    ///     message Foo { option (tags) = [1, 2]; }
+    /// ";
+    ///
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// let err = compiler.add_file_with_content("foo.proto", source).unwrap_err();
+    /// assert_eq!(err.to_string(), "option values cannot be lists");
+    ///
+    /// let mut options = ParseOptions::new();
+    /// options.allow_list_option_values(true);
+    /// compiler.with_parse_options(options);
+    /// compiler.add_file_with_content("foo.proto", source).unwrap();
+    /// ```
+    pub fn with_parse_options(&mut self, options: ParseOptions) -> &mut Self {
+        self.parse_options.set(options);
+        self
+    }
+
+    /// Sets how to handle a file passed to [`open_file`](Compiler::open_file) which is shadowed by a file with the same
+    /// name in an earlier include path.
+    ///
+    /// By default, this is an error. With [`ShadowPolicy::Warn`] or [`ShadowPolicy::Allow`], the shadowing file is
+    /// compiled instead, which allows patched versions of third-party files to take precedence. Warnings are added to
+    /// [`warnings()`](Compiler::warnings), or returned as errors if
+    /// [`with_warning_as_error()`](Compiler::with_warning_as_error) is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use protox::{Compiler, ShadowPolicy};
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::create_dir_all("patched").unwrap();
+    /// fs::create_dir_all("vendor").unwrap();
+    /// fs::write("patched/foo.proto", "message Patched {}").unwrap();
+    /// fs::write("vendor/foo.proto", "message Original {}").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["patched", "vendor"]).unwrap();
+    /// compiler.with_shadow_policy(ShadowPolicy::Warn);
+    /// compiler.open_file("vendor/foo.proto").unwrap();
+    ///
+    /// assert_eq!(compiler.file_descriptor_set().file[0].message_type[0].name(), "Patched");
+    /// assert_eq!(compiler.warnings().len(), 1);
+    /// ```
+    pub fn with_shadow_policy(&mut self, policy: ShadowPolicy) -> &mut Self {
+        self.shadow_policy = policy;
+        self
+    }
+
+    /// Restricts which `google/protobuf/` files may be imported.
+    ///
+    /// If set, any import of a file starting with `google/protobuf/` which is not in `allowed` is rejected, even if the
+    /// file resolver could find it. Other imports are unaffected. By default, all imports are allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::{Compiler, file::GoogleFileResolver};
+    /// let mut compiler = Compiler::with_file_resolver(GoogleFileResolver::new());
+    /// compiler.with_strict_imports(&["google/protobuf/timestamp.proto"]);
+    ///
+    /// compiler
+    ///     .add_file_with_content("foo.proto", "import 'google/protobuf/timestamp.proto';")
+    ///     .unwrap();
+    /// let err = compiler
+    ///     .add_file_with_content("bar.proto", "import 'google/protobuf/any.proto';")
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "import 'google/protobuf/any.proto' is not allowed");
+    /// ```
+    pub fn with_strict_imports(&mut self, allowed: &[&str]) -> &mut Self {
+        self.allowed_google_imports = Some(allowed.iter().map(|&name| name.to_owned()).collect());
+        self
+    }
+
+    /// Sets a function to check the JSON name of each message field and extension.
+    ///
+    /// The function is called with the name of each field and its JSON name, which is either given by the `json_name`
+    /// option or generated from the field name, such as `myField` for `my_field`. If it returns an error message,
+    /// [`open_file`](Compiler::open_file) will fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.with_json_name_validator(|_, json_name| {
+    ///     json_name
+    ///         .starts_with('@')
+    ///         .then(|| "JSON-LD keywords are not allowed".to_owned())
+    /// });
+    ///
+    /// let err = compiler
+    ///     .add_file_with_content("foo.proto", "message Foo { optional int32 type = 1 [json_name = '@type']; }")
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "invalid JSON name '@type' for field 'Foo.type': JSON-LD keywords are not allowed"
+    /// );
+    /// ```
+    pub fn with_json_name_validator<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&str, &str) -> Option<String> + 'static,
+    {
+        self.json_name_validator = Some(Box::new(f));
+        self
+    }
+
+    /// Set whether to record the time spent compiling each file, which can be retrieved with
+    /// [`stats()`](Compiler::stats).
+    ///
+    /// The default value is `false`.
+    #[cfg(feature = "timing")]
+    pub fn with_stats_collection(&mut self, yes: bool) -> &mut Self {
+        if !yes {
+            self.stats = None;
+        } else if self.stats.is_none() {
+            self.stats = Some(CompilationStats::default());
+        }
+        self
+    }
+
+    /// Gets the time spent compiling files, if enabled by [`with_stats_collection()`](Compiler::with_stats_collection).
+    #[cfg(feature = "timing")]
+    pub fn stats(&self) -> Option<CompilationStats> {
+        self.stats.clone()
+    }
+
     /// Compiles the file at the given path, and adds it to this `Compiler` instance.
     ///
     /// If the path is absolute, or relative to the current directory, it must reside under one of the
     /// include paths. Otherwise, it is looked up relative to the given include paths in the same way as
     /// `import` statements.
     pub fn open_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, Error> {
-        let path = path.as_ref();
+        self.open_file_with_progress(path.as_ref(), &mut |_| ())
+    }
+
+    fn open_file_with_progress(
+        &mut self,
+        path: &Path,
+        on_success: &mut dyn FnMut(&FileMetadata),
+    ) -> Result<&mut Self, Error> {
         let (name, is_resolved) = if let Some(name) = self.resolver.resolve_path(path) {
             (name, true)
         } else if let Some(name) = path_to_file_name(path) {
@@ -126,15 +728,18 @@ impl Compiler {
             }));
         };
 
-        if let Some(file_metadata) = self.files.get_mut(&name) {
+        if let Some(file_metadata) = self.files.get(&name) {
             if is_resolved {
-                check_shadow(&name, file_metadata.path(), path)?;
+                let result = check_shadow(&name, file_metadata.path(), path);
+                self.apply_shadow_policy(result)?;
+            }
+            if let Some(file_metadata) = self.files.get_mut(&name) {
+                file_metadata.is_import = false;
             }
-            file_metadata.is_import = false;
             return Ok(self);
         }
 
-        let file = self.resolver.open_file(&name).map_err(|err| {
+        let file = self.resolve_file(&name).map_err(|err| {
             if err.is_file_not_found() {
                 Error::from_kind(ErrorKind::FileNotIncluded {
                     path: path.to_owned(),
@@ -144,26 +749,294 @@ impl Compiler {
             }
         })?;
         if is_resolved {
-            check_shadow(&name, file.path(), path)?;
+            let result = check_shadow(&name, file.path(), path);
+            self.apply_shadow_policy(result)?;
         }
 
-        let mut import_stack = vec![name.clone()];
-        for (i, import) in file.descriptor.dependency.iter().enumerate() {
-            self.add_import(import, &mut import_stack)
-                .map_err(|e| e.into_import_error(&file, i))?;
+        self.add_root_file(name, file, on_success)
+    }
+
+    /// Compiles the given source code as if it were a file named `name`, and adds it to this `Compiler` instance.
+    ///
+    /// The file does not need to exist on disk, but its imports are opened using the file resolver of this `Compiler`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is invalid, or a file named `name` has already been added with different source
+    /// code. Adding the same source again has no effect, except that the file is no longer treated as an import.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::{Compiler, file::GoogleFileResolver};
+    /// let mut compiler = Compiler::with_file_resolver(GoogleFileResolver::new());
+    /// compiler.add_file_with_content("foo.proto", "
+    ///     syntax = 'proto3';
+    ///     import 'google/protobuf/empty.proto';
+    ///
+    ///     message Foo {
+    ///         google.protobuf.Empty empty = 1;
+    ///     }
+    /// ").unwrap();
+    ///
+    /// assert_eq!(compiler.roots().next().unwrap().name(), "foo.proto");
+    /// ```
+    pub fn add_file_with_content(&mut self, name: &str, source: &str) -> Result<&mut Self, Error> {
+        if let Some(file_metadata) = self.files.get_mut(name) {
+            if file_metadata.source.as_deref() != Some(source) {
+                return Err(Error::from_kind(ErrorKind::FileContentConflict {
+                    name: name.to_owned(),
+                }));
+            }
+            file_metadata.is_import = false;
+            return Ok(self);
         }
-        drop(import_stack);
 
-        let path = self.check_file(file)?;
-        self.files.insert(
-            name.clone(),
-            FileMetadata {
-                name,
-                path,
-                is_import: false,
-            },
-        );
-        Ok(self)
+        let file = File::from_source_with_options(name, source, &self.parse_options.get())?;
+        self.add_root_file(name.to_owned(), file, &mut |_| ())
+    }
+
+    /// Compiles a [`FileDescriptorProto`] which has not had its type names or imports resolved, and adds it to this
+    /// `Compiler` instance.
+    ///
+    /// This can be used to compile files which are generated programmatically, without writing them to disk. The
+    /// `name` field of the file must be set, and its imports are opened using the file resolver of this `Compiler`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_types::{DescriptorProto, FieldDescriptorProto, FileDescriptorProto};
+    /// # use prost_types::field_descriptor_proto::{Label, Type};
+    /// # use protox::{Compiler, file::GoogleFileResolver};
+    /// let mut compiler = Compiler::with_file_resolver(GoogleFileResolver::new());
+    /// compiler.open_file_descriptor_proto(FileDescriptorProto {
+    ///     name: Some("foo.proto".to_owned()),
+    ///     dependency: vec!["google/protobuf/timestamp.proto".to_owned()],
+    ///     message_type: vec![DescriptorProto {
+    ///         name: Some("Foo".to_owned()),
+    ///         field: vec![FieldDescriptorProto {
+    ///             name: Some("time".to_owned()),
+    ///             number: Some(1),
+    ///             label: Some(Label::Optional as i32),
+    ///             type_name: Some("google.protobuf.Timestamp".to_owned()),
+    ///             ..Default::default()
+    ///         }],
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// }).unwrap();
+    ///
+    /// let pool = compiler.descriptor_pool();
+    /// let field = pool.get_message_by_name("Foo").unwrap().get_field_by_name("time").unwrap();
+    /// assert_eq!(field.kind().as_message().unwrap().full_name(), "google.protobuf.Timestamp");
+    /// ```
+    pub fn open_file_descriptor_proto(
+        &mut self,
+        file: FileDescriptorProto,
+    ) -> Result<&mut Self, Error> {
+        let name = match &file.name {
+            Some(name) if !name.is_empty() => name.clone(),
+            _ => return Err(Error::new("file descriptor must have a name")),
+        };
+
+        if let Some(file_metadata) = self.files.get_mut(&name) {
+            file_metadata.is_import = false;
+            return Ok(self);
+        }
+
+        self.add_root_file(name, File::from_file_descriptor_proto(file), &mut |_| ())
+    }
+
+    /// Adds all files in an encoded [`FileDescriptorSet`] to this `Compiler` instance as imports.
+    ///
+    /// The files are available to resolve imports and options of other files, but, like files
+    /// found by [`DescriptorSetFileResolver`](crate::file::DescriptorSetFileResolver), they are not roots and so are only included in the
+    /// output if [`include_imports()`](Compiler::include_imports) is set. Any dependencies not
+    /// present in the set are resolved using this compiler's [`FileResolver`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid file descriptor set, or any of its files fail
+    /// to compile.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_types::{FileDescriptorProto, FileDescriptorSet};
+    /// # use protox::Compiler;
+    /// let set = FileDescriptorSet {
+    ///     file: vec![FileDescriptorProto {
+    ///         name: Some("dep.proto".to_owned()),
+    ///         package: Some("dep".to_owned()),
+    ///         ..Default::default()
+    ///     }],
+    /// };
+    ///
+    /// let mut compiler = Compiler::with_file_resolver(protox::file::GoogleFileResolver::new());
+    /// compiler.add_proto_descriptor_set_bytes(set.encode_to_vec().into()).unwrap();
+    /// compiler.add_file_with_content("root.proto", "import 'dep.proto';").unwrap();
+    ///
+    /// assert_eq!(compiler.roots().map(|f| f.name()).collect::<Vec<_>>(), ["root.proto"]);
+    /// assert!(compiler.descriptor_pool().get_file_by_name("dep.proto").is_some());
+    /// ```
+    pub fn add_proto_descriptor_set_bytes(&mut self, bytes: Bytes) -> Result<&mut Self, Error> {
+        let set = DescriptorSetFileResolver::decode(bytes).map_err(Error::new)?;
+        self.add_descriptor_set_resolver(set)
+    }
+
+    /// Adds all files in a file descriptor set to this `Compiler` instance as imports.
+    ///
+    /// This is equivalent to [`add_proto_descriptor_set_bytes()`](Compiler::add_proto_descriptor_set_bytes), but
+    /// takes an already decoded [`FileDescriptorSet`]. It can be used to combine a
+    /// pre-compiled schema with newly parsed files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the files in the set fail to compile.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+    /// # use protox::{Compiler, file::GoogleFileResolver};
+    /// let set = FileDescriptorSet {
+    ///     file: vec![FileDescriptorProto {
+    ///         name: Some("base.proto".to_owned()),
+    ///         message_type: vec![DescriptorProto {
+    ///             name: Some("Base".to_owned()),
+    ///             ..Default::default()
+    ///         }],
+    ///         ..Default::default()
+    ///     }],
+    /// };
+    ///
+    /// let mut compiler = Compiler::with_file_resolver(GoogleFileResolver::new());
+    /// compiler.add_file_descriptor_set(set).unwrap();
+    /// compiler
+    ///     .add_file_with_content("root.proto", "import 'base.proto'; message Foo { optional Base base = 1; }")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(compiler.roots().map(|f| f.name()).collect::<Vec<_>>(), ["root.proto"]);
+    /// assert!(compiler.files().any(|f| f.name() == "base.proto" && f.is_import()));
+    /// ```
+    pub fn add_file_descriptor_set(&mut self, set: FileDescriptorSet) -> Result<&mut Self, Error> {
+        self.add_descriptor_set_resolver(DescriptorSetFileResolver::new(set))
+    }
+
+    /// Reuses the files compiled by a previous `Compiler` instance, if their source is unchanged.
+    ///
+    /// This is intended for watch-mode builds, which create a new `Compiler` each time the files change. The `pool`
+    /// and `files` should be taken from the previous instance, using [`descriptor_pool()`](Compiler::descriptor_pool)
+    /// and [`files()`](Compiler::files). Each file is opened again using this compiler's [`FileResolver`], which
+    /// usually parses it, to compare its source with the previous compilation. If its source is the same and all of
+    /// its imports were also reused, the descriptor from `pool` is used instead, skipping the checks which are run
+    /// when a file is compiled.
+    /// Reused files are treated as imports until they are opened with [`open_file()`](Compiler::open_file). Files
+    /// which have changed, or which import a changed file, are compiled again when they are next opened or imported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("dep.proto", "message Dep {}").unwrap();
+    /// fs::write("root.proto", "import 'dep.proto'; message Root { optional Dep dep = 1; }").unwrap();
+    ///
+    /// let mut previous = Compiler::new(["."]).unwrap();
+    /// previous.open_file("root.proto").unwrap();
+    ///
+    /// fs::write("root.proto", "import 'dep.proto'; message Root { repeated Dep dep = 1; }").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."]).unwrap();
+    /// compiler.prime_from_pool(
+    ///     previous.descriptor_pool(),
+    ///     previous.files().map(|f| (f.name().to_owned(), f.clone())).collect(),
+    /// );
+    /// // Only 'dep.proto' is unchanged, so 'root.proto' is compiled again.
+    /// assert_eq!(compiler.files().map(|f| f.name()).collect::<Vec<_>>(), ["dep.proto"]);
+    /// compiler.open_file("root.proto").unwrap();
+    /// ```
+    pub fn prime_from_pool(
+        &mut self,
+        pool: DescriptorPool,
+        mut files: HashMap<String, FileMetadata>,
+    ) -> &mut Self {
+        let mut reused = Vec::new();
+        let mut reused_names: HashSet<String> = HashSet::new();
+        for file in pool.files() {
+            // Files whose name conflicts with another after removing the name prefix are compiled again, so the
+            // conflict is reported.
+            let stripped_name = self.strip_name_prefix(file.name());
+            if self.files.contains_key(file.name())
+                || !file
+                    .dependencies()
+                    .all(|dep| reused_names.contains(dep.name()))
+                || self.check_stripped_name(file.name()).is_err()
+                || reused_names
+                    .iter()
+                    .any(|other| self.strip_name_prefix(other) == stripped_name)
+            {
+                continue;
+            }
+            let Some(metadata) = files.remove(file.name()) else {
+                continue;
+            };
+            let Some(path) = self.check_unchanged(&file, &metadata) else {
+                continue;
+            };
+
+            reused_names.insert(file.name().to_owned());
+            reused.push((
+                file,
+                FileMetadata {
+                    path,
+                    is_import: true,
+                    ..metadata
+                },
+            ));
+        }
+
+        if self.files.is_empty() && reused.len() == pool.files().len() {
+            self.pool = pool;
+        } else {
+            let mut added = HashSet::new();
+            for (file, _) in &reused {
+                // The file was valid in the previous pool, so it can only fail to be added if one of its
+                // dependencies was not added.
+                if file.dependencies().all(|dep| added.contains(dep.name()))
+                    && self
+                        .pool
+                        .add_file_descriptor_proto(file.file_descriptor_proto().clone())
+                        .is_ok()
+                {
+                    added.insert(file.name().to_owned());
+                }
+            }
+            reused.retain(|(file, _)| added.contains(file.name()));
+        }
+
+        for (file, metadata) in reused {
+            self.files.insert(file.name().to_owned(), metadata);
+        }
+        self
+    }
+
+    /// Resolves a file from a previous compilation again, returning its new path if it is unchanged.
+    fn check_unchanged(
+        &mut self,
+        file: &FileDescriptor,
+        metadata: &FileMetadata,
+    ) -> Option<Option<PathBuf>> {
+        let resolved = self.resolver.open_file(file.name()).ok()?;
+        let unchanged = match (&metadata.source, resolved.source()) {
+            (Some(old), Some(new)) => old == new,
+            (None, None) => resolved.file_descriptor_proto() == file.file_descriptor_proto(),
+            _ => false,
+        };
+        unchanged.then(|| resolved.path().map(ToOwned::to_owned))
     }
 
     /// Compiles the given files, and adds them to this `Compiler` instance.
@@ -180,27 +1053,237 @@ impl Compiler {
         Ok(self)
     }
 
+    /// Compiles the given files, and adds them to this `Compiler` instance, calling `on_success` after each file is
+    /// compiled.
+    ///
+    /// This is useful for reporting progress when compiling many files. The callback is called for each newly compiled
+    /// file, including imports, after all of its own imports. Files which were already added to this `Compiler` are
+    /// skipped.
+    ///
+    /// See [`open_file()`][Compiler::open_file()].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("bar.proto", "message Bar {}").unwrap();
+    /// fs::write("foo.proto", "import 'bar.proto'; message Foo { optional Bar bar = 1; }").unwrap();
+    ///
+    /// let mut compiled = Vec::new();
+    /// Compiler::new(["."])
+    ///     .unwrap()
+    ///     .open_files_with_progress(["foo.proto"], |file| {
+    ///         compiled.push((file.name().to_owned(), file.is_import()));
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(compiled, [("bar.proto".to_owned(), true), ("foo.proto".to_owned(), false)]);
+    /// ```
+    pub fn open_files_with_progress(
+        &mut self,
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+        mut on_success: impl FnMut(&FileMetadata),
+    ) -> Result<&mut Self, Error> {
+        for path in paths {
+            self.open_file_with_progress(path.as_ref(), &mut on_success)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Compiles all files matching the given glob pattern, such as `src/**/*.proto`, and adds them to this
+    /// `Compiler` instance.
+    ///
+    /// Relative patterns are expanded from the current directory, and matching files are opened in sorted order
+    /// using [`open_file()`][Compiler::open_file()].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid, a directory could not be read while expanding it, or any
+    /// matching file could not be compiled.
+    #[cfg(feature = "glob")]
+    pub fn open_files_glob(&mut self, pattern: &str) -> Result<&mut Self, Error> {
+        let mut paths = glob::glob(pattern)
+            .map_err(Error::new)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Error::new)?;
+        paths.sort();
+
+        self.open_files(paths)
+    }
+
+    /// Gets the include paths searched for imported files, in order of priority.
+    ///
+    /// Resolvers which are not backed by a directory, such as the built-in resolver for standard files like
+    /// `google/protobuf/descriptor.proto`, are represented by `None`. A [`Compiler`] created by
+    /// [`with_file_resolver()`](Compiler::with_file_resolver) has a single such resolver.
+    pub fn include_paths(&self) -> Vec<Option<&Path>> {
+        self.include_paths
+            .iter()
+            .map(|path| path.as_deref())
+            .collect()
+    }
+
     /// Converts all added files into an instance of [`FileDescriptorSet`](prost_types::FileDescriptorSet).
     ///
-    /// Files are sorted topologically, with dependency files ordered before the files that import them.
+    /// Files are sorted topologically, with dependency files ordered before the files that import them. Files
+    /// at the same depth in the import graph are sorted by name, so the output does not depend on the order
+    /// in which files were added.
     pub fn file_descriptor_set(&self) -> prost_types::FileDescriptorSet {
-        let file = self
+        self.file_descriptor_set_filtered(|f| self.include_imports || !f.is_import)
+    }
+
+    /// Converts all added files into a [`DynamicMessage`] of type `google.protobuf.FileDescriptorSet`, keeping any
+    /// extension options.
+    ///
+    /// Unlike [`file_descriptor_set()`](Compiler::file_descriptor_set), custom options are not dropped. If
+    /// `google/protobuf/descriptor.proto` was imported, the message is created using its definition from the compiled
+    /// files, so custom options can be accessed as extension fields. Otherwise they are kept as unknown fields. The
+    /// [`include_imports()`](Compiler::include_imports) and [`include_source_info()`](Compiler::include_source_info)
+    /// options are respected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// # use prost_reflect::Value;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.add_file_with_content("foo.proto", "
+    ///     import 'google/protobuf/descriptor.proto';
+    ///     extend google.protobuf.FileOptions { optional int32 opt = 1001; }
+    ///     option (opt) = 5;
+    /// ").unwrap();
+    ///
+    /// let file_descriptor_set = compiler.file_descriptor_set_with_extensions();
+    /// let file = &file_descriptor_set.get_field_by_name("file").unwrap();
+    /// let options = file.as_list().unwrap()[0].as_message().unwrap().get_field_by_name("options").unwrap();
+    /// let opt = compiler.descriptor_pool().get_extension_by_name("opt").unwrap();
+    /// assert_eq!(*options.as_message().unwrap().get_extension(&opt), Value::I32(5));
+    /// ```
+    pub fn file_descriptor_set_with_extensions(&self) -> DynamicMessage {
+        let desc = self
             .pool
-            .files()
-            .filter(|f| self.include_imports || !self.files[f.name()].is_import)
+            .get_message_by_name("google.protobuf.FileDescriptorSet")
+            .unwrap_or_else(|| FileDescriptorSet::default().descriptor());
+        DynamicMessage::decode(desc, self.encode_file_descriptor_set().as_slice())
+            .expect("failed to decode file descriptor set")
+    }
+
+    /// Converts all added files into the protobuf JSON encoding of a `google.protobuf.FileDescriptorSet`.
+    ///
+    /// The output is the same as for [`file_descriptor_set_with_extensions()`](Compiler::file_descriptor_set_with_extensions),
+    /// so custom options are encoded as extension fields, such as `"[foo.opt]": 5`, if their definitions are
+    /// available. Options which are kept as unknown fields are omitted.
+    ///
+    /// This method requires the `json` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler.add_file_with_content("foo.proto", "message Foo {}").unwrap();
+    ///
+    /// assert_eq!(
+    ///     compiler.file_descriptor_set_json().unwrap(),
+    ///     r#"{"file":[{"name":"foo.proto","messageType":[{"name":"Foo"}]}]}"#
+    /// );
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn file_descriptor_set_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.file_descriptor_set_with_extensions())
+    }
+
+    /// Converts the added files for which `predicate` returns `true` into an instance of
+    /// [`FileDescriptorSet`](prost_types::FileDescriptorSet).
+    ///
+    /// Unlike [`file_descriptor_set()`](Compiler::file_descriptor_set), the [`include_imports()`](Compiler::include_imports)
+    /// option is ignored, so the predicate may select any imported file. Files are sorted in the same order as
+    /// [`file_descriptor_set()`](Compiler::file_descriptor_set).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// # fn main() -> Result<(), protox::Error> {
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// std::fs::write("foo.proto", "import 'google/protobuf/empty.proto';").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."])?;
+    /// compiler.open_file("foo.proto")?;
+    ///
+    /// let file_descriptor_set =
+    ///     compiler.file_descriptor_set_filtered(|f| !f.name().starts_with("google/protobuf/"));
+    /// assert_eq!(file_descriptor_set.file.len(), 1);
+    /// assert_eq!(file_descriptor_set.file[0].name(), "foo.proto");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn file_descriptor_set_filtered(
+        &self,
+        predicate: impl Fn(&FileMetadata) -> bool,
+    ) -> prost_types::FileDescriptorSet {
+        prost_types::FileDescriptorSet {
+            file: self.file_descriptors_filtered(predicate).collect(),
+        }
+    }
+
+    /// Returns an iterator over the files which would be included in [`file_descriptor_set()`](Compiler::file_descriptor_set).
+    ///
+    /// Files are returned in the same order, but each file descriptor is only created when the iterator is advanced,
+    /// so the whole set does not need to be held in memory at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::with_file_resolver(protox::file::GoogleFileResolver::new());
+    /// compiler.add_file_with_content("foo.proto", "import 'google/protobuf/empty.proto';").unwrap();
+    /// compiler.include_imports(true);
+    ///
+    /// let names: Vec<String> = compiler.file_descriptor_set_iter().map(|f| f.name().to_owned()).collect();
+    /// assert_eq!(names, ["google/protobuf/empty.proto", "foo.proto"]);
+    /// ```
+    pub fn file_descriptor_set_iter(&self) -> impl Iterator<Item = FileDescriptorProto> + '_ {
+        self.file_descriptors_filtered(|f| self.include_imports || !f.is_import)
+    }
+
+    fn file_descriptors_filtered<'a>(
+        &'a self,
+        predicate: impl Fn(&FileMetadata) -> bool + 'a,
+    ) -> impl Iterator<Item = FileDescriptorProto> + 'a {
+        self.sorted_files()
+            .into_iter()
+            .filter(move |f| predicate(&self.files[f.name()]))
             .map(|f| {
-                if self.include_source_info {
+                let mut file = if self.include_source_info {
                     f.file_descriptor_proto().clone()
                 } else {
                     prost_types::FileDescriptorProto {
                         source_code_info: None,
                         ..f.file_descriptor_proto().clone()
                     }
+                };
+                if let Some(name) = &mut file.name {
+                    *name = self.strip_name_prefix(name).to_owned();
                 }
+                for dependency in &mut file.dependency {
+                    *dependency = self.strip_name_prefix(dependency).to_owned();
+                }
+                file
             })
-            .collect();
+    }
 
-        prost_types::FileDescriptorSet { file }
+    /// Converts the files added by [`open_file()`](Compiler::open_file) into an instance of
+    /// [`FileDescriptorSet`](prost_types::FileDescriptorSet), excluding any imported files.
+    ///
+    /// This is equivalent to `file_descriptor_set_filtered(|f| !f.is_import())`.
+    pub fn file_descriptor_set_for_roots_only(&self) -> prost_types::FileDescriptorSet {
+        self.file_descriptor_set_filtered(|f| !f.is_import)
     }
 
     /// Converts all added files into an instance of [`FileDescriptorSet`](prost_types::FileDescriptorSet) and encodes it.
@@ -208,16 +1291,20 @@ impl Compiler {
     /// This is equivalent to `file_descriptor_set()?.encode_to_vec()`, with the exception that extension
     /// options are included.
     pub fn encode_file_descriptor_set(&self) -> Vec<u8> {
-        if self.include_imports && self.include_source_info {
+        if self.include_imports && self.include_source_info && self.name_prefix_strip.is_none() {
             // Avoid reflection if possible.
-            return self.pool.encode_to_vec();
+            let mut buf = Vec::new();
+            for file in self.sorted_files() {
+                prost::encoding::bytes::encode(1, &file.encode_to_vec(), &mut buf);
+            }
+            return buf;
         }
 
         let file_desc = FileDescriptorProto::default().descriptor();
 
         let files = self
-            .pool
-            .files()
+            .sorted_files()
+            .into_iter()
             .filter(|f| self.include_imports || !self.files[f.name()].is_import)
             .map(|f| {
                 let file_buf = f.encode_to_vec();
@@ -227,6 +1314,22 @@ impl Compiler {
                 if !self.include_source_info {
                     file_msg.clear_field_by_name("source_code_info");
                 }
+                if self.name_prefix_strip.is_some() {
+                    file_msg.set_field_by_name(
+                        "name",
+                        Value::String(self.strip_name_prefix(f.name()).to_owned()),
+                    );
+                    file_msg.set_field_by_name(
+                        "dependency",
+                        Value::List(
+                            f.file_descriptor_proto()
+                                .dependency
+                                .iter()
+                                .map(|dep| Value::String(self.strip_name_prefix(dep).to_owned()))
+                                .collect(),
+                        ),
+                    );
+                }
 
                 Value::Message(file_msg)
             })
@@ -237,11 +1340,236 @@ impl Compiler {
         file_descriptor_set.encode_to_vec()
     }
 
+    /// Writes the source of each file added to this `Compiler`, including imported files, to the given directory.
+    ///
+    /// Each file is written to a path under `dir` given by its name, creating subdirectories as needed, so the
+    /// directory can be used as a single include path for the same set of files. Files which do not have source
+    /// code, such as those added from a file descriptor, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with kind [`InvalidInput`](io::ErrorKind::InvalidInput), without writing any files, if the
+    /// name of a file is not a relative path within `dir`, such as `/tmp/foo.proto` or `../foo.proto`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// let mut compiler = Compiler::with_file_resolver(protox::file::GoogleFileResolver::new());
+    /// compiler.add_file_with_content("foo/bar.proto", "import 'google/protobuf/empty.proto';").unwrap();
+    /// compiler.export_to_directory(tempdir.path()).unwrap();
+    ///
+    /// assert!(tempdir.path().join("foo/bar.proto").exists());
+    /// assert!(tempdir.path().join("google/protobuf/empty.proto").exists());
+    /// ```
+    pub fn export_to_directory(&self, dir: &Path) -> io::Result<()> {
+        if let Some(file) = self
+            .files()
+            .find(|file| file.source.is_some() && !is_relative_file_name(&file.name))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("file name '{}' is not a relative path", file.name),
+            ));
+        }
+
+        for file in self.files() {
+            if let Some(source) = &file.source {
+                let path = dir.join(&file.name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, source)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a Makefile dependency file, listing the path of every source file read from the file system as a
+    /// prerequisite of `target`.
+    ///
+    /// This format is understood by build systems such as Make, CMake and Ninja, so that `target` is rebuilt when any
+    /// of the source files change. Files which were not read from the file system, such as the built-in
+    /// `google/protobuf/` files, are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("foo.proto", "import 'google/protobuf/empty.proto';").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."]).unwrap();
+    /// compiler.open_file("foo.proto").unwrap();
+    ///
+    /// let mut depfile = Vec::new();
+    /// compiler.write_depfile("out.bin", &mut depfile).unwrap();
+    /// assert_eq!(String::from_utf8(depfile).unwrap(), "out.bin: \\\n  ./foo.proto\n");
+    /// ```
+    pub fn write_depfile(&self, target: &str, writer: &mut impl io::Write) -> io::Result<()> {
+        self.write_depfile_with_prerequisites(target, &[], writer)
+    }
+
+    /// Writes a Makefile dependency file, as for [`write_depfile()`](Compiler::write_depfile), with additional
+    /// prerequisites listed before the source files.
+    ///
+    /// This can be used to include files which were read by other means, such as file descriptor sets passed to a
+    /// [`DescriptorSetFileResolver`](crate::file::DescriptorSetFileResolver).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::{fs, path::Path};
+    /// # use protox::Compiler;
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("foo.proto", "").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."]).unwrap();
+    /// compiler.open_file("foo.proto").unwrap();
+    ///
+    /// let mut depfile = Vec::new();
+    /// compiler
+    ///     .write_depfile_with_prerequisites("out.bin", &[Path::new("deps.bin")], &mut depfile)
+    ///     .unwrap();
+    /// assert_eq!(String::from_utf8(depfile).unwrap(), "out.bin: \\\n  deps.bin \\\n  ./foo.proto\n");
+    /// ```
+    pub fn write_depfile_with_prerequisites(
+        &self,
+        target: &str,
+        prerequisites: &[&Path],
+        writer: &mut impl io::Write,
+    ) -> io::Result<()> {
+        write!(writer, "{}:", escape_depfile_path(target))?;
+        let paths = self.files().filter_map(|file| file.path());
+        for path in prerequisites.iter().copied().chain(paths) {
+            write!(
+                writer,
+                " \\\n  {}",
+                escape_depfile_path(&path.to_string_lossy())
+            )?;
+        }
+        writeln!(writer)
+    }
+
     /// Gets a copy of the [`DescriptorPool`] containing all referenced files.
     pub fn descriptor_pool(&self) -> DescriptorPool {
         self.pool.clone()
     }
 
+    /// Gets the source code info of an added file, if it has any.
+    ///
+    /// Source code info is retained for all files parsed from source, regardless of the
+    /// [`include_source_info()`](Compiler::include_source_info) option, which only affects the output file descriptor
+    /// set. This can be used, for example, to map generated code annotations back to locations in the source.
+    pub fn source_info_for(&self, file_name: &str) -> Option<&SourceCodeInfo> {
+        self.pool
+            .file_descriptor_protos()
+            .find(|file| file.name() == file_name)?
+            .source_code_info
+            .as_ref()
+    }
+
+    /// Looks up a message by its fully-qualified name, such as `acme.v1.Foo` or `.acme.v1.Foo`.
+    ///
+    /// Unlike calling [`descriptor_pool()`](Compiler::descriptor_pool) and then
+    /// [`DescriptorPool::get_message_by_name`], this does not clone the pool.
+    pub fn describe_message(&self, name: &str) -> Option<MessageDescriptor> {
+        self.pool.get_message_by_name(strip_leading_dot(name))
+    }
+
+    /// Looks up an enum by its fully-qualified name, such as `acme.v1.Bar` or `.acme.v1.Bar`.
+    pub fn describe_enum(&self, name: &str) -> Option<EnumDescriptor> {
+        self.pool.get_enum_by_name(strip_leading_dot(name))
+    }
+
+    /// Looks up a service by its fully-qualified name, such as `acme.v1.Baz` or `.acme.v1.Baz`.
+    pub fn describe_service(&self, name: &str) -> Option<ServiceDescriptor> {
+        self.pool.get_service_by_name(strip_leading_dot(name))
+    }
+
+    /// Lists the names of all protobuf source files which can be found by the file resolver, sorted by name.
+    ///
+    /// For a compiler created by [`Compiler::new`], this includes every `.proto` file under each include path, but
+    /// not the built-in `google/protobuf` files. Resolvers which cannot enumerate their files, such as those added
+    /// by [`with_file_resolver()`](Compiler::with_file_resolver), contribute no files unless they implement
+    /// [`FileResolver::list_files`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// # fn main() -> Result<(), protox::Error> {
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// std::fs::write("foo.proto", "message Foo {}").unwrap();
+    /// std::fs::write("bar.proto", "message Bar {}").unwrap();
+    ///
+    /// let mut compiler = Compiler::new(["."])?;
+    /// let files = compiler.list_files()?;
+    /// assert_eq!(files, ["bar.proto", "foo.proto"]);
+    /// compiler.open_files(files)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_files(&self) -> Result<Vec<String>, Error> {
+        let mut files = self.resolver.list_files()?;
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Gets the warnings found after parsing the files added by [`open_file`](Compiler::open_file).
+    ///
+    /// Warnings found while parsing are available from [`File::warnings()`].
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// Finds extension ranges of messages in the files added by [`open_file`](Compiler::open_file) which are not used.
+    ///
+    /// A warning is returned for each extension range of a message, if no extension in this `Compiler` extends any
+    /// of the message's ranges. Since extensions may be defined in files compiled later, this should be called once
+    /// all files have been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::Compiler;
+    /// let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    /// compiler
+    ///     .add_file_with_content("foo.proto", "
+    ///         message Foo { extensions 100 to 199; }
+    ///         message Bar { extensions 100 to 199; }
+    ///         extend Bar { optional int32 bar = 100; }
+    ///     ")
+    ///     .unwrap();
+    ///
+    /// let warnings = compiler.unused_extension_ranges();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(
+    ///     warnings[0].to_string(),
+    ///     "extension range 100 to 199 of message 'Foo' is not used by any extension",
+    /// );
+    /// ```
+    pub fn unused_extension_ranges(&self) -> Vec<Error> {
+        let mut warnings = Vec::new();
+        for file in self.sorted_files() {
+            match self.files.get(file.name()) {
+                Some(metadata) if !metadata.is_import => check::check_unused_extension_ranges(
+                    &file,
+                    metadata.source.as_deref(),
+                    &mut warnings,
+                ),
+                _ => (),
+            }
+        }
+        warnings
+    }
+
     /// Gets a reference to all imported source files.
     ///
     /// The files will appear in topological order, so each file appears before any file that imports it.
@@ -249,7 +1577,166 @@ impl Compiler {
         self.pool.files().map(|f| &self.files[f.name()])
     }
 
-    fn add_import(&mut self, file_name: &str, import_stack: &mut Vec<String>) -> Result<(), Error> {
+    /// Gets a reference to the files added explicitly by [`open_file()`](Compiler::open_file), excluding any files
+    /// which were only added as imports.
+    ///
+    /// The files will appear in topological order, so each file appears before any file that imports it.
+    pub fn roots(&self) -> impl Iterator<Item = &'_ FileMetadata> {
+        self.files().filter(|f| !f.is_import)
+    }
+
+    /// Takes the current resolver, wrapped in a [`ChainFileResolver`] so more resolvers can be added.
+    fn take_resolver(&mut self) -> ChainFileResolver {
+        let mut chain = ChainFileResolver::new();
+        chain.add(mem::replace(
+            &mut self.resolver,
+            Box::new(ChainFileResolver::new()),
+        ));
+        chain
+    }
+
+    fn strip_name_prefix<'a>(&self, name: &'a str) -> &'a str {
+        match &self.name_prefix_strip {
+            Some(prefix) => name
+                .strip_prefix(prefix.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+                .unwrap_or(name),
+            None => name,
+        }
+    }
+
+    /// Checks that no other file has the same name as `name` after removing the name prefix.
+    fn check_stripped_name(&self, name: &str) -> Result<(), Error> {
+        if self.name_prefix_strip.is_none() {
+            return Ok(());
+        }
+
+        let stripped_name = self.strip_name_prefix(name);
+        match self
+            .files
+            .keys()
+            .find(|other| *other != name && self.strip_name_prefix(other) == stripped_name)
+        {
+            Some(first_name) => Err(Error::from_kind(ErrorKind::StrippedNameConflict {
+                name: name.to_owned(),
+                first_name: first_name.clone(),
+                stripped_name: stripped_name.to_owned(),
+            })),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the files in the pool, ordered by depth in the import graph and then by name.
+    fn sorted_files(&self) -> Vec<FileDescriptor> {
+        let mut depths: HashMap<String, usize> = HashMap::with_capacity(self.files.len());
+        let mut files: Vec<(usize, FileDescriptor)> = self
+            .pool
+            .files()
+            .map(|file| {
+                // The pool is topologically sorted, so all dependencies have already been visited.
+                let depth = file
+                    .dependencies()
+                    .map(|dep| depths[dep.name()] + 1)
+                    .max()
+                    .unwrap_or(0);
+                depths.insert(file.name().to_owned(), depth);
+                (depth, file)
+            })
+            .collect();
+
+        files.sort_by(|(l_depth, l), (r_depth, r)| {
+            l_depth.cmp(r_depth).then_with(|| l.name().cmp(r.name()))
+        });
+        files.into_iter().map(|(_, file)| file).collect()
+    }
+
+    fn add_descriptor_set_resolver(
+        &mut self,
+        set: DescriptorSetFileResolver,
+    ) -> Result<&mut Self, Error> {
+        let names: Vec<String> = set.file_names().map(ToOwned::to_owned).collect();
+        self.prepend_resolver(set);
+
+        for name in names {
+            self.add_import(&name, &mut Vec::new(), &mut |_| ())?;
+        }
+        Ok(self)
+    }
+
+    fn add_root_file(
+        &mut self,
+        name: String,
+        mut file: File,
+        on_success: &mut dyn FnMut(&FileMetadata),
+    ) -> Result<&mut Self, Error> {
+        self.check_stripped_name(&name)?;
+
+        let mut import_stack = vec![name.clone()];
+        self.add_imports(&mut file, &mut import_stack, on_success)?;
+        drop(import_stack);
+
+        if self.error_on_unused_import {
+            check::check_unused_imports(
+                &self.pool,
+                &file.descriptor,
+                file.source.as_deref(),
+                file.encoded.is_some(),
+            )?;
+        }
+
+        // Lints which are treated as errors need the resolved file, so the file must be removed from the pool if
+        // they fail.
+        let checkpoint = (self.warning_as_error
+            && (self.max_message_nesting_depth.is_some() || self.deprecation_check))
+            .then(|| self.pool.clone());
+        let (path, source) = self.check_file(file)?;
+
+        let file = self
+            .pool
+            .get_file_by_name(&name)
+            .expect("file not found in pool");
+        let mut result = Ok(());
+        if let (Ok(()), Some(max_depth)) = (&result, self.max_message_nesting_depth) {
+            if let Err(warning) =
+                check::check_message_nesting_depth(&file, source.as_deref(), max_depth)
+            {
+                if self.warning_as_error {
+                    result = Err(warning);
+                } else {
+                    self.warnings.push(warning);
+                }
+            }
+        }
+        if result.is_ok() && self.deprecation_check {
+            let mut warnings = check::check_deprecated_types(&file, source.as_deref());
+            if self.warning_as_error && !warnings.is_empty() {
+                result = Err(warnings.swap_remove(0));
+            } else {
+                self.warnings.extend(warnings);
+            }
+        }
+        if let Err(err) = result {
+            self.pool = checkpoint.expect("pool checkpoint not taken");
+            return Err(err);
+        }
+
+        let file_metadata = FileMetadata {
+            name: name.clone(),
+            path,
+            source,
+            is_import: false,
+        };
+        on_success(&file_metadata);
+        self.files.insert(name, file_metadata);
+        Ok(self)
+    }
+
+    fn add_import(
+        &mut self,
+        file_name: &str,
+        import_stack: &mut Vec<String>,
+        on_success: &mut dyn FnMut(&FileMetadata),
+    ) -> Result<(), Error> {
         if import_stack.iter().any(|name| name == file_name) {
             let mut cycle = String::new();
             for import in import_stack {
@@ -260,56 +1747,211 @@ impl Compiler {
             return Err(Error::from_kind(ErrorKind::CircularImport {
                 name: file_name.to_owned(),
                 cycle,
+                imports: Vec::new(),
             }));
         }
 
         if self.files.contains_key(file_name) {
             return Ok(());
         }
+        self.check_stripped_name(file_name)?;
 
-        let file = self.resolver.open_file(file_name)?;
+        let mut file = self.resolve_file(file_name)?;
 
         import_stack.push(file_name.to_owned());
+        self.add_imports(&mut file, import_stack, on_success)?;
+        import_stack.pop();
+
+        let (path, source) = self.check_file(file)?;
+        let file_metadata = FileMetadata {
+            name: file_name.to_owned(),
+            path,
+            source,
+            is_import: true,
+        };
+        on_success(&file_metadata);
+        self.files.insert(file_name.to_owned(), file_metadata);
+        Ok(())
+    }
+
+    fn add_imports(
+        &mut self,
+        file: &mut File,
+        import_stack: &mut Vec<String>,
+        on_success: &mut dyn FnMut(&FileMetadata),
+    ) -> Result<(), Error> {
+        let mut missing_weak_imports = Vec::new();
         for (i, import) in file.descriptor.dependency.iter().enumerate() {
-            self.add_import(import, import_stack)
-                .map_err(|e| e.into_import_error(&file, i))?;
+            if !self.is_import_allowed(import) {
+                return Err(Error::import_not_allowed(file, i));
+            }
+
+            match self.add_import(import, import_stack, on_success) {
+                Ok(()) => (),
+                Err(err)
+                    if matches!(err.kind(), ErrorKind::FileNotFound { .. })
+                        && file.descriptor.weak_dependency.contains(&(i as i32))
+                        && !self.protoc_compatibility =>
+                {
+                    // Weak imports are allowed to be missing, unlike in protoc.
+                    missing_weak_imports.push(i);
+                }
+                Err(err) => return Err(err.into_import_error(file, i)),
+            }
         }
-        import_stack.pop();
 
-        let path = self.check_file(file)?;
-        self.files.insert(
-            file_name.to_owned(),
-            FileMetadata {
-                name: file_name.to_owned(),
-                path,
-                is_import: true,
-            },
-        );
+        if !missing_weak_imports.is_empty() {
+            remove_dependencies(&mut file.descriptor, &missing_weak_imports);
+            file.encoded = None;
+        }
         Ok(())
     }
 
-    fn check_file(
+    fn apply_shadow_policy(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        if self.protoc_compatibility {
+            return result;
+        }
+        match (result, self.shadow_policy) {
+            (Err(warning), ShadowPolicy::Warn) if !self.warning_as_error => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+            (Err(_), ShadowPolicy::Allow) => Ok(()),
+            (result, _) => result,
+        }
+    }
+
+    fn is_import_allowed(&self, name: &str) -> bool {
+        match &self.allowed_google_imports {
+            Some(allowed) if name.starts_with("google/protobuf/") => allowed.contains(name),
+            _ => true,
+        }
+    }
+
+    fn resolve_file(&mut self, name: &str) -> Result<File, Error> {
+        #[cfg(feature = "timing")]
+        let start = self.stats.is_some().then(std::time::Instant::now);
+
+        let result = self.resolver.open_file(name);
+
+        #[cfg(feature = "timing")]
+        if let (Some(start), Some(stats)) = (start, &mut self.stats) {
+            stats.record_parse(name, start.elapsed());
+        }
+        result
+    }
+
+    /// Checks a file and adds it to the pool, returning its path and source.
+    fn check_file(&mut self, file: File) -> Result<(Option<PathBuf>, Option<String>), Error> {
+        #[cfg(feature = "timing")]
+        let start = self
+            .stats
+            .is_some()
+            .then(|| (file.name().to_owned(), std::time::Instant::now()));
+
+        let result = self.check_file_inner(file);
+
+        #[cfg(feature = "timing")]
+        if let (Some((name, start)), Some(stats)) = (start, &mut self.stats) {
+            stats.record_check(&name, start.elapsed());
+        }
+        result
+    }
+
+    fn check_file_inner(
         &mut self,
         File {
             path,
             source,
             descriptor,
             encoded,
+            warnings,
+            ..
         }: File,
-    ) -> Result<Option<PathBuf>, Error> {
-        if let Some(encoded) = &encoded {
+    ) -> Result<(Option<PathBuf>, Option<String>), Error> {
+        if (self.warning_as_error || self.protoc_compatibility) && !warnings.is_empty() {
+            return Err(Error::from_kind(ErrorKind::WarningsAsErrors {
+                name: descriptor.name().to_owned(),
+                warnings,
+            }));
+        }
+
+        if self.require_syntax_statement && source.is_some() {
+            check::check_syntax_statement(&descriptor)?;
+        }
+        if self.require_package_declaration {
+            check::check_package_declaration(&descriptor)?;
+        }
+        check::check_reserved_names(&descriptor, source.as_deref())?;
+        if self.check_extension_range_overlap || self.protoc_compatibility {
+            check::check_extension_range_overlap(&descriptor, source.as_deref())?;
+        }
+        check::check_synthetic_oneof_names(&descriptor, source.as_deref())?;
+        check::check_duplicate_names(&self.pool, &descriptor, source.as_deref(), |name| {
+            self.files.get(name).and_then(|file| file.source.as_deref())
+        })?;
+        check::check_package_conflicts(&self.pool, &descriptor)?;
+        let lists: Vec<_> = warnings
+            .iter()
+            .filter(|warning| warning.is_list_option_value())
+            .map(|warning| warning.span())
+            .collect();
+        check::check_list_options(&self.pool, &descriptor, source.as_deref(), &lists)?;
+
+        check::check_map_entry_fields(&self.pool, &descriptor, source.as_deref())?;
+        if let Some(validator) = &self.json_name_validator {
+            check::check_json_names(&descriptor, source.as_deref(), validator)?;
+        }
+
+        let result = if let Some(encoded) = &encoded {
             self.pool.decode_file_descriptor_proto(encoded.clone())
+        } else if check::has_aggregate_options(&descriptor)
+            || !descriptor.service.is_empty()
+            || check::has_extensions(&descriptor)
+            || check::has_negative_int_options(&descriptor)
+        {
+            let result = self.pool.add_file_descriptor_proto(descriptor.clone());
+            if let Err(err) = &result {
+                // Give a more specific error for unknown fields in aggregate option values, negative values for
+                // unsigned options, unknown method types, or invalid extensions. The pool is left unchanged if a
+                // file fails to be added.
+                if let Some(err) =
+                    check::check_aggregate_options(&self.pool, &descriptor, source.as_deref())
+                        .or_else(|| {
+                            check::check_negative_unsigned_options(
+                                &self.pool,
+                                &descriptor,
+                                source.as_deref(),
+                            )
+                        })
+                        .or_else(|| {
+                            check::check_method_types(
+                                &self.pool,
+                                &descriptor,
+                                source.as_deref(),
+                                err,
+                            )
+                        })
+                        .or_else(|| {
+                            check::check_extendees(&self.pool, &descriptor, source.as_deref())
+                        })
+                {
+                    return Err(err);
+                }
+            }
+            result
         } else {
             self.pool.add_file_descriptor_proto(descriptor)
-        }
-        .map_err(|mut err| {
-            if let Some(source) = source {
-                err = err.with_source_code(&source);
+        };
+
+        result.map_err(|mut err| {
+            if let Some(source) = &source {
+                err = err.with_source_code(source);
             }
             err
         })?;
 
-        Ok(path)
+        Ok((path, source))
     }
 }
 
@@ -318,6 +1960,92 @@ impl fmt::Debug for Compiler {
         f.debug_struct("Compiler")
             .field("include_imports", &self.include_imports)
             .field("include_source_info", &self.include_source_info)
+            .field("error_on_unused_import", &self.error_on_unused_import)
+            .field("warning_as_error", &self.warning_as_error)
+            .field("protoc_compatibility", &self.protoc_compatibility)
+            .field("require_syntax_statement", &self.require_syntax_statement)
+            .field(
+                "require_package_declaration",
+                &self.require_package_declaration,
+            )
+            .field(
+                "check_extension_range_overlap",
+                &self.check_extension_range_overlap,
+            )
+            .field("deprecation_check", &self.deprecation_check)
             .finish_non_exhaustive()
     }
 }
+
+/// Returns true if `name` is a relative path with no `..` components, so it can be joined to a directory without
+/// escaping it.
+fn is_relative_file_name(name: &str) -> bool {
+    Path::new(name)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// The field number of `FileDescriptorProto.dependency`.
+const FILE_DEPENDENCY_TAG: i32 = 3;
+
+/// Removes the imports at the given (sorted) indices from a file, updating any references to the remaining imports.
+fn remove_dependencies(file: &mut FileDescriptorProto, removed: &[usize]) {
+    let remap = |index: i32| -> Option<i32> {
+        let index = index as usize;
+        match removed.binary_search(&index) {
+            Ok(_) => None,
+            Err(offset) => Some((index - offset) as i32),
+        }
+    };
+
+    let mut index = 0;
+    file.dependency.retain(|_| {
+        let keep = removed.binary_search(&index).is_err();
+        index += 1;
+        keep
+    });
+    file.public_dependency = file
+        .public_dependency
+        .iter()
+        .filter_map(|&i| remap(i))
+        .collect();
+    file.weak_dependency = file
+        .weak_dependency
+        .iter()
+        .filter_map(|&i| remap(i))
+        .collect();
+
+    if let Some(source_code_info) = &mut file.source_code_info {
+        source_code_info.location.retain_mut(|location| {
+            if location.path.len() < 2 || location.path[0] != FILE_DEPENDENCY_TAG {
+                return true;
+            }
+
+            match remap(location.path[1]) {
+                Some(index) => {
+                    location.path[1] = index;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+/// Escapes characters which have a special meaning in a Makefile rule.
+fn escape_depfile_path(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for ch in path.chars() {
+        match ch {
+            ' ' | '#' => escaped.push('\\'),
+            '$' => escaped.push('$'),
+            _ => (),
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+fn strip_leading_dot(name: &str) -> &str {
+    name.strip_prefix('.').unwrap_or(name)
+}
@@ -0,0 +1,57 @@
+use std::{ops::Deref, sync::Arc};
+
+use prost_reflect::DescriptorPool;
+
+/// A [`DescriptorPool`] of already-compiled files which can be shared between many [`Compiler`](crate::Compiler)
+/// instances.
+///
+/// Files in the shared pool are not resolved or checked again when imported by a `Compiler` created with
+/// [`Compiler::with_shared_pool()`](crate::Compiler::with_shared_pool). This is useful for long-running processes
+/// which repeatedly compile files with a common set of dependencies, such as the well-known types.
+///
+/// Cloning a `SharedDescriptorPool` is cheap.
+///
+/// # Examples
+///
+/// ```
+/// # use protox::{Compiler, SharedDescriptorPool};
+/// # fn main() -> Result<(), protox::Error> {
+/// let mut common = Compiler::new::<_, &str>([])?;
+/// common.open_file("google/protobuf/timestamp.proto")?;
+/// let shared = SharedDescriptorPool::new(common.descriptor_pool());
+///
+/// for name in ["foo.proto", "bar.proto"] {
+///     let mut compiler = Compiler::with_shared_pool(shared.clone());
+///     compiler.add_file_with_content(name, "import 'google/protobuf/timestamp.proto';")?;
+///     assert_eq!(compiler.file_descriptor_set().file.len(), 1);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SharedDescriptorPool {
+    pool: Arc<DescriptorPool>,
+}
+
+impl SharedDescriptorPool {
+    /// Creates a new `SharedDescriptorPool` containing the files in the given pool.
+    pub fn new(pool: DescriptorPool) -> Self {
+        SharedDescriptorPool {
+            pool: Arc::new(pool),
+        }
+    }
+}
+
+impl Deref for SharedDescriptorPool {
+    type Target = DescriptorPool;
+
+    fn deref(&self) -> &DescriptorPool {
+        &self.pool
+    }
+}
+
+impl From<DescriptorPool> for SharedDescriptorPool {
+    fn from(pool: DescriptorPool) -> Self {
+        SharedDescriptorPool::new(pool)
+    }
+}
@@ -0,0 +1,42 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Timing information collected while compiling files, returned by [`Compiler::stats()`](crate::Compiler::stats).
+///
+/// Name resolution and option interpretation are both performed when a file is added to the
+/// [`DescriptorPool`](prost_reflect::DescriptorPool), so they are included in the check phase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilationStats {
+    /// The total time spent opening and parsing files.
+    pub parse_duration: Duration,
+    /// The total time spent resolving and checking parsed files.
+    pub check_duration: Duration,
+    /// The time spent on each file, keyed by file name.
+    pub per_file: HashMap<String, FileDuration>,
+}
+
+/// Timing information for a single file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileDuration {
+    /// The time spent opening and parsing the file.
+    pub parse_duration: Duration,
+    /// The time spent resolving and checking the file.
+    pub check_duration: Duration,
+}
+
+impl CompilationStats {
+    pub(super) fn record_parse(&mut self, name: &str, duration: Duration) {
+        self.parse_duration += duration;
+        self.per_file
+            .entry(name.to_owned())
+            .or_default()
+            .parse_duration += duration;
+    }
+
+    pub(super) fn record_check(&mut self, name: &str, duration: Duration) {
+        self.check_duration += duration;
+        self.per_file
+            .entry(name.to_owned())
+            .or_default()
+            .check_duration += duration;
+    }
+}
@@ -1,5 +1,7 @@
 use std::{fs, iter::once};
 
+use miette::SourceSpan;
+use protox_parse::ParseOptions;
 use tempfile::TempDir;
 
 use super::*;
@@ -743,6 +745,57 @@ fn shadow_file_abs_subdir() {
     }
 }
 
+#[test]
+fn shadow_file_policy() {
+    let dir = TempDir::new().unwrap();
+
+    fs::create_dir_all(dir.path().join("include1")).unwrap();
+    std::fs::write(
+        dir.path().join("include1").join("foo.proto"),
+        "message Foo {}",
+    )
+    .unwrap();
+
+    fs::create_dir_all(dir.path().join("include2")).unwrap();
+    std::fs::write(dir.path().join("include2").join("foo.proto"), EMPTY).unwrap();
+
+    let path = dir.path().join("include2").join("foo.proto");
+    let compiler = |policy| {
+        let mut compiler =
+            Compiler::new([dir.path().join("include1"), dir.path().join("include2")]).unwrap();
+        compiler.with_shadow_policy(policy);
+        compiler
+    };
+
+    let mut allow = compiler(ShadowPolicy::Allow);
+    allow.open_file(&path).unwrap();
+    assert_eq!(
+        allow.files().next().unwrap().path(),
+        Some(dir.path().join("include1").join("foo.proto").as_ref())
+    );
+    assert!(allow.warnings().is_empty());
+
+    let mut warn = compiler(ShadowPolicy::Warn);
+    warn.open_file(&path).unwrap();
+    warn.open_file(&path).unwrap();
+    assert_eq!(warn.warnings().len(), 2);
+    assert!(matches!(
+        warn.warnings()[0].kind(),
+        ErrorKind::FileShadowed { .. }
+    ));
+
+    let mut strict = compiler(ShadowPolicy::Warn);
+    strict.with_warning_as_error(true);
+    let err = strict.open_file(&path).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::FileShadowed { .. }));
+    assert!(strict.warnings().is_empty());
+
+    let mut protoc = compiler(ShadowPolicy::Allow);
+    protoc.with_protoc_compatibility_mode(true);
+    let err = protoc.open_file(&path).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::FileShadowed { .. }));
+}
+
 #[test]
 fn shadow_invalid_file() {
     let dir = TempDir::new().unwrap();
@@ -865,6 +918,113 @@ fn import_files_include_imports_path_already_imported() {
     assert_eq!(file_descriptor_set.file[1].name(), "root1.proto");
 }
 
+#[test]
+fn file_descriptor_set_filtered() {
+    let dir = TempDir::new().unwrap();
+
+    std::fs::write(dir.path().join("dep.proto"), "").unwrap();
+    std::fs::write(dir.path().join("dep2.proto"), "import 'dep.proto';").unwrap();
+    std::fs::write(dir.path().join("root.proto"), "import 'dep2.proto';").unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler.include_imports(true);
+    compiler.open_file("root.proto").unwrap();
+
+    let file_descriptor_set = compiler.file_descriptor_set_filtered(|f| f.name() != "dep2.proto");
+    assert_eq!(file_descriptor_set.file.len(), 2);
+    assert_eq!(file_descriptor_set.file[0].name(), "dep.proto");
+    assert_eq!(file_descriptor_set.file[1].name(), "root.proto");
+
+    let file_descriptor_set = compiler.file_descriptor_set_for_roots_only();
+    assert_eq!(file_descriptor_set.file.len(), 1);
+    assert_eq!(file_descriptor_set.file[0].name(), "root.proto");
+}
+
+#[test]
+fn open_file_descriptor_proto() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("dep.proto"), "package dep; message Dep {}").unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler
+        .open_file_descriptor_proto(
+            protox_parse::parse(
+                "root.proto",
+                "import 'dep.proto'; message Foo { optional dep.Dep dep = 1; }",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        compiler.files().map(|f| f.name()).collect::<Vec<_>>(),
+        ["dep.proto", "root.proto"]
+    );
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["root.proto"]
+    );
+    assert_eq!(
+        compiler.file_descriptor_set().file[0].message_type[0].field[0].type_name(),
+        ".dep.Dep"
+    );
+
+    let err = compiler
+        .open_file_descriptor_proto(FileDescriptorProto::default())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "file descriptor must have a name");
+}
+
+#[test]
+fn add_file_with_content() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("dep.proto"), "message Dep {}").unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "import 'dep.proto'; message Foo { optional Dep dep = 1; }",
+        )
+        .unwrap();
+
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["root.proto"]
+    );
+    assert_eq!(compiler.files().nth(1).unwrap().path(), None);
+
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "import 'dep.proto'; message Foo { optional Dep dep = 1; }",
+        )
+        .unwrap();
+    let err = compiler
+        .add_file_with_content("root.proto", "message Bar {}")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "file 'root.proto' has already been added with different content"
+    );
+    assert_eq!(err.file(), Some("root.proto"));
+
+    // An import read from the file system is added as a root if the content is the same.
+    compiler
+        .add_file_with_content("dep.proto", "message Dep {}")
+        .unwrap();
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["dep.proto", "root.proto"]
+    );
+
+    let err = compiler
+        .add_file_with_content("invalid.proto", "message {")
+        .unwrap_err();
+    assert!(err.is_parse());
+    assert_eq!(err.file(), Some("invalid.proto"));
+}
+
 #[test]
 fn import_cycle() {
     let dir = TempDir::new().unwrap();
@@ -883,9 +1043,24 @@ fn import_cycle() {
     let err = compiler.open_file("root.proto").unwrap_err();
 
     match err.kind() {
-        ErrorKind::CircularImport { name, cycle } => {
+        ErrorKind::CircularImport {
+            name,
+            cycle,
+            imports,
+        } => {
             assert_eq!(name, "root.proto");
-            assert_eq!(cycle, "root.proto -> dep.proto -> dep2.proto -> root.proto")
+            assert_eq!(cycle, "root.proto -> dep.proto -> dep2.proto -> root.proto");
+            assert_eq!(
+                imports
+                    .iter()
+                    .map(|i| (i.source_code.name(), i.name.as_str(), i.span))
+                    .collect::<Vec<_>>(),
+                [
+                    ("root.proto", "dep.proto", Some(SourceSpan::from((0, 19)))),
+                    ("dep.proto", "dep2.proto", Some(SourceSpan::from((0, 20)))),
+                    ("dep2.proto", "root.proto", Some(SourceSpan::from((0, 20)))),
+                ]
+            );
         }
         kind => panic!("unexpected error: {}", kind),
     }
@@ -902,9 +1077,20 @@ fn import_cycle_short() {
     let err = compiler.open_file("root.proto").unwrap_err();
 
     match err.kind() {
-        ErrorKind::CircularImport { name, cycle } => {
+        ErrorKind::CircularImport {
+            name,
+            cycle,
+            imports,
+        } => {
             assert_eq!(name, "dep.proto");
-            assert_eq!(cycle, "root.proto -> dep.proto -> dep.proto")
+            assert_eq!(cycle, "root.proto -> dep.proto -> dep.proto");
+            assert_eq!(
+                imports
+                    .iter()
+                    .map(|i| (i.source_code.name(), i.name.as_str(), i.span))
+                    .collect::<Vec<_>>(),
+                [("dep.proto", "dep.proto", Some(SourceSpan::from((0, 19)))),]
+            );
         }
         kind => panic!("unexpected error: {}", kind),
     }
@@ -920,14 +1106,103 @@ fn import_cycle_nested() {
     let err = compiler.open_file("root.proto").unwrap_err();
 
     match err.kind() {
-        ErrorKind::CircularImport { name, cycle } => {
+        ErrorKind::CircularImport {
+            name,
+            cycle,
+            imports,
+        } => {
             assert_eq!(name, "root.proto");
-            assert_eq!(cycle, "root.proto -> root.proto")
+            assert_eq!(cycle, "root.proto -> root.proto");
+            assert_eq!(
+                imports
+                    .iter()
+                    .map(|i| (i.source_code.name(), i.name.as_str(), i.span))
+                    .collect::<Vec<_>>(),
+                [("root.proto", "root.proto", Some(SourceSpan::from((0, 20)))),]
+            );
         }
         kind => panic!("unexpected error: {}", kind),
     }
 }
 
+#[test]
+fn weak_import_missing() {
+    let dir = TempDir::new().unwrap();
+
+    std::fs::write(
+        dir.path().join("root.proto"),
+        "import weak 'notfound.proto'; import public 'dep.proto'; message Foo { optional Dep dep = 1; }",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("dep.proto"), "message Dep {}").unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler.include_source_info(true);
+    compiler.open_file("root.proto").unwrap();
+
+    let file = &compiler.file_descriptor_set().file[0];
+    assert_eq!(file.dependency, ["dep.proto"]);
+    assert_eq!(file.public_dependency, [0]);
+    assert!(file.weak_dependency.is_empty());
+    assert!(file
+        .source_code_info
+        .as_ref()
+        .unwrap()
+        .location
+        .iter()
+        .any(|location| location.path == [3, 0] && location.span == [0, 30, 56]));
+}
+
+#[test]
+fn weak_import_missing_protoc_compatibility() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_protoc_compatibility_mode(true);
+
+    let err = compiler
+        .add_file_with_content("root.proto", "import weak 'notfound.proto';")
+        .unwrap_err();
+    assert!(
+        matches!(err.kind(), ErrorKind::ImportNotFound { .. }),
+        "{err}"
+    );
+}
+
+#[test]
+fn stream_method_type_protoc_compatibility() {
+    let source = "message stream {} service Foo { rpc Bar(stream) returns (stream stream); }";
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_protoc_compatibility_mode(true);
+    let err = compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap_err();
+    assert!(
+        matches!(err.kind(), ErrorKind::WarningsAsErrors { .. }),
+        "{err}"
+    );
+}
+
+#[test]
+fn weak_import_nested_missing() {
+    let dir = TempDir::new().unwrap();
+
+    std::fs::write(dir.path().join("root.proto"), "import weak 'dep.proto';").unwrap();
+    std::fs::write(dir.path().join("dep.proto"), "import 'notfound.proto';").unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    let err = compiler.open_file("root.proto").unwrap_err();
+
+    match err.kind() {
+        ErrorKind::ImportNotFound { name, .. } => assert_eq!(name, "notfound.proto"),
+        kind => panic!("unexpected error: {}", kind),
+    }
+}
+
 #[test]
 fn duplicated_import() {
     let dir = TempDir::new().unwrap();
@@ -1006,3 +1281,1140 @@ fn open_file_case_insensitive() {
         "foo.proto",
     );
 }
+
+#[test]
+#[cfg(feature = "timing")]
+fn stats_collection() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("dep.proto"), "message Dep {}").unwrap();
+    std::fs::write(
+        dir.path().join("root.proto"),
+        "import 'dep.proto'; message Foo { optional Dep dep = 1; }",
+    )
+    .unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    assert_eq!(compiler.stats(), None);
+
+    compiler.with_stats_collection(true);
+    compiler.open_file("root.proto").unwrap();
+
+    let stats = compiler.stats().unwrap();
+    let mut names: Vec<_> = stats.per_file.keys().map(String::as_str).collect();
+    names.sort();
+    assert_eq!(names, ["dep.proto", "root.proto"]);
+    assert_eq!(
+        stats.parse_duration,
+        stats.per_file.values().map(|f| f.parse_duration).sum()
+    );
+    assert_eq!(
+        stats.check_duration,
+        stats.per_file.values().map(|f| f.check_duration).sum()
+    );
+
+    compiler.with_stats_collection(false);
+    assert_eq!(compiler.stats(), None);
+}
+
+#[test]
+fn with_proto_path_env() {
+    let first = TempDir::new().unwrap();
+    let second = TempDir::new().unwrap();
+    std::fs::write(second.path().join("dep.proto"), "message Dep {}").unwrap();
+    std::fs::write(
+        first.path().join("root.proto"),
+        "import 'dep.proto'; import 'google/protobuf/empty.proto'; message Foo { optional Dep dep = 1; }",
+    )
+    .unwrap();
+
+    let proto_path = std::env::join_paths([first.path(), second.path()]).unwrap();
+    std::env::set_var("PROTO_PATH", proto_path);
+    let result = Compiler::with_proto_path_env();
+    std::env::remove_var("PROTO_PATH");
+
+    let mut compiler = result.unwrap();
+    compiler.open_file("root.proto").unwrap();
+    assert_eq!(
+        compiler.files().map(|f| f.name()).collect::<Vec<_>>(),
+        ["dep.proto", "google/protobuf/empty.proto", "root.proto"]
+    );
+}
+
+#[test]
+#[cfg(feature = "glob")]
+fn open_files_glob() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src").join("nested")).unwrap();
+    fs::write(dir.path().join("src").join("b.proto"), "message B {}").unwrap();
+    fs::write(dir.path().join("src").join("a.proto"), "message A {}").unwrap();
+    fs::write(
+        dir.path().join("src").join("nested").join("c.proto"),
+        "message C {}",
+    )
+    .unwrap();
+    fs::write(dir.path().join("src").join("d.txt"), "invalid").unwrap();
+
+    with_current_dir(&dir, || {
+        let mut compiler = Compiler::new(["src"]).unwrap();
+        compiler.open_files_glob("src/**/*.proto").unwrap();
+        assert_eq!(
+            compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+            ["a.proto", "b.proto", "nested/c.proto"]
+        );
+
+        assert!(compiler.open_files_glob("src/***.proto").is_err());
+    })
+}
+
+#[test]
+fn include_paths() {
+    let compiler = Compiler::new(["a", "b"]).unwrap();
+    assert_eq!(
+        compiler.include_paths(),
+        [Some(Path::new("a")), Some(Path::new("b")), None]
+    );
+
+    let compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    assert_eq!(compiler.include_paths(), [None]);
+}
+
+#[test]
+fn add_proto_descriptor_set_bytes() {
+    let set = FileDescriptorSet {
+        file: vec![
+            FileDescriptorProto {
+                name: Some("b.proto".to_owned()),
+                dependency: vec!["a.proto".to_owned()],
+                ..Default::default()
+            },
+            FileDescriptorProto {
+                name: Some("a.proto".to_owned()),
+                message_type: vec![prost_types::DescriptorProto {
+                    name: Some("A".to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ],
+    };
+
+    let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    compiler
+        .add_proto_descriptor_set_bytes(set.encode_to_vec().into())
+        .unwrap();
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "import 'a.proto'; import 'b.proto'; message Root { optional A a = 1; }",
+        )
+        .unwrap();
+
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["root.proto"]
+    );
+    assert_eq!(compiler.file_descriptor_set().file.len(), 1);
+    assert_eq!(compiler.files().len(), 3);
+
+    assert!(compiler
+        .add_proto_descriptor_set_bytes(Bytes::from_static(&[255]))
+        .is_err());
+}
+
+#[test]
+fn file_descriptor_set_sorted_by_name() {
+    let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    compiler.include_imports(true);
+    compiler
+        .add_file_with_content("c.proto", "import 'google/protobuf/empty.proto';")
+        .unwrap();
+    compiler.add_file_with_content("b.proto", "").unwrap();
+    compiler.add_file_with_content("a.proto", "").unwrap();
+
+    let names = [
+        "a.proto",
+        "b.proto",
+        "google/protobuf/empty.proto",
+        "c.proto",
+    ];
+    assert_eq!(
+        compiler
+            .file_descriptor_set()
+            .file
+            .iter()
+            .map(|f| f.name())
+            .collect::<Vec<_>>(),
+        names
+    );
+    assert_eq!(
+        compiler.file_descriptor_set_iter().collect::<Vec<_>>(),
+        compiler.file_descriptor_set().file
+    );
+
+    compiler.include_source_info(true);
+    let encoded =
+        FileDescriptorSet::decode(compiler.encode_file_descriptor_set().as_slice()).unwrap();
+    assert_eq!(
+        encoded.file.iter().map(|f| f.name()).collect::<Vec<_>>(),
+        names
+    );
+}
+
+#[test]
+fn prepend_append_resolver() {
+    use crate::file::{DescriptorSetFileResolver, GoogleFileResolver};
+
+    let resolver = |name: &str, message: &str| {
+        DescriptorSetFileResolver::new(FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some(name.to_owned()),
+                message_type: vec![prost_types::DescriptorProto {
+                    name: Some(message.to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        })
+    };
+
+    let mut compiler = Compiler::with_file_resolver(GoogleFileResolver::new());
+    compiler
+        .prepend_resolver(resolver("google/protobuf/empty.proto", "Shadowed"))
+        .append_resolver(resolver("dep.proto", "Dep"))
+        .append_resolver(resolver("dep.proto", "Unused"));
+    assert_eq!(compiler.include_paths(), [None, None, None, None]);
+
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "import 'google/protobuf/empty.proto'; import 'dep.proto';",
+        )
+        .unwrap();
+
+    let pool = compiler.descriptor_pool();
+    assert!(pool.get_message_by_name("Shadowed").is_some());
+    assert!(pool.get_message_by_name("google.protobuf.Empty").is_none());
+    assert!(pool.get_message_by_name("Dep").is_some());
+    assert!(pool.get_message_by_name("Unused").is_none());
+}
+
+#[test]
+fn export_to_directory() {
+    use crate::file::DescriptorSetFileResolver;
+
+    let mut compiler =
+        Compiler::with_file_resolver(DescriptorSetFileResolver::new(FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("dep.proto".to_owned()),
+                ..Default::default()
+            }],
+        }));
+    compiler
+        .add_file_with_content("foo/root.proto", "import 'dep.proto';")
+        .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    compiler.export_to_directory(dir.path()).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("foo/root.proto")).unwrap(),
+        "import 'dep.proto';"
+    );
+    assert!(!dir.path().join("dep.proto").exists());
+}
+
+#[test]
+fn export_to_directory_invalid_name() {
+    let dir = TempDir::new().unwrap();
+    let export_dir = dir.path().join("export");
+
+    for name in ["../escape.proto", "foo/../../escape.proto"] {
+        let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+        compiler.add_file_with_content("root.proto", "").unwrap();
+        compiler.add_file_with_content(name, "").unwrap();
+
+        let err = compiler.export_to_directory(&export_dir).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            format!("file name '{}' is not a relative path", name)
+        );
+        assert!(!dir.path().join("escape.proto").exists());
+        assert!(!export_dir.join("root.proto").exists());
+    }
+
+    let absolute = dir.path().join("absolute.proto");
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content(absolute.to_str().unwrap(), "")
+        .unwrap();
+    let err = compiler.export_to_directory(&export_dir).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!absolute.exists());
+}
+
+#[test]
+fn with_shared_pool() {
+    let mut common = Compiler::new::<_, &str>([]).unwrap();
+    common
+        .add_file_with_content("common.proto", "message Common {}")
+        .unwrap();
+    let shared = SharedDescriptorPool::new(common.descriptor_pool());
+
+    // The shared file is not resolved again, so no resolver is needed to find it.
+    let mut compiler = Compiler::with_shared_pool(shared.clone());
+    compiler.include_imports(true);
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "import 'common.proto'; message Root { optional Common common = 1; }",
+        )
+        .unwrap();
+
+    let names: Vec<_> = compiler.files().map(|f| f.name()).collect();
+    assert_eq!(names, ["common.proto", "root.proto"]);
+    assert!(compiler.files().next().unwrap().is_import());
+    assert_eq!(compiler.file_descriptor_set().file.len(), 2);
+
+    compiler.include_imports(false);
+    assert_eq!(compiler.file_descriptor_set().file.len(), 1);
+    assert!(shared.get_message_by_name("Root").is_none());
+}
+
+#[test]
+fn describe_types() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "package acme.v1; message Foo {} enum Bar { ZERO = 0; } service Baz {}",
+        )
+        .unwrap();
+
+    assert_eq!(
+        compiler
+            .describe_message(".acme.v1.Foo")
+            .unwrap()
+            .full_name(),
+        "acme.v1.Foo"
+    );
+    assert!(compiler.describe_message("acme.v1.Foo").is_some());
+    assert!(compiler.describe_message("acme.v1.Bar").is_none());
+    assert!(compiler.describe_enum(".acme.v1.Bar").is_some());
+    assert!(compiler.describe_service("acme.v1.Baz").is_some());
+    assert!(compiler.describe_service("Baz").is_none());
+}
+
+#[test]
+fn source_info_for() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.include_source_info(false);
+    compiler
+        .add_file_with_content("root.proto", "message Foo {}")
+        .unwrap();
+
+    assert!(compiler.file_descriptor_set().file[0]
+        .source_code_info
+        .is_none());
+    let source_info = compiler.source_info_for("root.proto").unwrap();
+    assert!(source_info
+        .location
+        .iter()
+        .any(|location| location.path == [4, 0, 1] && location.span == [0, 8, 11]));
+    assert!(compiler.source_info_for("notfound.proto").is_none());
+}
+
+#[test]
+fn file_descriptor_set_with_extensions() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "
+            import 'google/protobuf/descriptor.proto';
+            extend google.protobuf.MessageOptions { optional string tag = 1001; }
+            message Foo { option (tag) = 'foo'; }
+        ",
+        )
+        .unwrap();
+
+    let set = compiler.file_descriptor_set_with_extensions();
+    assert_eq!(
+        set.descriptor().full_name(),
+        "google.protobuf.FileDescriptorSet"
+    );
+    let files = set.get_field_by_name("file").unwrap();
+    let files = files.as_list().unwrap();
+    assert_eq!(files.len(), 1);
+
+    let file = files[0].as_message().unwrap();
+    let message = &file.get_field_by_name("message_type").unwrap();
+    let options = message.as_list().unwrap()[0]
+        .as_message()
+        .unwrap()
+        .get_field_by_name("options")
+        .unwrap();
+    let tag = compiler
+        .descriptor_pool()
+        .get_extension_by_name("tag")
+        .unwrap();
+    assert_eq!(
+        *options.as_message().unwrap().get_extension(&tag),
+        Value::String("foo".to_owned())
+    );
+
+    // The standard conversion drops the custom option.
+    assert!(compiler.file_descriptor_set().file[0].message_type[0]
+        .options
+        .as_ref()
+        .is_some_and(|options| options.uninterpreted_option.is_empty()));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn file_descriptor_set_json() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "
+            package pkg;
+            import 'google/protobuf/descriptor.proto';
+            extend google.protobuf.MessageOptions { optional string tag = 1001; }
+            message Foo { option (tag) = 'foo'; optional int32 foo_bar = 1; }
+        ",
+        )
+        .unwrap();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&compiler.file_descriptor_set_json().unwrap()).unwrap();
+    let file = &json["file"][0];
+    assert_eq!(file["name"], "root.proto");
+    assert_eq!(file["dependency"][0], "google/protobuf/descriptor.proto");
+    let message = &file["messageType"][0];
+    assert_eq!(message["options"]["[pkg.tag]"], "foo");
+    assert_eq!(message["field"][0]["jsonName"], "fooBar");
+}
+
+#[test]
+fn max_message_nesting_depth() {
+    let source = "
+        message A { optional B b = 1; repeated A list = 2; map<string, A> map = 3; }
+        message B { optional C c = 1; }
+        message C {}
+        message Node { optional Node next = 1; }
+        message Loop { required Outer outer = 1; }
+        message Outer { required Loop loop = 1; }
+    ";
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+    assert!(compiler.warnings().is_empty());
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_max_message_nesting_depth(3);
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+    assert_eq!(compiler.warnings().len(), 1);
+    assert_eq!(
+        compiler.warnings()[0].to_string(),
+        "message 'Loop' contains itself through required message fields"
+    );
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_max_message_nesting_depth(1);
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+    assert_eq!(
+        compiler.warnings()[0].to_string(),
+        "message 'A' has a chain of 2 nested non-repeated message fields, which exceeds the maximum of 1"
+    );
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_max_message_nesting_depth(u32::MAX);
+    compiler
+        .add_file_with_content("root.proto", "message Node { optional Node next = 1; }")
+        .unwrap();
+    assert!(compiler.warnings().is_empty());
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_max_message_nesting_depth(3);
+    compiler.with_warning_as_error(true);
+    let err = compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap_err();
+    assert_eq!(err.file(), Some("root.proto"));
+    assert!(compiler.warnings().is_empty());
+    assert!(compiler
+        .descriptor_pool()
+        .get_file_by_name("root.proto")
+        .is_none());
+}
+
+#[test]
+fn deprecation_check() {
+    let source = "
+        message Old { option deprecated = true; }
+        message New {
+            optional Old old = 1;
+            map<string, Old> map = 2;
+            optional Old ignored = 3 [deprecated = true];
+            message Nested { repeated Old list = 1; }
+            extensions 10 to 20;
+        }
+        message Legacy {
+            option deprecated = true;
+            optional Old old = 1;
+        }
+        extend New { optional Old ext = 10; }
+    ";
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+    assert!(compiler.warnings().is_empty());
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_deprecation_check(true);
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+    let warnings: Vec<_> = compiler.warnings().iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        [
+            "field 'New.old' uses deprecated message type 'Old'",
+            "field 'New.map' uses deprecated message type 'Old'",
+            "field 'New.Nested.list' uses deprecated message type 'Old'",
+            "field 'ext' uses deprecated message type 'Old'",
+        ]
+    );
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .with_deprecation_check(true)
+        .with_warning_as_error(true);
+    let err = compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field 'New.old' uses deprecated message type 'Old'"
+    );
+    assert!(compiler
+        .descriptor_pool()
+        .get_file_by_name("root.proto")
+        .is_none());
+}
+
+fn compiler_with_list_option_values() -> Compiler {
+    let mut options = ParseOptions::new();
+    options.allow_list_option_values(true);
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_parse_options(options);
+    compiler
+}
+
+#[test]
+fn list_option_value() {
+    let source = "
+        syntax = 'proto3';
+        import 'google/protobuf/descriptor.proto';
+        extend google.protobuf.MessageOptions { repeated int32 tags = 1001; }
+        message Foo { option (tags) = [1, 2, 3]; }
+    ";
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    let err = compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "option values cannot be lists");
+
+    let mut compiler = compiler_with_list_option_values();
+    compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap();
+
+    let message = compiler
+        .descriptor_pool()
+        .get_message_by_name("Foo")
+        .unwrap();
+    let tags = compiler
+        .descriptor_pool()
+        .get_extension_by_name("tags")
+        .unwrap();
+    assert_eq!(
+        *message.options().get_extension(&tags),
+        Value::List(vec![Value::I32(1), Value::I32(2), Value::I32(3)])
+    );
+
+    let mut compiler = compiler_with_list_option_values();
+    compiler.with_protoc_compatibility_mode(true);
+    let err = compiler
+        .add_file_with_content("root.proto", source)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "file 'root.proto' has warnings, which are treated as errors"
+    );
+}
+
+#[test]
+fn list_option_value_singular_field() {
+    for (source, field_name) in [
+        (
+            "message Foo { option deprecated = [true]; }",
+            "google.protobuf.MessageOptions.deprecated",
+        ),
+        (
+            "message Foo { option deprecated = [true, false]; }",
+            "google.protobuf.MessageOptions.deprecated",
+        ),
+        (
+            "message Foo { option deprecated = /* comment */ [true]; }",
+            "google.protobuf.MessageOptions.deprecated",
+        ),
+        (
+            "message Foo { option deprecated =\n// comment\n[true]; }",
+            "google.protobuf.MessageOptions.deprecated",
+        ),
+        (
+            "message Foo { optional int32 a = 1 [deprecated = [true]]; }",
+            "google.protobuf.FieldOptions.deprecated",
+        ),
+        ("message Foo { option (opt) = [1]; }", "opt"),
+    ] {
+        let source = format!(
+            "
+            syntax = 'proto2';
+            import 'google/protobuf/descriptor.proto';
+            extend google.protobuf.MessageOptions {{ optional int32 opt = 1001; }}
+            {}
+            ",
+            source
+        );
+        let mut compiler = compiler_with_list_option_values();
+        let err = compiler
+            .add_file_with_content("root.proto", &source)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "option field '{}' is not repeated, so it cannot be set to a list",
+                field_name
+            )
+        );
+    }
+
+    // Options can be checked without importing `descriptor.proto`.
+    let mut compiler = compiler_with_list_option_values();
+    let err = compiler
+        .add_file_with_content("root.proto", "message Foo { option deprecated = [true]; }")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "option field 'google.protobuf.MessageOptions.deprecated' is not repeated, so it cannot be set to a list"
+    );
+}
+
+#[test]
+fn aggregate_option_unknown_field_unformatted() {
+    use crate::file::{ChainFileResolver, DescriptorSetFileResolver, GoogleFileResolver};
+
+    let mut file = protox_parse::parse(
+        "root.proto",
+        "
+        syntax = 'proto2';
+        import 'google/protobuf/descriptor.proto';
+        message Opt {
+            optional int32 a = 1;
+            optional Opt nested = 2;
+        }
+        extend google.protobuf.MessageOptions { optional Opt opt = 1001; }
+        message Foo { option (opt) = { a: 1 }; }
+        ",
+    )
+    .unwrap();
+    // Descriptors which were not produced by the parser may use any whitespace in aggregate values.
+    let option = &mut file.message_type[1]
+        .options
+        .as_mut()
+        .unwrap()
+        .uninterpreted_option[0];
+    option.aggregate_value = Some("a:1\nnested{nested{b:4}}".to_owned());
+
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(DescriptorSetFileResolver::new(FileDescriptorSet {
+        file: vec![file],
+    }));
+    resolver.add(GoogleFileResolver::new());
+
+    let mut compiler = Compiler::with_file_resolver(resolver);
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field 'b' not found for message 'Opt' in option '(opt)'"
+    );
+}
+
+#[test]
+fn open_files_with_progress() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("dep.proto"), "message Dep {}").unwrap();
+    fs::write(
+        dir.path().join("foo.proto"),
+        "import 'dep.proto'; message Foo { optional Dep dep = 1; }",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("bar.proto"),
+        "import 'dep.proto'; message Bar { optional Dep dep = 1; }",
+    )
+    .unwrap();
+
+    let mut compiled = Vec::new();
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler
+        .open_files_with_progress(["foo.proto", "bar.proto", "dep.proto"], |file| {
+            compiled.push((file.name().to_owned(), file.is_import()))
+        })
+        .unwrap();
+
+    assert_eq!(
+        compiled,
+        [
+            ("dep.proto".to_owned(), true),
+            ("foo.proto".to_owned(), false),
+            ("bar.proto".to_owned(), false),
+        ]
+    );
+    assert!(!compiler.files["dep.proto"].is_import());
+}
+
+#[test]
+fn add_file_descriptor_set() {
+    let set = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("base.proto".to_owned()),
+            dependency: vec!["google/protobuf/empty.proto".to_owned()],
+            message_type: vec![prost_types::DescriptorProto {
+                name: Some("Base".to_owned()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    };
+
+    let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    compiler.add_file_descriptor_set(set).unwrap();
+    compiler
+        .add_file_with_content(
+            "root.proto",
+            "import 'base.proto'; message Root { optional Base base = 1; }",
+        )
+        .unwrap();
+
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["root.proto"]
+    );
+    assert_eq!(compiler.files().len(), 3);
+    assert_eq!(compiler.file_descriptor_set().file.len(), 1);
+    compiler.include_imports(true);
+    assert_eq!(compiler.file_descriptor_set().file.len(), 3);
+
+    let invalid = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("invalid.proto".to_owned()),
+            dependency: vec!["missing.proto".to_owned()],
+            ..Default::default()
+        }],
+    };
+    assert!(compiler.add_file_descriptor_set(invalid).is_err());
+}
+
+#[test]
+fn unused_extension_ranges() {
+    let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    compiler
+        .add_file_with_content(
+            "base.proto",
+            "
+            message Used { extensions 100 to 199, 300 to max; }
+            message Unused {
+                extensions 100 to 199, 300;
+                message Nested { extensions 5; }
+            }
+        ",
+        )
+        .unwrap();
+    compiler
+        .add_file_with_content(
+            "ext.proto",
+            "import 'base.proto'; extend Used { optional int32 ext = 300; }",
+        )
+        .unwrap();
+
+    let warnings: Vec<_> = compiler
+        .unused_extension_ranges()
+        .iter()
+        .map(|warning| format!("{:?}", warning))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            "base.proto:4:28: extension range 100 to 199 of message 'Unused' is not used by any extension",
+            "base.proto:4:40: extension range 300 to 300 of message 'Unused' is not used by any extension",
+            "base.proto:5:45: extension range 5 to 5 of message 'Unused.Nested' is not used by any extension",
+        ]
+    );
+}
+
+#[test]
+fn name_prefix_strip() {
+    let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    compiler
+        .with_name_prefix_strip("myorg/proto/")
+        .unwrap()
+        .include_imports(true);
+    compiler
+        .add_file_with_content("myorg/proto/bar.proto", "message Bar {}")
+        .unwrap();
+    compiler
+        .add_file_with_content(
+            "myorg/proto/foo.proto",
+            "
+            import 'google/protobuf/empty.proto';
+            import 'myorg/proto/bar.proto';
+            message Foo { optional Bar bar = 1; }
+        ",
+        )
+        .unwrap();
+
+    let file_descriptor_set = compiler.file_descriptor_set();
+    let names: Vec<_> = file_descriptor_set.file.iter().map(|f| f.name()).collect();
+    assert_eq!(
+        names,
+        ["google/protobuf/empty.proto", "bar.proto", "foo.proto"]
+    );
+    assert_eq!(
+        file_descriptor_set.file[2].dependency,
+        ["google/protobuf/empty.proto", "bar.proto"]
+    );
+    assert_eq!(
+        FileDescriptorSet::decode(compiler.encode_file_descriptor_set().as_slice()).unwrap(),
+        file_descriptor_set
+    );
+    assert!(compiler.files.contains_key("myorg/proto/foo.proto"));
+}
+
+#[test]
+fn name_prefix_strip_component_boundary() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_name_prefix_strip("myorg/proto").unwrap();
+    compiler
+        .add_file_with_content("myorg/proto/foo.proto", "")
+        .unwrap();
+    compiler
+        .add_file_with_content("myorg/protobuf/bar.proto", "")
+        .unwrap();
+
+    let file_descriptor_set = compiler.file_descriptor_set();
+    let names: Vec<_> = file_descriptor_set.file.iter().map(|f| f.name()).collect();
+    assert_eq!(names, ["foo.proto", "myorg/protobuf/bar.proto"]);
+}
+
+#[test]
+fn name_prefix_strip_conflict() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.with_name_prefix_strip("myorg/proto/").unwrap();
+    compiler
+        .add_file_with_content("myorg/proto/foo.proto", "")
+        .unwrap();
+    let err = compiler.add_file_with_content("foo.proto", "").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "files 'myorg/proto/foo.proto' and 'foo.proto' both have the name 'foo.proto' after removing the name prefix"
+    );
+    assert_eq!(compiler.files().count(), 1);
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("foo.proto", "")
+        .unwrap()
+        .add_file_with_content("myorg/proto/foo.proto", "")
+        .unwrap();
+    let err = compiler.with_name_prefix_strip("myorg/proto/").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "files 'myorg/proto/foo.proto' and 'foo.proto' both have the name 'foo.proto' after removing the name prefix"
+    );
+}
+
+#[test]
+fn prime_from_pool() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.proto"), "message A {}").unwrap();
+    fs::write(
+        dir.path().join("b.proto"),
+        "import 'a.proto'; message B { optional A a = 1; }",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("c.proto"),
+        "import 'b.proto'; message C { optional B b = 1; }",
+    )
+    .unwrap();
+
+    let mut previous = Compiler::new([dir.path()]).unwrap();
+    previous.open_file("c.proto").unwrap();
+    let files = || -> HashMap<String, FileMetadata> {
+        previous
+            .files()
+            .map(|f| (f.name().to_owned(), f.clone()))
+            .collect()
+    };
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler.prime_from_pool(previous.descriptor_pool(), files());
+    assert_eq!(compiler.files().len(), 3);
+    assert!(compiler.files().all(|f| f.is_import()));
+    compiler.open_file("c.proto").unwrap();
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["c.proto"]
+    );
+    assert_eq!(
+        compiler.files["a.proto"].path(),
+        Some(dir.path().join("a.proto").as_ref())
+    );
+
+    fs::write(
+        dir.path().join("b.proto"),
+        "import 'a.proto'; message B { repeated A a = 1; }",
+    )
+    .unwrap();
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler.prime_from_pool(previous.descriptor_pool(), files());
+    assert_eq!(
+        compiler.files().map(|f| f.name()).collect::<Vec<_>>(),
+        ["a.proto"]
+    );
+    assert!(compiler
+        .descriptor_pool()
+        .get_message_by_name("B")
+        .is_none());
+
+    compiler.open_file("c.proto").unwrap();
+    assert!(compiler
+        .descriptor_pool()
+        .get_message_by_name("B")
+        .unwrap()
+        .get_field_by_name("a")
+        .unwrap()
+        .is_list());
+}
+
+#[test]
+fn error_predicates() {
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+
+    let err = compiler
+        .add_file_with_content("root.proto", "import 'root.proto';")
+        .unwrap_err();
+    assert!(err.is_circular_import());
+    assert!(!err.is_check() && !err.is_parse() && !err.is_file_not_found());
+
+    let err = compiler
+        .add_file_with_content("root.proto", "message Foo {")
+        .unwrap_err();
+    assert!(err.is_parse());
+    assert!(!err.is_check() && !err.is_circular_import());
+
+    let err = compiler
+        .add_file_with_content("root.proto", "message Foo { optional Bar bar = 1; }")
+        .unwrap_err();
+    assert!(err.is_check());
+    assert!(!err.is_parse() && !err.is_circular_import());
+
+    let err = compiler
+        .add_file_with_content(
+            "root.proto",
+            "message Foo { optional int32 foo = 1; reserved 'foo'; }",
+        )
+        .unwrap_err();
+    assert!(err.is_check());
+
+    let err = compiler
+        .add_file_with_content("root.proto", "import 'missing.proto';")
+        .unwrap_err();
+    assert!(err.is_file_not_found());
+    assert!(!err.is_check() && !err.is_circular_import());
+}
+
+#[test]
+fn strict_imports() {
+    let mut compiler = Compiler::with_file_resolver(crate::file::GoogleFileResolver::new());
+    compiler.with_strict_imports(&["google/protobuf/empty.proto"]);
+
+    compiler
+        .add_file_with_content("dep.proto", "import 'google/protobuf/empty.proto';")
+        .unwrap();
+    compiler
+        .add_file_with_content("foo.proto", "import 'dep.proto';")
+        .unwrap();
+
+    let err = compiler
+        .add_file_with_content(
+            "bar.proto",
+            "import 'dep.proto';\nimport 'google/protobuf/any.proto';",
+        )
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::ImplicitImportNotAllowed { name, span, .. } => {
+            assert_eq!(name, "google/protobuf/any.proto");
+            assert_eq!(*span, Some((20..55).into()));
+        }
+        kind => panic!("unexpected error: {}", kind),
+    }
+    assert_eq!(err.file(), Some("bar.proto"));
+}
+
+#[test]
+#[cfg(feature = "file-cache")]
+fn file_cache_dir() {
+    let dir = TempDir::new().unwrap();
+    let cache_dir = dir.path().join("cache");
+    fs::create_dir(dir.path().join("include")).unwrap();
+    fs::write(dir.path().join("include/foo.proto"), "message Foo {}").unwrap();
+
+    let compile = || {
+        let mut compiler = Compiler::new([dir.path().join("include")]).unwrap();
+        compiler.with_file_cache_dir(&cache_dir);
+        compiler.open_file("foo.proto").unwrap();
+        compiler.file_descriptor_set().file[0].message_type[0]
+            .name()
+            .to_owned()
+    };
+
+    assert_eq!(compile(), "Foo");
+    let entries: Vec<_> = fs::read_dir(&cache_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+
+    // Entries are keyed on the crate version as well as the source, so they are not reused after an upgrade.
+    use sha2::Digest;
+
+    let source_hash: String = sha2::Sha256::digest("message Foo {}")
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_ne!(entries[0].file_stem().unwrap(), source_hash.as_str());
+
+    // Unchanged files are loaded from the cache instead of being parsed.
+    let mut cached =
+        FileDescriptorProto::decode(fs::read(&entries[0]).unwrap().as_slice()).unwrap();
+    cached.message_type[0].name = Some("Cached".to_owned());
+    fs::write(&entries[0], cached.encode_to_vec()).unwrap();
+    assert_eq!(compile(), "Cached");
+
+    fs::write(dir.path().join("include/foo.proto"), "message Bar {}").unwrap();
+    assert_eq!(compile(), "Bar");
+    assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 2);
+}
+
+#[test]
+fn duplicate_package() {
+    let mut message = protox_parse::parse("message.proto", "package foo; message Bar {}").unwrap();
+    let mut package = protox_parse::parse("package.proto", "package foo.Bar.baz;").unwrap();
+    message.source_code_info = None;
+    package.source_code_info = None;
+
+    for (first, second) in [
+        (message.clone(), package.clone()),
+        (package.clone(), message.clone()),
+    ] {
+        let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+        compiler
+            .add_file_descriptor_set(FileDescriptorSet { file: vec![first] })
+            .unwrap();
+        let err = compiler
+            .add_file_descriptor_set(FileDescriptorSet {
+                file: vec![second.clone()],
+            })
+            .unwrap_err();
+
+        assert!(err.is_check());
+        assert_eq!(err.file(), second.name.as_deref());
+        assert_eq!(
+            err.to_string(),
+            "package 'foo.Bar' declared in file 'package.proto' conflicts with a definition in file 'message.proto'"
+        );
+    }
+
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("a.proto", "package foo.bar; message Baz {}")
+        .unwrap();
+    compiler
+        .add_file_with_content("b.proto", "package foo; message Baz {}")
+        .unwrap();
+}
+
+#[test]
+fn file_read_limit() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("foo.proto"),
+        "message Foo { optional int32 a = 1; }",
+    )
+    .unwrap();
+    fs::write(dir.path().join("bar.proto"), "import 'foo.proto';").unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler.with_file_read_limit(20);
+    let err = compiler.open_file("bar.proto").unwrap_err();
+    match err.kind() {
+        ErrorKind::FileTooLarge { name, size, limit } => {
+            assert_eq!(name, "foo.proto");
+            assert_eq!(*size, 37);
+            assert_eq!(*limit, 20);
+        }
+        kind => panic!("unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn write_depfile() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("my dir")).unwrap();
+    fs::write(dir.path().join("my dir/foo.proto"), "message Foo {}").unwrap();
+    fs::write(
+        dir.path().join("bar.proto"),
+        "import 'my dir/foo.proto'; import 'google/protobuf/empty.proto';",
+    )
+    .unwrap();
+
+    let mut compiler = Compiler::new([dir.path()]).unwrap();
+    compiler.open_file("bar.proto").unwrap();
+
+    let mut depfile = Vec::new();
+    compiler.write_depfile("out$#.bin", &mut depfile).unwrap();
+
+    let escape = |path: PathBuf| path.to_string_lossy().replace(' ', "\\ ");
+    assert_eq!(
+        String::from_utf8(depfile).unwrap(),
+        format!(
+            "out$$\\#.bin: \\\n  {} \\\n  {}\n",
+            escape(dir.path().join("my dir/foo.proto")),
+            escape(dir.path().join("bar.proto")),
+        )
+    );
+}
@@ -0,0 +1,824 @@
+//! Human-readable differences between versions of a protobuf schema.
+//!
+//! See [`diff_file_descriptor_protos()`] for details. To check the wire compatibility of message fields, see
+//! [`field_number_mapping()`].
+
+use std::{collections::HashMap, fmt};
+
+use prost_types::{
+    field_descriptor_proto::Label, DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto,
+    FieldDescriptorProto, FileDescriptorProto, MethodDescriptorProto, ServiceDescriptorProto,
+};
+
+/// The differences between two versions of a file, as returned by [`diff_file_descriptor_protos()`].
+///
+/// The [`Display`](fmt::Display) implementation formats the changes similarly to a unified diff, with added lines
+/// prefixed by `+`, removed lines prefixed by `-`, and enclosing definitions shown for context.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiffLine {
+    kind: DiffKind,
+    depth: usize,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// Compares two versions of a file, returning all added, removed and modified definitions.
+///
+/// Definitions are matched by name, so a renamed field is reported as one removed and one added field. Unlike a
+/// plain text diff, changes to formatting, comments and the order of definitions are ignored.
+///
+/// # Examples
+///
+/// ```
+/// # use protox::diff::diff_file_descriptor_protos;
+/// let old = protox_parse::parse("foo.proto", "
+///     message Foo {
+///         optional string phone = 4;
+///     }
+/// ").unwrap();
+/// let new = protox_parse::parse("foo.proto", "
+///     message Foo {
+///         optional string email = 5;
+///     }
+/// ").unwrap();
+///
+/// let diff = diff_file_descriptor_protos(&old, &new);
+/// assert_eq!(diff.to_string(), "  message Foo {
+/// -   optional string phone = 4;
+/// +   optional string email = 5;
+///   }
+/// ");
+/// ```
+pub fn diff_file_descriptor_protos(
+    old: &FileDescriptorProto,
+    new: &FileDescriptorProto,
+) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+    let old_syntax = old.syntax();
+    let new_syntax = new.syntax();
+
+    diff.diff_lines(0, &file_header(old), &file_header(new));
+    diff.diff_items(
+        0,
+        &old.message_type,
+        &new.message_type,
+        |m| m.name(),
+        |diff, kind, depth, message| {
+            diff.add_message(
+                kind,
+                depth,
+                message,
+                syntax_for(kind, old_syntax, new_syntax),
+            )
+        },
+        |diff, depth, old, new| diff.diff_message(depth, old, new, old_syntax, new_syntax),
+    );
+    diff.diff_items(
+        0,
+        &old.enum_type,
+        &new.enum_type,
+        |e| e.name(),
+        |diff, kind, depth, enum_| diff.add_enum(kind, depth, enum_),
+        |diff, depth, old, new| diff.diff_enum(depth, old, new),
+    );
+    diff.diff_extensions(0, &old.extension, &new.extension, old_syntax, new_syntax);
+    diff.diff_items(
+        0,
+        &old.service,
+        &new.service,
+        |s| s.name(),
+        |diff, kind, depth, service| diff.add_service(kind, depth, service),
+        |diff, depth, old, new| diff.diff_service(depth, old, new),
+    );
+
+    diff
+}
+
+/// How the field with a given number changed between two versions of a message, as returned by
+/// [`field_number_mapping()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FieldChange {
+    /// The field has the same name and type in both versions.
+    Unchanged,
+    /// The type of the field changed, which is not wire compatible. The field may also have been renamed.
+    TypeChanged {
+        /// The type of the field in the old version, such as `int32`, `repeated foo.Bar` or `map<string, int64>`.
+        old_type: String,
+        /// The type of the field in the new version.
+        new_type: String,
+    },
+    /// The field was renamed, but kept its type.
+    Renamed {
+        /// The name of the field in the old version.
+        old_name: String,
+        /// The name of the field in the new version.
+        new_name: String,
+    },
+    /// The number is used in the old version, but not the new version.
+    Removed {
+        /// The name of the field in the old version.
+        name: String,
+    },
+    /// The number is used in the new version, but not the old version.
+    Added {
+        /// The name of the field in the new version.
+        name: String,
+    },
+}
+
+/// Compares the fields of each message in two versions of a file by their field numbers.
+///
+/// Unlike [`diff_file_descriptor_protos()`], which matches fields by name, this detects a field number being reused
+/// for a field with a different type, which breaks compatibility with previously encoded messages. The result is keyed
+/// by the fully-qualified name of the message, without a leading `.`, and the field number. Messages are matched by
+/// name, so the fields of an added or removed message are all reported as added or removed.
+///
+/// # Examples
+///
+/// ```
+/// # use protox::diff::{field_number_mapping, FieldChange};
+/// let old = protox_parse::parse("foo.proto", "
+///     package pkg;
+///     message Foo {
+///         optional string phone = 4;
+///         optional int32 count = 5;
+///     }
+/// ").unwrap();
+/// let new = protox_parse::parse("foo.proto", "
+///     package pkg;
+///     message Foo {
+///         optional string email = 4;
+///         optional int64 count = 5;
+///     }
+/// ").unwrap();
+///
+/// let mapping = field_number_mapping(&old, &new);
+/// assert_eq!(mapping[&("pkg.Foo".to_owned(), 4)], FieldChange::Renamed {
+///     old_name: "phone".to_owned(),
+///     new_name: "email".to_owned(),
+/// });
+/// assert_eq!(mapping[&("pkg.Foo".to_owned(), 5)], FieldChange::TypeChanged {
+///     old_type: "int32".to_owned(),
+///     new_type: "int64".to_owned(),
+/// });
+/// ```
+pub fn field_number_mapping(
+    old: &FileDescriptorProto,
+    new: &FileDescriptorProto,
+) -> HashMap<(String, i32), FieldChange> {
+    let mut old_messages = HashMap::new();
+    for message in &old.message_type {
+        collect_messages(old.package(), message, &mut old_messages);
+    }
+    let mut new_messages = HashMap::new();
+    for message in &new.message_type {
+        collect_messages(new.package(), message, &mut new_messages);
+    }
+
+    let mut mapping = HashMap::new();
+    for (name, old_message) in &old_messages {
+        let new_message = new_messages.get(name);
+        for old_field in &old_message.field {
+            let new_field = new_message.and_then(|new_message| {
+                new_message
+                    .field
+                    .iter()
+                    .find(|field| field.number() == old_field.number())
+                    .map(|field| (*new_message, field))
+            });
+
+            let change = match new_field {
+                None => FieldChange::Removed {
+                    name: old_field.name().to_owned(),
+                },
+                Some((new_message, new_field)) => {
+                    let old_type = field_type_with_label(old_message, old_field);
+                    let new_type = field_type_with_label(new_message, new_field);
+                    if old_type != new_type {
+                        FieldChange::TypeChanged { old_type, new_type }
+                    } else if old_field.name() != new_field.name() {
+                        FieldChange::Renamed {
+                            old_name: old_field.name().to_owned(),
+                            new_name: new_field.name().to_owned(),
+                        }
+                    } else {
+                        FieldChange::Unchanged
+                    }
+                }
+            };
+            mapping.insert((name.clone(), old_field.number()), change);
+        }
+    }
+    for (name, new_message) in &new_messages {
+        for new_field in &new_message.field {
+            mapping
+                .entry((name.clone(), new_field.number()))
+                .or_insert_with(|| FieldChange::Added {
+                    name: new_field.name().to_owned(),
+                });
+        }
+    }
+
+    mapping
+}
+
+impl SchemaDiff {
+    /// Returns `true` if there are no differences between the files.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    fn push(&mut self, kind: DiffKind, depth: usize, text: impl Into<String>) {
+        self.lines.push(DiffLine {
+            kind,
+            depth,
+            text: text.into(),
+        });
+    }
+
+    /// Runs `f`, surrounding any lines it adds with the given opening line and a closing brace.
+    fn with_context(&mut self, depth: usize, header: String, f: impl FnOnce(&mut Self)) {
+        let start = self.lines.len();
+        f(self);
+        if self.lines.len() != start {
+            self.lines.insert(
+                start,
+                DiffLine {
+                    kind: DiffKind::Context,
+                    depth,
+                    text: header,
+                },
+            );
+            self.push(DiffKind::Context, depth, "}");
+        }
+    }
+
+    fn diff_lines(&mut self, depth: usize, old: &[String], new: &[String]) {
+        for line in old.iter().filter(|line| !new.contains(line)) {
+            self.push(DiffKind::Removed, depth, line.as_str());
+        }
+        for line in new.iter().filter(|line| !old.contains(line)) {
+            self.push(DiffKind::Added, depth, line.as_str());
+        }
+    }
+
+    fn diff_items<T>(
+        &mut self,
+        depth: usize,
+        old: &[T],
+        new: &[T],
+        name: impl Fn(&T) -> &str,
+        mut add: impl FnMut(&mut Self, DiffKind, usize, &T),
+        mut modify: impl FnMut(&mut Self, usize, &T, &T),
+    ) {
+        let old_by_name: HashMap<&str, &T> = old.iter().map(|item| (name(item), item)).collect();
+        let new_by_name: HashMap<&str, &T> = new.iter().map(|item| (name(item), item)).collect();
+
+        for item in old {
+            if !new_by_name.contains_key(name(item)) {
+                add(self, DiffKind::Removed, depth, item);
+            }
+        }
+        for item in new {
+            match old_by_name.get(name(item)) {
+                Some(old_item) => modify(self, depth, old_item, item),
+                None => add(self, DiffKind::Added, depth, item),
+            }
+        }
+    }
+
+    fn add_message(
+        &mut self,
+        kind: DiffKind,
+        depth: usize,
+        message: &DescriptorProto,
+        syntax: &str,
+    ) {
+        self.push(kind, depth, format!("message {} {{", message.name()));
+        for field in &message.field {
+            self.push(kind, depth + 1, field_line(message, field, syntax));
+        }
+        for nested in nested_messages(message) {
+            self.add_message(kind, depth + 1, nested, syntax);
+        }
+        for nested in &message.enum_type {
+            self.add_enum(kind, depth + 1, nested);
+        }
+        self.add_extensions(kind, depth + 1, &message.extension, syntax);
+        self.push(kind, depth, "}");
+    }
+
+    fn diff_message(
+        &mut self,
+        depth: usize,
+        old: &DescriptorProto,
+        new: &DescriptorProto,
+        old_syntax: &str,
+        new_syntax: &str,
+    ) {
+        self.with_context(depth, format!("message {} {{", new.name()), |diff| {
+            diff.diff_items(
+                depth + 1,
+                &old.field,
+                &new.field,
+                |f| f.name(),
+                |diff, kind, depth, field| {
+                    let (message, syntax) = match kind {
+                        DiffKind::Removed => (old, old_syntax),
+                        _ => (new, new_syntax),
+                    };
+                    diff.push(kind, depth, field_line(message, field, syntax))
+                },
+                |diff, depth, old_field, new_field| {
+                    diff.diff_lines(
+                        depth,
+                        &[field_line(old, old_field, old_syntax)],
+                        &[field_line(new, new_field, new_syntax)],
+                    )
+                },
+            );
+            diff.diff_items(
+                depth + 1,
+                &nested_messages(old).cloned().collect::<Vec<_>>(),
+                &nested_messages(new).cloned().collect::<Vec<_>>(),
+                |m| m.name(),
+                |diff, kind, depth, message| {
+                    diff.add_message(
+                        kind,
+                        depth,
+                        message,
+                        syntax_for(kind, old_syntax, new_syntax),
+                    )
+                },
+                |diff, depth, old, new| diff.diff_message(depth, old, new, old_syntax, new_syntax),
+            );
+            diff.diff_items(
+                depth + 1,
+                &old.enum_type,
+                &new.enum_type,
+                |e| e.name(),
+                |diff, kind, depth, enum_| diff.add_enum(kind, depth, enum_),
+                |diff, depth, old, new| diff.diff_enum(depth, old, new),
+            );
+            diff.diff_extensions(
+                depth + 1,
+                &old.extension,
+                &new.extension,
+                old_syntax,
+                new_syntax,
+            );
+        });
+    }
+
+    fn add_enum(&mut self, kind: DiffKind, depth: usize, enum_: &EnumDescriptorProto) {
+        self.push(kind, depth, format!("enum {} {{", enum_.name()));
+        for value in &enum_.value {
+            self.push(kind, depth + 1, enum_value_line(value));
+        }
+        self.push(kind, depth, "}");
+    }
+
+    fn diff_enum(&mut self, depth: usize, old: &EnumDescriptorProto, new: &EnumDescriptorProto) {
+        self.with_context(depth, format!("enum {} {{", new.name()), |diff| {
+            diff.diff_items(
+                depth + 1,
+                &old.value,
+                &new.value,
+                |v| v.name(),
+                |diff, kind, depth, value| diff.push(kind, depth, enum_value_line(value)),
+                |diff, depth, old, new| {
+                    diff.diff_lines(depth, &[enum_value_line(old)], &[enum_value_line(new)])
+                },
+            );
+        });
+    }
+
+    fn add_extensions(
+        &mut self,
+        kind: DiffKind,
+        depth: usize,
+        extensions: &[FieldDescriptorProto],
+        syntax: &str,
+    ) {
+        for extension in extensions {
+            self.push(kind, depth, extension_line(extension, syntax));
+        }
+    }
+
+    fn diff_extensions(
+        &mut self,
+        depth: usize,
+        old: &[FieldDescriptorProto],
+        new: &[FieldDescriptorProto],
+        old_syntax: &str,
+        new_syntax: &str,
+    ) {
+        let old_lines: Vec<String> = old.iter().map(|e| extension_line(e, old_syntax)).collect();
+        let new_lines: Vec<String> = new.iter().map(|e| extension_line(e, new_syntax)).collect();
+        self.diff_lines(depth, &old_lines, &new_lines);
+    }
+
+    fn add_service(&mut self, kind: DiffKind, depth: usize, service: &ServiceDescriptorProto) {
+        self.push(kind, depth, format!("service {} {{", service.name()));
+        for method in &service.method {
+            self.push(kind, depth + 1, method_line(method));
+        }
+        self.push(kind, depth, "}");
+    }
+
+    fn diff_service(
+        &mut self,
+        depth: usize,
+        old: &ServiceDescriptorProto,
+        new: &ServiceDescriptorProto,
+    ) {
+        self.with_context(depth, format!("service {} {{", new.name()), |diff| {
+            diff.diff_items(
+                depth + 1,
+                &old.method,
+                &new.method,
+                |m| m.name(),
+                |diff, kind, depth, method| diff.push(kind, depth, method_line(method)),
+                |diff, depth, old, new| {
+                    diff.diff_lines(depth, &[method_line(old)], &[method_line(new)])
+                },
+            );
+        });
+    }
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            let prefix = match line.kind {
+                DiffKind::Context => ' ',
+                DiffKind::Added => '+',
+                DiffKind::Removed => '-',
+            };
+            writeln!(
+                f,
+                "{} {:indent$}{}",
+                prefix,
+                "",
+                line.text,
+                indent = line.depth * 2
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn syntax_for<'a>(kind: DiffKind, old_syntax: &'a str, new_syntax: &'a str) -> &'a str {
+    match kind {
+        DiffKind::Removed => old_syntax,
+        _ => new_syntax,
+    }
+}
+
+fn file_header(file: &FileDescriptorProto) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(syntax) = &file.syntax {
+        lines.push(format!("syntax = \"{}\";", syntax));
+    }
+    if let Some(package) = &file.package {
+        lines.push(format!("package {};", package));
+    }
+    for (index, dependency) in file.dependency.iter().enumerate() {
+        let index = index as i32;
+        let modifier = if file.public_dependency.contains(&index) {
+            "public "
+        } else if file.weak_dependency.contains(&index) {
+            "weak "
+        } else {
+            ""
+        };
+        lines.push(format!("import {}\"{}\";", modifier, dependency));
+    }
+    lines
+}
+
+/// Gets the nested messages of a message, excluding generated map entry messages.
+fn nested_messages(message: &DescriptorProto) -> impl Iterator<Item = &DescriptorProto> {
+    message
+        .nested_type
+        .iter()
+        .filter(|nested| !is_map_entry(nested))
+}
+
+fn is_map_entry(message: &DescriptorProto) -> bool {
+    message
+        .options
+        .as_ref()
+        .is_some_and(|options| options.map_entry())
+}
+
+/// Adds a message and its nested messages to `messages`, keyed by their fully-qualified names.
+fn collect_messages<'a>(
+    scope: &str,
+    message: &'a DescriptorProto,
+    messages: &mut HashMap<String, &'a DescriptorProto>,
+) {
+    let name = if scope.is_empty() {
+        message.name().to_owned()
+    } else {
+        format!("{}.{}", scope, message.name())
+    };
+    for nested in nested_messages(message) {
+        collect_messages(&name, nested, messages);
+    }
+    messages.insert(name, message);
+}
+
+/// Gets the type of a field for checking wire compatibility, distinguishing repeated fields and map types.
+fn field_type_with_label(message: &DescriptorProto, field: &FieldDescriptorProto) -> String {
+    if let Some(map_type) = map_type(message, field) {
+        map_type
+    } else if field.label() == Label::Repeated {
+        format!("repeated {}", field_type(field))
+    } else {
+        field_type(field)
+    }
+}
+
+/// Gets the type of a map field, such as `map<string, int32>`, or `None` if it is not a map field.
+fn map_type(message: &DescriptorProto, field: &FieldDescriptorProto) -> Option<String> {
+    let map_entry = field
+        .type_name
+        .as_deref()
+        .and_then(|type_name| type_name.rsplit('.').next())
+        .and_then(|name| {
+            message
+                .nested_type
+                .iter()
+                .find(|nested| nested.name() == name && is_map_entry(nested))
+        });
+    match (map_entry, field.label()) {
+        (Some(entry), Label::Repeated) => match entry.field.as_slice() {
+            [key, value] => Some(format!("map<{}, {}>", field_type(key), field_type(value))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn field_line(message: &DescriptorProto, field: &FieldDescriptorProto, syntax: &str) -> String {
+    if let Some(map_type) = map_type(message, field) {
+        return format!("{} {} = {};", map_type, field.name(), field.number());
+    }
+
+    format!(
+        "{}{} {} = {};",
+        field_label(field, syntax),
+        field_type(field),
+        field.name(),
+        field.number()
+    )
+}
+
+fn extension_line(extension: &FieldDescriptorProto, syntax: &str) -> String {
+    format!(
+        "extend {} {{ {}{} {} = {}; }}",
+        extension.extendee().trim_start_matches('.'),
+        field_label(extension, syntax),
+        field_type(extension),
+        extension.name(),
+        extension.number()
+    )
+}
+
+fn field_label(field: &FieldDescriptorProto, syntax: &str) -> &'static str {
+    match field.label() {
+        Label::Repeated => "repeated ",
+        Label::Required => "required ",
+        Label::Optional if field.proto3_optional() || syntax != "proto3" => "optional ",
+        Label::Optional => "",
+    }
+}
+
+fn field_type(field: &FieldDescriptorProto) -> String {
+    match &field.type_name {
+        Some(type_name) => type_name.trim_start_matches('.').to_owned(),
+        None => field
+            .r#type()
+            .as_str_name()
+            .trim_start_matches("TYPE_")
+            .to_ascii_lowercase(),
+    }
+}
+
+fn enum_value_line(value: &EnumValueDescriptorProto) -> String {
+    format!("{} = {};", value.name(), value.number())
+}
+
+fn method_line(method: &MethodDescriptorProto) -> String {
+    format!(
+        "rpc {}({}{}) returns ({}{});",
+        method.name(),
+        if method.client_streaming() {
+            "stream "
+        } else {
+            ""
+        },
+        method.input_type().trim_start_matches('.'),
+        if method.server_streaming() {
+            "stream "
+        } else {
+            ""
+        },
+        method.output_type().trim_start_matches('.'),
+    )
+}
+
+#[test]
+fn diff_messages() {
+    let old = protox_parse::parse(
+        "test.proto",
+        "
+        syntax = 'proto3';
+        package foo;
+
+        message Foo {
+            string name = 1;
+            string phone = 4;
+            map<string, int32> counts = 6;
+
+            message Unchanged {
+                int32 a = 1;
+            }
+
+            message Removed {}
+        }
+
+        enum Kind {
+            KIND_UNSPECIFIED = 0;
+            KIND_OLD = 1;
+        }
+
+        service Service {
+            rpc Get(Foo) returns (Foo);
+        }
+    ",
+    )
+    .unwrap();
+    let new = protox_parse::parse(
+        "test.proto",
+        "
+        syntax = 'proto3';
+        package foo;
+
+        import 'bar.proto';
+
+        message Foo {
+            string name = 1;
+            optional string email = 5;
+            map<string, int64> counts = 6;
+
+            message Unchanged {
+                int32 a = 1;
+            }
+        }
+
+        enum Kind {
+            KIND_UNSPECIFIED = 0;
+            KIND_NEW = 1;
+        }
+
+        service Service {
+            rpc Get(Foo) returns (stream Foo);
+        }
+
+        message Bar {
+            repeated Foo foos = 1;
+        }
+    ",
+    )
+    .unwrap();
+
+    assert!(diff_file_descriptor_protos(&old, &old).is_empty());
+    assert_eq!(
+        diff_file_descriptor_protos(&old, &new).to_string(),
+        r#"+ import "bar.proto";
+  message Foo {
+-   string phone = 4;
++   optional string email = 5;
+-   map<string, int32> counts = 6;
++   map<string, int64> counts = 6;
+-   message Removed {
+-   }
+  }
++ message Bar {
++   repeated Foo foos = 1;
++ }
+  enum Kind {
+-   KIND_OLD = 1;
++   KIND_NEW = 1;
+  }
+  service Service {
+-   rpc Get(Foo) returns (Foo);
++   rpc Get(Foo) returns (stream Foo);
+  }
+"#
+    );
+}
+
+#[test]
+fn field_numbers() {
+    let old = protox_parse::parse(
+        "test.proto",
+        "
+        message Foo {
+            optional string name = 1;
+            optional string phone = 4;
+            map<string, int32> counts = 6;
+            repeated int32 ids = 7;
+
+            message Nested {
+                optional int32 a = 1;
+            }
+        }
+
+        message Removed {
+            optional int32 a = 1;
+        }
+    ",
+    )
+    .unwrap();
+    let new = protox_parse::parse(
+        "test.proto",
+        "
+        message Foo {
+            optional string name = 1;
+            optional string email = 4;
+            optional string address = 5;
+            map<string, int64> counts = 6;
+            optional int32 ids = 7;
+
+            message Nested {
+                optional sint32 b = 1;
+            }
+        }
+    ",
+    )
+    .unwrap();
+
+    let mapping = field_number_mapping(&old, &new);
+    let change = |message: &str, number: i32| mapping[&(message.to_owned(), number)].clone();
+
+    assert_eq!(mapping.len(), 7);
+    assert_eq!(change("Foo", 1), FieldChange::Unchanged);
+    assert_eq!(
+        change("Foo", 4),
+        FieldChange::Renamed {
+            old_name: "phone".to_owned(),
+            new_name: "email".to_owned(),
+        }
+    );
+    assert_eq!(
+        change("Foo", 5),
+        FieldChange::Added {
+            name: "address".to_owned(),
+        }
+    );
+    assert_eq!(
+        change("Foo", 6),
+        FieldChange::TypeChanged {
+            old_type: "map<string, int32>".to_owned(),
+            new_type: "map<string, int64>".to_owned(),
+        }
+    );
+    assert_eq!(
+        change("Foo", 7),
+        FieldChange::TypeChanged {
+            old_type: "repeated int32".to_owned(),
+            new_type: "int32".to_owned(),
+        }
+    );
+    assert_eq!(
+        change("Foo.Nested", 1),
+        FieldChange::TypeChanged {
+            old_type: "int32".to_owned(),
+            new_type: "sint32".to_owned(),
+        }
+    );
+    assert_eq!(
+        change("Removed", 1),
+        FieldChange::Removed {
+            name: "a".to_owned(),
+        }
+    );
+    assert!(field_number_mapping(&old, &old)
+        .values()
+        .all(|change| *change == FieldChange::Unchanged));
+}
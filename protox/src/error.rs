@@ -2,10 +2,11 @@ use std::{fmt, io, path::PathBuf};
 
 use miette::{Diagnostic, NamedSource, SourceCode, SourceOffset, SourceSpan};
 use prost_reflect::DescriptorError;
-use protox_parse::ParseError;
+use prost_types::FileDescriptorProto;
+use protox_parse::{ParseError, Warning};
 use thiserror::Error;
 
-use crate::file::File;
+use crate::file::{File, FileEncoding};
 
 /// An error that can occur when compiling protobuf files.
 #[derive(Diagnostic, Error)]
@@ -31,12 +32,21 @@ pub(crate) enum ErrorKind {
         err: io::Error,
     },
     #[error("file '{name}' is too large")]
-    #[diagnostic(help("the maximum file length is 2,147,483,647 bytes"))]
-    FileTooLarge { name: String },
+    #[diagnostic(help("the file is {size} bytes, but the maximum file length is {limit} bytes"))]
+    FileTooLarge { name: String, size: u64, limit: u64 },
     #[error("file '{name}' is not valid utf-8")]
     FileInvalidUtf8 { name: String },
+    #[error("file '{name}' is not valid {encoding}")]
+    FileInvalidEncoding {
+        name: String,
+        encoding: FileEncoding,
+    },
     #[error("file '{name}' not found")]
-    FileNotFound { name: String },
+    FileNotFound {
+        name: String,
+        #[help]
+        searched: Option<SearchedPaths>,
+    },
     #[error("import '{name}' not found")]
     ImportNotFound {
         #[label("imported here")]
@@ -44,9 +54,216 @@ pub(crate) enum ErrorKind {
         #[source_code]
         source_code: NamedSource<String>,
         name: String,
+        #[help]
+        searched: Option<SearchedPaths>,
+    },
+    #[error("import '{name}' is not allowed")]
+    ImplicitImportNotAllowed {
+        #[label("imported here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+        name: String,
+    },
+    #[error("import '{name}' is not used")]
+    UnusedImport {
+        #[label("imported here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+        name: String,
+        #[related]
+        related: Vec<ErrorKind>,
+    },
+    #[error(
+        "field '{field_name}' not found for message '{message_name}' in option '{option_name}'"
+    )]
+    OptionUnknownField {
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+        option_name: String,
+        field_name: String,
+        message_name: String,
+    },
+    #[error("name '{name}' is already defined in file '{first_file}'")]
+    DuplicateName {
+        name: String,
+        first_file: String,
+        #[label("defined again here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+        #[related]
+        first: Option<FirstDefinition>,
+    },
+    #[error("package '{name}' declared in file '{package_file}' conflicts with a definition in file '{definition_file}'")]
+    DuplicatePackage {
+        name: String,
+        file: String,
+        package_file: String,
+        definition_file: String,
+    },
+    #[error("field name '{name}' is reserved")]
+    FieldNameReserved {
+        name: String,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[label("reserved here")]
+        reserved_span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("name '{name}' of the synthetic oneof for field '{field_name}' is defined twice")]
+    SyntheticOneofNameConflict {
+        name: String,
+        field_name: String,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[label("synthetic oneof generated for this field")]
+        field_span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("'{name}' is a synthetic map entry message type, which cannot be used as the type of field '{field_name}'")]
+    MapEntryFieldType {
+        name: String,
+        field_name: String,
+        #[label("used here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
     },
+    #[error("'{message_name}' is a synthetic map entry message type, which cannot be extended")]
+    ExtendingMapEntry {
+        message_name: String,
+        #[label("extended here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("option field '{field_name}' is not repeated, so it cannot be set to a list")]
+    ListOptionValueForSingularField {
+        field_name: String,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("negative value {value} is not valid for unsigned option field '{field_name}'")]
+    #[diagnostic(help("the value must be between 0 and {max} inclusive"))]
+    OptionValueNegativeForUnsigned {
+        field_name: String,
+        value: i64,
+        max: u64,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("message '{message_name}' does not define {number} as an extension number")]
+    InvalidExtensionNumber {
+        message_name: String,
+        number: i32,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[help]
+        help: Option<String>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("invalid JSON name '{json_name}' for field '{name}': {message}")]
+    InvalidJsonName {
+        name: String,
+        json_name: String,
+        message: String,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("method '{method_name}' {kind} type '{type_name}' not found")]
+    MethodTypeNotFound {
+        method_name: String,
+        kind: &'static str,
+        type_name: String,
+        #[label("used here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("extension range {} to {} of message '{message}' overlaps with range {} to {}", .second.0, .second.1, .first.0, .first.1)]
+    OverlappingExtensionRanges {
+        message: String,
+        first: (i32, i32),
+        second: (i32, i32),
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[label("overlapping range defined here")]
+        first_span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("extension range {} to {} of message '{message}' is not used by any extension", .range.0, .range.1)]
+    UnusedExtensionRange {
+        message: String,
+        range: (i32, i32),
+        #[label("declared here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("field '{field_name}' uses deprecated message type '{type_name}'")]
+    DeprecatedTypeUsed {
+        field_name: String,
+        type_name: String,
+        #[label("used here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("message '{name}' has a chain of {depth} nested non-repeated message fields, which exceeds the maximum of {max_depth}")]
+    MessageNestingTooDeep {
+        name: String,
+        depth: u32,
+        max_depth: u32,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("message '{name}' contains itself through required message fields")]
+    RecursiveMessage {
+        name: String,
+        #[label("defined here")]
+        span: Option<SourceSpan>,
+        #[source_code]
+        source_code: NamedSource<String>,
+    },
+    #[error("file '{name}' does not have a syntax statement")]
+    #[diagnostic(help(
+        "add 'syntax = \"proto2\";' or 'syntax = \"proto3\";' to the start of the file"
+    ))]
+    MissingSyntaxDeclaration { name: String },
+    #[error("file '{name}' does not have a package statement")]
+    #[diagnostic(help("definitions in files without a package are in the global namespace, and may conflict with definitions in other files"))]
+    MissingPackageDeclaration { name: String },
+    #[error("file '{name}' has warnings, which are treated as errors")]
+    WarningsAsErrors {
+        name: String,
+        #[related]
+        warnings: Vec<Warning>,
+    },
+    #[error("file '{name}' has already been added with different content")]
+    FileContentConflict { name: String },
     #[error("import cycle detected: {cycle}")]
-    CircularImport { name: String, cycle: String },
+    CircularImport {
+        name: String,
+        cycle: String,
+        #[related]
+        imports: Vec<CycleImport>,
+    },
     #[error("file '{path}' is not in any include path")]
     FileNotIncluded { path: PathBuf },
     #[error("path '{path}' is shadowed by '{shadow}' in the include paths")]
@@ -56,10 +273,51 @@ pub(crate) enum ErrorKind {
         path: PathBuf,
         shadow: PathBuf,
     },
+    #[error("files '{first_name}' and '{name}' both have the name '{stripped_name}' after removing the name prefix")]
+    StrippedNameConflict {
+        name: String,
+        first_name: String,
+        stripped_name: String,
+    },
     #[error(transparent)]
     Custom(Box<dyn std::error::Error + Send + Sync>),
 }
 
+/// The file system paths searched for a file which was not found.
+#[derive(Debug, Default)]
+pub(crate) struct SearchedPaths(pub(crate) Vec<PathBuf>);
+
+impl fmt::Display for SearchedPaths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "searched the following paths:")?;
+        for path in &self.0 {
+            write!(f, "\n  • {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// An `import` statement forming one edge of an import cycle.
+#[derive(Debug, Diagnostic, Error)]
+#[error("'{}' imports '{name}'", source_code.name())]
+pub(crate) struct CycleImport {
+    pub(crate) name: String,
+    #[label("imported here")]
+    pub(crate) span: Option<SourceSpan>,
+    #[source_code]
+    pub(crate) source_code: NamedSource<String>,
+}
+
+/// The original definition of a name which is defined again in another file.
+#[derive(Debug, Diagnostic, Error)]
+#[error("'{}' first defines the name here", source_code.name())]
+pub(crate) struct FirstDefinition {
+    #[label("first defined here")]
+    pub(crate) span: Option<SourceSpan>,
+    #[source_code]
+    pub(crate) source_code: NamedSource<String>,
+}
+
 impl Error {
     /// Creates an instance of [`struct@Error`] with an arbitrary payload.
     pub fn new<E>(error: E) -> Self
@@ -75,23 +333,77 @@ impl Error {
     pub fn file_not_found(name: &str) -> Self {
         Error::from_kind(ErrorKind::FileNotFound {
             name: name.to_owned(),
+            searched: None,
         })
     }
 
+    /// Creates an instance of [`struct@Error`] from a [`ParseError`], attaching the given source code.
+    ///
+    /// See [`ParseError::with_source_code()`] for how the source code is used.
+    pub fn from_parse_error(err: ParseError, source: NamedSource<String>) -> Self {
+        Error::from_kind(ErrorKind::Parse {
+            err: err.with_source_code(source),
+        })
+    }
+
+    /// Creates an instance of [`Error::file_not_found()`] which lists the file system paths that were searched.
+    pub(crate) fn file_not_found_in(name: &str, searched: Vec<PathBuf>) -> Self {
+        Error::from_kind(ErrorKind::FileNotFound {
+            name: name.to_owned(),
+            searched: (!searched.is_empty()).then_some(SearchedPaths(searched)),
+        })
+    }
+
+    /// Gets the file system paths searched for a file which was not found.
+    pub(crate) fn into_searched_paths(self) -> Vec<PathBuf> {
+        match *self.kind {
+            ErrorKind::FileNotFound {
+                searched: Some(searched),
+                ..
+            } => searched.0,
+            _ => Vec::new(),
+        }
+    }
+
     /// The file in which this error occurred, if available.
     pub fn file(&self) -> Option<&str> {
         match &*self.kind {
             ErrorKind::Parse { err } => Some(err.file()),
             ErrorKind::Check { err } => err.file(),
             ErrorKind::OpenFile { name, .. }
-            | ErrorKind::FileTooLarge { name }
+            | ErrorKind::FileTooLarge { name, .. }
             | ErrorKind::FileInvalidUtf8 { name }
-            | ErrorKind::FileNotFound { name }
+            | ErrorKind::FileInvalidEncoding { name, .. }
+            | ErrorKind::FileNotFound { name, .. }
             | ErrorKind::CircularImport { name, .. }
-            | ErrorKind::FileShadowed { name, .. } => Some(name),
+            | ErrorKind::FileShadowed { name, .. }
+            | ErrorKind::StrippedNameConflict { name, .. }
+            | ErrorKind::FileContentConflict { name }
+            | ErrorKind::MissingSyntaxDeclaration { name }
+            | ErrorKind::MissingPackageDeclaration { name }
+            | ErrorKind::WarningsAsErrors { name, .. } => Some(name),
+            ErrorKind::DuplicatePackage { file, .. } => Some(file),
             ErrorKind::FileNotIncluded { .. } => None,
             ErrorKind::Custom(_) => None,
-            ErrorKind::ImportNotFound { source_code, .. } => Some(source_code.name()),
+            ErrorKind::ImportNotFound { source_code, .. }
+            | ErrorKind::ImplicitImportNotAllowed { source_code, .. }
+            | ErrorKind::UnusedImport { source_code, .. }
+            | ErrorKind::OptionUnknownField { source_code, .. }
+            | ErrorKind::OptionValueNegativeForUnsigned { source_code, .. }
+            | ErrorKind::ListOptionValueForSingularField { source_code, .. }
+            | ErrorKind::DuplicateName { source_code, .. }
+            | ErrorKind::FieldNameReserved { source_code, .. }
+            | ErrorKind::SyntheticOneofNameConflict { source_code, .. }
+            | ErrorKind::MapEntryFieldType { source_code, .. }
+            | ErrorKind::ExtendingMapEntry { source_code, .. }
+            | ErrorKind::InvalidExtensionNumber { source_code, .. }
+            | ErrorKind::InvalidJsonName { source_code, .. }
+            | ErrorKind::MethodTypeNotFound { source_code, .. }
+            | ErrorKind::OverlappingExtensionRanges { source_code, .. }
+            | ErrorKind::UnusedExtensionRange { source_code, .. }
+            | ErrorKind::MessageNestingTooDeep { source_code, .. }
+            | ErrorKind::DeprecatedTypeUsed { source_code, .. }
+            | ErrorKind::RecursiveMessage { source_code, .. } => Some(source_code.name()),
         }
     }
 
@@ -101,7 +413,6 @@ impl Error {
         }
     }
 
-    #[cfg(test)]
     pub(crate) fn kind(&self) -> &ErrorKind {
         &self.kind
     }
@@ -123,6 +434,41 @@ impl Error {
             ErrorKind::Parse { .. }
                 | ErrorKind::FileTooLarge { .. }
                 | ErrorKind::FileInvalidUtf8 { .. }
+                | ErrorKind::FileInvalidEncoding { .. }
+        )
+    }
+
+    /// Returns true if this error is caused by a file which imports itself, directly or indirectly.
+    pub fn is_circular_import(&self) -> bool {
+        matches!(&*self.kind, ErrorKind::CircularImport { .. })
+    }
+
+    /// Returns true if this error is caused by a file which is syntactically valid, but fails semantic checks such as
+    /// name resolution or option validation.
+    pub fn is_check(&self) -> bool {
+        matches!(
+            &*self.kind,
+            ErrorKind::Check { .. }
+                | ErrorKind::UnusedImport { .. }
+                | ErrorKind::OptionUnknownField { .. }
+                | ErrorKind::OptionValueNegativeForUnsigned { .. }
+                | ErrorKind::ListOptionValueForSingularField { .. }
+                | ErrorKind::DuplicateName { .. }
+                | ErrorKind::DuplicatePackage { .. }
+                | ErrorKind::FieldNameReserved { .. }
+                | ErrorKind::SyntheticOneofNameConflict { .. }
+                | ErrorKind::MapEntryFieldType { .. }
+                | ErrorKind::ExtendingMapEntry { .. }
+                | ErrorKind::InvalidExtensionNumber { .. }
+                | ErrorKind::InvalidJsonName { .. }
+                | ErrorKind::MethodTypeNotFound { .. }
+                | ErrorKind::OverlappingExtensionRanges { .. }
+                | ErrorKind::UnusedExtensionRange { .. }
+                | ErrorKind::MessageNestingTooDeep { .. }
+                | ErrorKind::DeprecatedTypeUsed { .. }
+                | ErrorKind::RecursiveMessage { .. }
+                | ErrorKind::MissingSyntaxDeclaration { .. }
+                | ErrorKind::MissingPackageDeclaration { .. }
         )
     }
 
@@ -135,44 +481,93 @@ impl Error {
         }
     }
 
-    pub(crate) fn into_import_error(self, file: &File, import_idx: usize) -> Self {
-        fn find_span(file: &File, import_idx: usize) -> Option<SourceSpan> {
-            if let Some(sci) = &file.descriptor.source_code_info {
-                if let Some(source) = file.source() {
-                    for location in &sci.location {
-                        if location.path == vec![3, import_idx as i32] {
-                            if location.span.len() != 3 {
-                                continue;
-                            }
-                            let start_line = location.span[0] as usize + 1;
-                            let start_col = location.span[1] as usize + 1;
-                            let end_col = location.span[2] as usize + 1;
-                            return Some(SourceSpan::new(
-                                SourceOffset::from_location(source, start_line, start_col),
-                                end_col - start_col,
-                            ));
-                        }
-                    }
-                }
-            }
-            None
-        }
-        match *self.kind {
-            ErrorKind::FileNotFound { name } => {
-                let source_code: NamedSource<String> =
-                    NamedSource::new(file.name(), file.source().unwrap_or_default().to_owned());
-                let span = find_span(file, import_idx);
+    pub(crate) fn import_not_allowed(file: &File, import_idx: usize) -> Self {
+        Error::from_kind(ErrorKind::ImplicitImportNotAllowed {
+            span: file
+                .source()
+                .and_then(|source| import_span(&file.descriptor, source, import_idx)),
+            source_code: NamedSource::new(
+                file.name(),
+                file.source().unwrap_or_default().to_owned(),
+            ),
+            name: file.descriptor.dependency[import_idx].clone(),
+        })
+    }
+
+    pub(crate) fn into_import_error(mut self, file: &File, import_idx: usize) -> Self {
+        let source_code = || -> NamedSource<String> {
+            NamedSource::new(file.name(), file.source().unwrap_or_default().to_owned())
+        };
+        let span = || {
+            file.source()
+                .and_then(|source| import_span(&file.descriptor, source, import_idx))
+        };
+
+        match &mut *self.kind {
+            ErrorKind::FileNotFound { name, searched } => {
                 Error::from_kind(ErrorKind::ImportNotFound {
-                    span,
-                    source_code,
-                    name,
+                    span: span(),
+                    source_code: source_code(),
+                    name: std::mem::take(name),
+                    searched: searched.take(),
                 })
             }
+            ErrorKind::CircularImport { name, imports, .. } => {
+                // The error is propagated back through every file in the import stack, but only the imports
+                // between the first and last occurrences of the repeated file form part of the cycle.
+                let is_complete = imports
+                    .first()
+                    .is_some_and(|import| import.source_code.name() == name);
+                if !is_complete {
+                    imports.insert(
+                        0,
+                        CycleImport {
+                            name: file.descriptor.dependency[import_idx].clone(),
+                            span: span(),
+                            source_code: source_code(),
+                        },
+                    );
+                }
+                self
+            }
             _ => self,
         }
     }
 }
 
+/// Finds the span of the import statement with the given index in a parsed file.
+pub(crate) fn import_span(
+    file: &FileDescriptorProto,
+    source: &str,
+    import_idx: usize,
+) -> Option<SourceSpan> {
+    location_span(file, source, &[3, import_idx as i32])
+}
+
+/// Finds the span of the definition at the given path in a parsed file.
+pub(crate) fn location_span(
+    file: &FileDescriptorProto,
+    source: &str,
+    path: &[i32],
+) -> Option<SourceSpan> {
+    let location = file
+        .source_code_info
+        .as_ref()?
+        .location
+        .iter()
+        .find(|location| location.path == path)?;
+
+    let (start_line, start_col, end_line, end_col) = match *location.span.as_slice() {
+        [start_line, start_col, end_col] => (start_line, start_col, start_line, end_col),
+        [start_line, start_col, end_line, end_col] => (start_line, start_col, end_line, end_col),
+        _ => return None,
+    };
+    let start =
+        SourceOffset::from_location(source, start_line as usize + 1, start_col as usize + 1);
+    let end = SourceOffset::from_location(source, end_line as usize + 1, end_col as usize + 1);
+    Some(SourceSpan::new(start, end.offset() - start.offset()))
+}
+
 impl From<DescriptorError> for Error {
     fn from(err: DescriptorError) -> Self {
         Error::from_kind(ErrorKind::Check { err })
@@ -199,13 +594,77 @@ impl fmt::Debug for Error {
             ErrorKind::OpenFile { err, .. } => write!(f, "{}: {}", self, err),
             ErrorKind::FileTooLarge { .. }
             | ErrorKind::FileInvalidUtf8 { .. }
+            | ErrorKind::FileInvalidEncoding { .. }
             | ErrorKind::FileNotFound { .. }
             | ErrorKind::CircularImport { .. }
             | ErrorKind::FileNotIncluded { .. }
-            | ErrorKind::FileShadowed { .. } => write!(f, "{}", self),
+            | ErrorKind::DuplicatePackage { .. }
+            | ErrorKind::MissingSyntaxDeclaration { .. }
+            | ErrorKind::MissingPackageDeclaration { .. }
+            | ErrorKind::FileShadowed { .. }
+            | ErrorKind::StrippedNameConflict { .. }
+            | ErrorKind::FileContentConflict { .. } => write!(f, "{}", self),
             ErrorKind::Custom(err) => err.fmt(f),
+            ErrorKind::WarningsAsErrors { warnings, .. } => {
+                write!(f, "{}: ", self)?;
+                warnings[0].fmt(f)
+            }
             ErrorKind::ImportNotFound {
                 span, source_code, ..
+            }
+            | ErrorKind::ImplicitImportNotAllowed {
+                span, source_code, ..
+            }
+            | ErrorKind::UnusedImport {
+                span, source_code, ..
+            }
+            | ErrorKind::OptionUnknownField {
+                span, source_code, ..
+            }
+            | ErrorKind::OptionValueNegativeForUnsigned {
+                span, source_code, ..
+            }
+            | ErrorKind::ListOptionValueForSingularField {
+                span, source_code, ..
+            }
+            | ErrorKind::DuplicateName {
+                span, source_code, ..
+            }
+            | ErrorKind::FieldNameReserved {
+                span, source_code, ..
+            }
+            | ErrorKind::SyntheticOneofNameConflict {
+                span, source_code, ..
+            }
+            | ErrorKind::MapEntryFieldType {
+                span, source_code, ..
+            }
+            | ErrorKind::ExtendingMapEntry {
+                span, source_code, ..
+            }
+            | ErrorKind::InvalidExtensionNumber {
+                span, source_code, ..
+            }
+            | ErrorKind::InvalidJsonName {
+                span, source_code, ..
+            }
+            | ErrorKind::MethodTypeNotFound {
+                span, source_code, ..
+            }
+            | ErrorKind::OverlappingExtensionRanges {
+                span, source_code, ..
+            }
+            | ErrorKind::UnusedExtensionRange {
+                span, source_code, ..
+            }
+            | ErrorKind::MessageNestingTooDeep {
+                span, source_code, ..
+            }
+            | ErrorKind::DeprecatedTypeUsed {
+                span, source_code, ..
+            }
+            | ErrorKind::RecursiveMessage {
+                span, source_code, ..
             } => {
                 write!(f, "{}:", source_code.name())?;
                 if let Some(span) = span {
@@ -240,6 +699,23 @@ fn fmt_debug_io() {
     );
 }
 
+#[test]
+fn from_parse_error() {
+    let source = "message Foo {\n  optional int32 a = 1\n}\n";
+    let parse_err = protox_parse::parse("file.proto", source).unwrap_err();
+    let err = Error::from_parse_error(
+        parse_err,
+        NamedSource::new("path/to/file.proto", source.to_owned()),
+    );
+
+    assert!(err.is_parse());
+    assert_eq!(err.file(), Some("path/to/file.proto"));
+    assert_eq!(
+        format!("{:?}", err),
+        "path/to/file.proto:3:1: expected ';' or '[', but found '}'"
+    );
+}
+
 #[test]
 fn fmt_debug_parse() {
     let err = Error::from(protox_parse::parse("file.proto", "invalid").unwrap_err());
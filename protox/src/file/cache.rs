@@ -0,0 +1,113 @@
+#[cfg(feature = "source-hash")]
+use std::sync::OnceLock;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use prost::Message;
+use prost_types::FileDescriptorProto;
+use protox_parse::ParseOptions;
+use sha2::{Digest, Sha256};
+
+use super::File;
+use crate::Error;
+
+/// Prefixed to the source when computing the name of a cache entry, so that entries written by a different version
+/// of protox, which may parse files differently, are never loaded.
+const KEY_PREFIX: &str = concat!("protox ", env!("CARGO_PKG_VERSION"), "\0");
+
+/// A directory of parsed files, shared between a [`Compiler`](crate::Compiler) and the resolvers it creates.
+///
+/// Each entry is named after the SHA-256 hash of the source it was parsed from and the crate version, so any change to
+/// a file's contents, or upgrading protox, results in a cache miss. Errors reading or writing the cache are ignored,
+/// and the file is parsed as normal.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct FileCache {
+    dir: Arc<RwLock<Option<PathBuf>>>,
+}
+
+impl FileCache {
+    pub(crate) fn set_dir(&self, dir: Option<PathBuf>) {
+        *self.dir.write().unwrap() = dir;
+    }
+
+    /// Parses a file read from `path`, loading it from the cache if possible.
+    pub(crate) fn parse(
+        &self,
+        name: &str,
+        path: &Path,
+        source: String,
+        options: &ParseOptions,
+    ) -> Result<File, Error> {
+        let (descriptor, warnings) = match self.load(name, &source) {
+            Some(descriptor) => (descriptor, Vec::new()),
+            None => {
+                let (descriptor, warnings) =
+                    protox_parse::parse_with_options(name, &source, options)?;
+                // Warnings are not stored in the cache, so files with warnings are always parsed.
+                if warnings.is_empty() {
+                    self.store(&source, &descriptor);
+                }
+                (descriptor, warnings)
+            }
+        };
+
+        Ok(File {
+            path: Some(path.to_owned()),
+            source: Some(source),
+            descriptor,
+            encoded: None,
+            warnings,
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
+        })
+    }
+
+    /// Loads the parsed descriptor for a file with the given source, if one has been cached.
+    fn load(&self, name: &str, source: &str) -> Option<FileDescriptorProto> {
+        let path = self.entry_path(source)?;
+        let bytes = fs::read(path).ok()?;
+        let mut descriptor = FileDescriptorProto::decode(bytes.as_slice()).ok()?;
+        // Files with identical contents may be opened under different names.
+        descriptor.name = Some(name.to_owned());
+        Some(descriptor)
+    }
+
+    /// Stores the parsed descriptor for a file with the given source.
+    fn store(&self, source: &str, descriptor: &FileDescriptorProto) {
+        let Some(path) = self.entry_path(source) else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            if fs::create_dir_all(dir).is_err() {
+                return;
+            }
+        }
+
+        // Write to a temporary file first, so a concurrent reader never sees a partially written entry.
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        if fs::write(&tmp_path, descriptor.encode_to_vec()).is_ok()
+            && fs::rename(&tmp_path, &path).is_err()
+        {
+            let _ = fs::remove_file(&tmp_path);
+        }
+    }
+
+    fn entry_path(&self, source: &str) -> Option<PathBuf> {
+        let dir = self.dir.read().unwrap().clone()?;
+
+        let mut file_name = String::with_capacity(68);
+        let hash = Sha256::new()
+            .chain_update(KEY_PREFIX)
+            .chain_update(source)
+            .finalize();
+        for byte in hash {
+            write!(&mut file_name, "{:02x}", byte).unwrap();
+        }
+        file_name.push_str(".bin");
+        Some(dir.join(file_name))
+    }
+}
@@ -5,7 +5,8 @@ use crate::Error;
 
 /// An implementation of [`FileResolver`] which chains together several other resolvers.
 ///
-/// When opening files, each resolver is searched in turn until the file is found.
+/// When opening files, each resolver is searched in turn until the file is found. If no resolver has the file,
+/// the error lists every file system path that was searched.
 #[derive(Default)]
 pub struct ChainFileResolver {
     resolvers: Vec<Box<dyn FileResolver>>,
@@ -26,6 +27,24 @@ impl ChainFileResolver {
     {
         self.resolvers.push(Box::new(resolver))
     }
+
+    /// Adds a new resolver, which will be searched before all previously-added resolvers.
+    pub fn prepend<F>(&mut self, resolver: F)
+    where
+        F: FileResolver + 'static,
+    {
+        self.resolvers.insert(0, Box::new(resolver))
+    }
+
+    /// Adds a new resolver, which will be searched after all previously-added resolvers.
+    ///
+    /// This is equivalent to [`add()`](ChainFileResolver::add).
+    pub fn append<F>(&mut self, resolver: F)
+    where
+        F: FileResolver + 'static,
+    {
+        self.add(resolver)
+    }
 }
 
 impl FileResolver for ChainFileResolver {
@@ -40,15 +59,26 @@ impl FileResolver for ChainFileResolver {
     }
 
     fn open_file(&self, name: &str) -> Result<File, Error> {
+        let mut searched = Vec::new();
         for resolver in &self.resolvers {
             match resolver.open_file(name) {
                 Ok(file) => return Ok(file),
-                Err(err) if err.is_file_not_found() => continue,
+                Err(err) if err.is_file_not_found() => searched.extend(err.into_searched_paths()),
                 Err(err) => return Err(err),
             }
         }
 
-        Err(Error::file_not_found(name))
+        Err(Error::file_not_found_in(name, searched))
+    }
+
+    fn list_files(&self) -> Result<Vec<String>, Error> {
+        let mut files = Vec::new();
+        for resolver in &self.resolvers {
+            files.extend(resolver.list_files()?);
+        }
+        files.sort();
+        files.dedup();
+        Ok(files)
     }
 }
 
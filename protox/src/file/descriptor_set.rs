@@ -1,3 +1,6 @@
+#[cfg(feature = "source-hash")]
+use std::sync::OnceLock;
+
 use bytes::{Buf, Bytes};
 use prost::{
     encoding::{check_wire_type, decode_key, decode_varint, skip_field, DecodeContext, WireType},
@@ -63,6 +66,10 @@ impl DescriptorSetFileResolver {
         }
         Ok(DescriptorSetFileResolver { set })
     }
+
+    pub(crate) fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.set.iter().map(|file| file.file.name())
+    }
 }
 
 impl FileResolver for DescriptorSetFileResolver {
@@ -74,6 +81,9 @@ impl FileResolver for DescriptorSetFileResolver {
                     source: None,
                     descriptor: file.file.clone(),
                     encoded: file.encoded.clone(),
+                    warnings: Vec::new(),
+                    #[cfg(feature = "source-hash")]
+                    source_hash: OnceLock::new(),
                 });
             }
         }
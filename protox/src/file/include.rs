@@ -1,19 +1,224 @@
-use std::path::{self, Path, PathBuf};
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{self, Path, PathBuf},
+};
 
 use crate::{error::ErrorKind, Error};
 
-use super::{File, FileResolver};
+#[cfg(feature = "file-cache")]
+use super::FileCache;
+use super::{File, FileParseOptions, FileReadLimit, FileResolver};
 
 /// An implementation of [`FileResolver`] which searches an include path on the file system.
 #[derive(Debug)]
 pub struct IncludeFileResolver {
     include: PathBuf,
+    extensions: Vec<String>,
+    follow_symlinks: bool,
+    encoding: FileEncoding,
+    #[cfg(feature = "file-cache")]
+    cache: Option<FileCache>,
+    read_limit: FileReadLimit,
+    parse_options: FileParseOptions,
+}
+
+/// The text encoding of protobuf source files read by an [`IncludeFileResolver`].
+///
+/// Protobuf source files should be UTF-8, but other encodings may be used by legacy code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileEncoding {
+    /// UTF-8, which is the default.
+    #[default]
+    Utf8,
+    /// Little-endian UTF-16. A leading byte order mark is ignored.
+    Utf16Le,
+    /// Big-endian UTF-16. A leading byte order mark is ignored.
+    Utf16Be,
+    /// ISO-8859-1, where each byte is decoded to the Unicode code point with the same value.
+    Latin1,
 }
 
 impl IncludeFileResolver {
     /// Constructs a `IncludeFileResolver` that searches the given include path.
     pub fn new(include: PathBuf) -> Self {
-        IncludeFileResolver { include }
+        IncludeFileResolver {
+            include,
+            extensions: vec!["proto".to_owned()],
+            follow_symlinks: true,
+            encoding: FileEncoding::Utf8,
+            #[cfg(feature = "file-cache")]
+            cache: None,
+            read_limit: FileReadLimit::default(),
+            parse_options: FileParseOptions::default(),
+        }
+    }
+
+    /// Restricts this resolver to only open files with one of the given extensions. Defaults to `["proto"]`.
+    ///
+    /// Requests for any other file names return [`Error::file_not_found()`] without accessing the file system.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::{fs, path::PathBuf};
+    /// # use protox::file::{IncludeFileResolver, FileResolver};
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("./foo.proto", "").unwrap();
+    /// fs::write("./foo.txt", "").unwrap();
+    ///
+    /// let resolver = IncludeFileResolver::new(PathBuf::from("."));
+    /// assert!(resolver.open_file("foo.proto").is_ok());
+    /// assert!(resolver.open_file("foo.txt").unwrap_err().is_file_not_found());
+    ///
+    /// let resolver = resolver.with_extension_filter(&["proto", "txt"]);
+    /// assert!(resolver.open_file("foo.txt").is_ok());
+    /// ```
+    pub fn with_extension_filter(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions.iter().map(|&ext| ext.to_owned()).collect();
+        self
+    }
+
+    /// Sets whether this resolver opens files through symbolic links. Defaults to `true`.
+    ///
+    /// If set to `false`, requests for a file which is a symbolic link, or is contained in a directory under the
+    /// include path which is a symbolic link, return [`Error::file_not_found()`]. The include path itself may still
+    /// be a symbolic link.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::{fs, path::PathBuf};
+    /// # use protox::file::{IncludeFileResolver, FileResolver};
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("./foo.proto", "").unwrap();
+    /// # #[cfg(unix)]
+    /// std::os::unix::fs::symlink("foo.proto", "./bar.proto").unwrap();
+    ///
+    /// let resolver = IncludeFileResolver::new(PathBuf::from(".")).with_follow_symlinks(false);
+    /// assert!(resolver.open_file("foo.proto").is_ok());
+    /// # #[cfg(unix)]
+    /// assert!(resolver.open_file("bar.proto").unwrap_err().is_file_not_found());
+    /// ```
+    pub fn with_follow_symlinks(mut self, yes: bool) -> Self {
+        self.follow_symlinks = yes;
+        self
+    }
+
+    /// Sets the text encoding of source files opened by this resolver. Defaults to [`FileEncoding::Utf8`].
+    ///
+    /// Files are converted to UTF-8 before being parsed, so [`File::source()`] and the spans of any errors refer to
+    /// the converted source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::{fs, path::PathBuf};
+    /// # use protox::file::{FileEncoding, IncludeFileResolver, FileResolver};
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::write("./foo.proto", b"// caf\xe9\nmessage Foo {}").unwrap();
+    ///
+    /// let resolver = IncludeFileResolver::new(PathBuf::from(".")).with_encoding(FileEncoding::Latin1);
+    /// let file = resolver.open_file("foo.proto").unwrap();
+    /// assert_eq!(file.source(), Some("// caf\u{e9}\nmessage Foo {}"));
+    /// ```
+    pub fn with_encoding(mut self, encoding: FileEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    #[cfg(feature = "file-cache")]
+    pub(crate) fn with_cache(mut self, cache: FileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub(crate) fn with_read_limit(mut self, read_limit: FileReadLimit) -> Self {
+        self.read_limit = read_limit;
+        self
+    }
+
+    pub(crate) fn with_parse_options(mut self, parse_options: FileParseOptions) -> Self {
+        self.parse_options = parse_options;
+        self
+    }
+
+    /// Returns `true` if `file`, opened from the include path, was not reached through a symbolic link.
+    ///
+    /// The path is checked after the file is opened, since a directory could be replaced by a symbolic link between
+    /// checking the path and opening it. On Unix, the path must also still refer to the opened file.
+    pub(super) fn is_opened_without_symlink(&self, name: &str, file: &fs::File) -> bool {
+        if self.has_symlink(name) {
+            return false;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let (Ok(opened), Ok(current)) = (
+                file.metadata(),
+                fs::symlink_metadata(self.include.join(name)),
+            ) else {
+                return false;
+            };
+            opened.dev() == current.dev() && opened.ino() == current.ino()
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = file;
+            true
+        }
+    }
+
+    fn has_symlink(&self, name: &str) -> bool {
+        let mut path = self.include.clone();
+        Path::new(name).components().any(|component| {
+            path.push(component);
+            fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+        })
+    }
+
+    /// Recursively adds the names of source files in `dir`, skipping directories which have already been visited.
+    fn list_dir(
+        &self,
+        dir: &Path,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<String>,
+    ) -> io::Result<()> {
+        if !visited.insert(fs::canonicalize(dir)?) {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !self.follow_symlinks && entry.file_type()?.is_symlink() {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.list_dir(&path, visited, files)?;
+            } else if let Some(name) =
+                strip_prefix(&path, &self.include).and_then(path_to_file_name)
+            {
+                if self.has_allowed_extension(&name) && path.is_file() {
+                    files.push(name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn has_allowed_extension(&self, name: &str) -> bool {
+        Path::new(name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|allowed| allowed == ext))
     }
 }
 
@@ -48,7 +253,10 @@ impl FileResolver for IncludeFileResolver {
     /// Returns an error if there is an IO error opening the file, or it is not
     /// a valid protobuf source file.
     ///
-    /// If the file does not exist, [`Error::file_not_found()`] is returned
+    /// If the file does not exist, does not have an extension allowed by
+    /// [`with_extension_filter()`](IncludeFileResolver::with_extension_filter), or is a symbolic link when
+    /// [`with_follow_symlinks(false)`](IncludeFileResolver::with_follow_symlinks) is set, [`Error::file_not_found()`]
+    /// is returned
     ///
     /// # Examples
     ///
@@ -65,7 +273,91 @@ impl FileResolver for IncludeFileResolver {
     /// assert_eq!(file.source(), Some("/* hello! */"));
     /// ```
     fn open_file(&self, name: &str) -> Result<File, Error> {
-        File::open(name, &self.include.join(name))
+        if !self.has_allowed_extension(name) {
+            return Err(Error::file_not_found(name));
+        }
+
+        let path = self.include.join(name);
+        let file = File::open_source_file(name, &path)?;
+        if !self.follow_symlinks && !self.is_opened_without_symlink(name, &file) {
+            return Err(Error::file_not_found_in(name, vec![path]));
+        }
+
+        let source =
+            File::read_source_file(name, &path, file, self.encoding, self.read_limit.get())?;
+        #[cfg(feature = "file-cache")]
+        if let Some(cache) = &self.cache {
+            return cache.parse(name, &path, source, &self.parse_options.get());
+        }
+        File::parse_source_file(name, &path, source, &self.parse_options.get())
+    }
+
+    /// Lists the names of all protobuf source files under the include path.
+    ///
+    /// Only files with an extension allowed by
+    /// [`with_extension_filter()`](IncludeFileResolver::with_extension_filter) are included, which defaults to
+    /// `.proto`. If the include path does not exist, an empty list is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::{fs, path::PathBuf};
+    /// # use protox::file::{IncludeFileResolver, FileResolver};
+    /// # let tempdir = tempfile::TempDir::new().unwrap();
+    /// # std::env::set_current_dir(&tempdir).unwrap();
+    /// fs::create_dir("dir").unwrap();
+    /// fs::write("dir/foo.proto", "").unwrap();
+    /// fs::write("bar.proto", "").unwrap();
+    /// fs::write("README.md", "").unwrap();
+    ///
+    /// let resolver = IncludeFileResolver::new(PathBuf::from("."));
+    /// assert_eq!(resolver.list_files().unwrap(), ["bar.proto", "dir/foo.proto"]);
+    /// ```
+    fn list_files(&self) -> Result<Vec<String>, Error> {
+        let mut files = Vec::new();
+        match self.list_dir(&self.include, &mut HashSet::new(), &mut files) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !self.include.exists() => (),
+            Err(err) => return Err(err.into()),
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl FileEncoding {
+    /// Converts the contents of a file to UTF-8, returning `None` if it is not valid in this encoding.
+    pub(crate) fn decode(self, bytes: Vec<u8>) -> Option<String> {
+        let decode_utf16 = |from_bytes: fn([u8; 2]) -> u16| -> Option<String> {
+            if bytes.len() % 2 != 0 {
+                return None;
+            }
+
+            let mut units = bytes
+                .chunks_exact(2)
+                .map(|chunk| from_bytes([chunk[0], chunk[1]]))
+                .peekable();
+            units.next_if_eq(&0xfeff);
+            char::decode_utf16(units).collect::<Result<_, _>>().ok()
+        };
+
+        match self {
+            FileEncoding::Utf8 => String::from_utf8(bytes).ok(),
+            FileEncoding::Utf16Le => decode_utf16(u16::from_le_bytes),
+            FileEncoding::Utf16Be => decode_utf16(u16::from_be_bytes),
+            FileEncoding::Latin1 => Some(bytes.iter().map(|&byte| char::from(byte)).collect()),
+        }
+    }
+}
+
+impl fmt::Display for FileEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileEncoding::Utf8 => write!(f, "UTF-8"),
+            FileEncoding::Utf16Le => write!(f, "UTF-16LE"),
+            FileEncoding::Utf16Be => write!(f, "UTF-16BE"),
+            FileEncoding::Latin1 => write!(f, "ISO-8859-1"),
+        }
     }
 }
 
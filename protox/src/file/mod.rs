@@ -1,32 +1,91 @@
 //! Interfaces for customizing resolution of protobuf source files.
 
+#[cfg(feature = "file-cache")]
+mod cache;
 mod chain;
 mod descriptor_set;
 mod google;
 mod include;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "zip")]
+mod zip;
 
+#[cfg(feature = "zip")]
+pub use self::zip::ZipFileResolver;
 pub use chain::ChainFileResolver;
 pub use descriptor_set::DescriptorSetFileResolver;
 pub use google::GoogleFileResolver;
-pub use include::IncludeFileResolver;
-use prost_types::FileDescriptorProto;
+pub use include::{FileEncoding, IncludeFileResolver};
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorProto, SourceCodeInfo};
 
+#[cfg(feature = "source-hash")]
+use std::sync::OnceLock;
 use std::{
     fs,
     io::{self, Read},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
 };
 
 use bytes::{Buf, Bytes};
+#[cfg(feature = "file-cache")]
+pub(crate) use cache::FileCache;
 pub(crate) use include::{check_shadow, path_to_file_name};
-use prost::{DecodeError, Message};
+use prost::{
+    encoding::{decode_key, skip_field, DecodeContext},
+    DecodeError, Message,
+};
+use protox_parse::{ParseError, ParseOptions, Warning};
+#[cfg(feature = "source-hash")]
+use sha2::{Digest, Sha256};
 
 use crate::error::{Error, ErrorKind};
 
 const MAX_FILE_LEN: u64 = i32::MAX as u64;
 
+/// The default maximum size of a source file read from the file system, in bytes.
+pub(crate) const DEFAULT_FILE_READ_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// The maximum size of files read by an [`IncludeFileResolver`], shared with the [`Compiler`](crate::Compiler) which
+/// created it.
+#[derive(Debug, Clone)]
+pub(crate) struct FileReadLimit(Arc<AtomicU64>);
+
+impl FileReadLimit {
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set(&self, bytes: u64) {
+        self.0.store(bytes, Ordering::Relaxed)
+    }
+}
+
+impl Default for FileReadLimit {
+    fn default() -> Self {
+        FileReadLimit(Arc::new(AtomicU64::new(DEFAULT_FILE_READ_LIMIT)))
+    }
+}
+
+/// The options used to parse files opened by an [`IncludeFileResolver`], shared with the
+/// [`Compiler`](crate::Compiler) which created it.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct FileParseOptions(Arc<RwLock<ParseOptions>>);
+
+impl FileParseOptions {
+    pub(crate) fn get(&self) -> ParseOptions {
+        self.0.read().unwrap().clone()
+    }
+
+    pub(crate) fn set(&self, options: ParseOptions) {
+        *self.0.write().unwrap() = options;
+    }
+}
+
 /// A strategy for locating protobuf source files.
 ///
 /// The main implementation is [`IncludeFileResolver`] which uses the file system, but
@@ -43,6 +102,13 @@ pub trait FileResolver {
     ///
     /// If the file is not found, the implementation should return [`Error::file_not_found`].
     fn open_file(&self, name: &str) -> Result<File, Error>;
+
+    /// Lists the names of all protobuf source files which can be opened by this resolver.
+    ///
+    /// The default implementation returns an empty list, for resolvers which cannot enumerate their files.
+    fn list_files(&self) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
 }
 
 impl<T> FileResolver for Box<T>
@@ -56,6 +122,10 @@ where
     fn open_file(&self, name: &str) -> Result<File, Error> {
         (**self).open_file(name)
     }
+
+    fn list_files(&self) -> Result<Vec<String>, Error> {
+        (**self).list_files()
+    }
 }
 
 /// An opened protobuf source file, returned by [`FileResolver::open_file`].
@@ -65,6 +135,9 @@ pub struct File {
     pub(crate) source: Option<String>,
     pub(crate) descriptor: FileDescriptorProto,
     pub(crate) encoded: Option<Bytes>,
+    pub(crate) warnings: Vec<Warning>,
+    #[cfg(feature = "source-hash")]
+    pub(crate) source_hash: OnceLock<[u8; 32]>,
 }
 
 /// Information about a [`File`] after it has been added to a [`Compiler`](crate::Compiler) instance.
@@ -72,6 +145,7 @@ pub struct File {
 pub struct FileMetadata {
     pub(crate) name: String,
     pub(crate) path: Option<PathBuf>,
+    pub(crate) source: Option<String>,
     pub(crate) is_import: bool,
 }
 
@@ -117,41 +191,105 @@ impl File {
     /// assert!(File::open("notfound.proto", "notfound.proto".as_ref()).unwrap_err().is_file_not_found());
     /// ```
     pub fn open(name: &str, path: &Path) -> Result<Self, Error> {
-        let map_io_err = |err: io::Error| -> Error {
-            match err.kind() {
-                io::ErrorKind::NotFound => Error::file_not_found(name),
-                io::ErrorKind::InvalidData => Error::from_kind(ErrorKind::FileInvalidUtf8 {
-                    name: name.to_owned(),
-                }),
-                _ => Error::from_kind(ErrorKind::OpenFile {
-                    name: name.to_owned(),
-                    path: path.to_owned(),
-                    err,
-                }),
-            }
-        };
+        File::open_with_encoding(
+            name,
+            path,
+            FileEncoding::Utf8,
+            MAX_FILE_LEN,
+            &ParseOptions::default(),
+        )
+    }
+
+    /// Reads a protobuf source file with the given text encoding, converting it to UTF-8 before parsing.
+    pub(crate) fn open_with_encoding(
+        name: &str,
+        path: &Path,
+        encoding: FileEncoding,
+        limit: u64,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
+        let file = File::open_source_file(name, path)?;
+        let source = File::read_source_file(name, path, file, encoding, limit)?;
+        File::parse_source_file(name, path, source, options)
+    }
 
-        let file = fs::File::open(path).map_err(map_io_err)?;
+    /// Opens a protobuf source file for reading with [`read_source_file()`](File::read_source_file).
+    pub(crate) fn open_source_file(name: &str, path: &Path) -> Result<fs::File, Error> {
+        fs::File::open(path).map_err(|err| map_io_err(name, path, err))
+    }
+
+    /// Reads an opened protobuf source file with the given text encoding, converting it to UTF-8.
+    ///
+    /// Files larger than `limit` bytes are rejected, either before reading if the size reported by the file system
+    /// is too large, or once more than `limit` bytes have been read.
+    pub(crate) fn read_source_file(
+        name: &str,
+        path: &Path,
+        file: fs::File,
+        encoding: FileEncoding,
+        limit: u64,
+    ) -> Result<String, Error> {
+        let map_io_err = |err: io::Error| map_io_err(name, path, err);
         let metadata = file.metadata().map_err(map_io_err)?;
 
-        if metadata.len() > MAX_FILE_LEN {
+        let limit = limit.min(MAX_FILE_LEN);
+        if metadata.len() > limit {
             return Err(Error::from_kind(ErrorKind::FileTooLarge {
                 name: name.to_owned(),
+                size: metadata.len(),
+                limit,
             }));
         }
 
-        let mut buf = String::with_capacity(metadata.len() as usize);
-        file.take(MAX_FILE_LEN)
-            .read_to_string(&mut buf)
+        // The metadata may not report the actual length, for example for a named pipe, so check the number of
+        // bytes read as well.
+        let mut bytes = Vec::with_capacity(metadata.len() as usize);
+        file.take(limit + 1)
+            .read_to_end(&mut bytes)
             .map_err(map_io_err)?;
+        if bytes.len() as u64 > limit {
+            return Err(Error::from_kind(ErrorKind::FileTooLarge {
+                name: name.to_owned(),
+                size: bytes.len() as u64,
+                limit,
+            }));
+        }
 
-        let descriptor = protox_parse::parse(name, &buf)?;
+        let buf = if encoding == FileEncoding::Utf8 {
+            String::from_utf8(bytes).map_err(|_| {
+                Error::from_kind(ErrorKind::FileInvalidUtf8 {
+                    name: name.to_owned(),
+                })
+            })?
+        } else {
+            encoding.decode(bytes).ok_or_else(|| {
+                Error::from_kind(ErrorKind::FileInvalidEncoding {
+                    name: name.to_owned(),
+                    encoding,
+                })
+            })?
+        };
+
+        Ok(buf)
+    }
+
+    /// Parses the source of a file read from `path`.
+    pub(crate) fn parse_source_file(
+        name: &str,
+        path: &Path,
+        source: String,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
+        let (descriptor, warnings) = protox_parse::parse_with_options(name, &source, options)?;
 
         Ok(File {
             path: Some(path.to_owned()),
-            source: Some(buf),
+            source: Some(source),
             descriptor,
             encoded: None,
+            warnings,
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
         })
     }
 
@@ -187,13 +325,46 @@ impl File {
     /// });
     /// ```
     pub fn from_source(name: &str, source: &str) -> Result<Self, Error> {
-        let descriptor = protox_parse::parse(name, source)?;
+        File::from_source_with_options(name, source, &ParseOptions::default())
+    }
+
+    /// Read a protobuf source file from a string into a new instance of [`File`], using the given parse options.
+    ///
+    /// Constructs which are allowed by `options`, but are rejected by protoc, are returned by
+    /// [`warnings()`](File::warnings).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error the string is not a valid protobuf source file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::file::File;
+    /// # use protox_parse::ParseOptions;
+    /// let source = "syntax = 'proto3'; message Foo { int32 a = 1 [default = 5]; }";
+    /// assert!(File::from_source("foo.proto", source).is_err());
+    ///
+    /// let mut options = ParseOptions::new();
+    /// options.allow_proto3_default_values(true);
+    /// let file = File::from_source_with_options("foo.proto", source, &options).unwrap();
+    /// assert_eq!(file.warnings().len(), 1);
+    /// ```
+    pub fn from_source_with_options(
+        name: &str,
+        source: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
+        let (descriptor, warnings) = protox_parse::parse_with_options(name, source, options)?;
 
         Ok(File {
             path: None,
             source: Some(source.to_owned()),
             descriptor,
             encoded: None,
+            warnings,
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
         })
     }
 
@@ -206,6 +377,9 @@ impl File {
             source: None,
             descriptor: file,
             encoded: None,
+            warnings: Vec::new(),
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
         }
     }
 
@@ -226,6 +400,71 @@ impl File {
             source: None,
             descriptor: FileDescriptorProto::decode(encoded.as_ref())?,
             encoded: Some(encoded),
+            warnings: Vec::new(),
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
+        })
+    }
+
+    /// Parses the original source of this file, and replaces the [`SourceCodeInfo`] of the descriptor with the
+    /// result.
+    ///
+    /// This is useful for files created by [`decode_file_descriptor_proto()`](File::decode_file_descriptor_proto)
+    /// which were compiled without source info, so that errors found when compiling them can include spans in the
+    /// source. Any other fields of the descriptor, including extension options, are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source is not a valid protobuf source file.
+    pub fn merge_source_info(self, source: &str) -> Result<File, ParseError> {
+        let (parsed, warnings) = protox_parse::parse_with_warnings(self.name(), source)?;
+        let source_code_info = parsed.source_code_info.unwrap_or_default();
+
+        let encoded = self
+            .encoded
+            .map(|encoded| replace_source_code_info(&encoded, &source_code_info));
+
+        Ok(File {
+            path: self.path,
+            source: Some(source.to_owned()),
+            descriptor: FileDescriptorProto {
+                source_code_info: Some(source_code_info),
+                ..self.descriptor
+            },
+            encoded,
+            warnings,
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
+        })
+    }
+
+    /// Replaces the source of this file, returning a new [`File`] with the same name and path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source is not a valid protobuf source file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::file::File;
+    /// let file = File::from_source("foo.proto", "message Foo {}").unwrap();
+    /// let file = file.with_source("message Bar {}".to_owned()).unwrap();
+    /// assert_eq!(file.name(), "foo.proto");
+    /// assert_eq!(file.source(), Some("message Bar {}"));
+    /// assert_eq!(file.file_descriptor_proto().message_type[0].name(), "Bar");
+    /// ```
+    pub fn with_source(self, source: String) -> Result<Self, Error> {
+        let (descriptor, warnings) = protox_parse::parse_with_warnings(self.name(), &source)?;
+
+        Ok(File {
+            path: self.path,
+            source: Some(source),
+            descriptor,
+            encoded: None,
+            warnings,
+            #[cfg(feature = "source-hash")]
+            source_hash: OnceLock::new(),
         })
     }
 
@@ -244,12 +483,99 @@ impl File {
         self.source.as_deref()
     }
 
+    /// Returns the SHA-256 hash of the source, if available.
+    ///
+    /// Unlike [`fingerprint()`](protox_parse::fingerprint()), which hashes the parsed descriptor, this hashes the raw
+    /// source bytes, so any edit to the file, including to whitespace or comments, changes the hash. It is computed
+    /// the first time it is requested.
+    ///
+    /// This method requires the `source-hash` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::file::File;
+    /// use sha2::{Digest, Sha256};
+    ///
+    /// let file = File::from_source("foo.proto", "message Foo {}").unwrap();
+    /// assert_eq!(file.source_hash(), Some(Sha256::digest(b"message Foo {}").into()));
+    ///
+    /// let reformatted = File::from_source("foo.proto", "message Foo { }").unwrap();
+    /// assert_ne!(file.source_hash(), reformatted.source_hash());
+    /// ```
+    #[cfg(feature = "source-hash")]
+    pub fn source_hash(&self) -> Option<[u8; 32]> {
+        let source = self.source.as_deref()?;
+        Some(
+            *self
+                .source_hash
+                .get_or_init(|| Sha256::digest(source.as_bytes()).into()),
+        )
+    }
+
     /// Returns the parsed value of the source file.
     ///
     /// This is typically equivalent to calling [`parse()`](protox_parse::parse()) on the string returned by [`source()`](File::source).
     pub fn file_descriptor_proto(&self) -> &FileDescriptorProto {
         &self.descriptor
     }
+
+    /// Returns any warnings found while parsing the source file.
+    ///
+    /// These are reported as errors by [`Compiler::with_warning_as_error`](crate::Compiler::with_warning_as_error).
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns a rough estimate of how expensive this file is to compile.
+    ///
+    /// This is the total number of messages, fields, extensions, enum values and service methods defined in the file,
+    /// including nested definitions. It is computed from [`file_descriptor_proto()`](File::file_descriptor_proto), so
+    /// the source is not parsed again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protox::file::File;
+    /// let file = File::from_source("foo.proto", "
+    ///     message Foo {
+    ///         optional int32 a = 1;
+    ///         message Bar { optional int32 b = 1; }
+    ///     }
+    ///     enum Baz { ZERO = 0; ONE = 1; }
+    ///     service Qux { rpc Method(Foo) returns (Foo); }
+    /// ").unwrap();
+    /// assert_eq!(file.estimated_complexity(), 7);
+    /// ```
+    pub fn estimated_complexity(&self) -> u64 {
+        fn enum_complexity(enum_: &EnumDescriptorProto) -> u64 {
+            enum_.value.len() as u64
+        }
+
+        fn message_complexity(message: &DescriptorProto) -> u64 {
+            1 + message.field.len() as u64
+                + message.extension.len() as u64
+                + message
+                    .nested_type
+                    .iter()
+                    .map(message_complexity)
+                    .sum::<u64>()
+                + message.enum_type.iter().map(enum_complexity).sum::<u64>()
+        }
+
+        let file = &self.descriptor;
+        file.message_type
+            .iter()
+            .map(message_complexity)
+            .sum::<u64>()
+            + file.enum_type.iter().map(enum_complexity).sum::<u64>()
+            + file.extension.len() as u64
+            + file
+                .service
+                .iter()
+                .map(|service| service.method.len() as u64)
+                .sum::<u64>()
+    }
 }
 
 impl FileMetadata {
@@ -270,6 +596,25 @@ impl FileMetadata {
     }
 }
 
+fn replace_source_code_info(encoded: &[u8], source_code_info: &SourceCodeInfo) -> Bytes {
+    const SOURCE_CODE_INFO_TAG: u32 = 9;
+
+    let mut result = Vec::with_capacity(encoded.len());
+    let mut buf = encoded;
+    while buf.has_remaining() {
+        let field = buf;
+        let (tag, wire_type) = decode_key(&mut buf).expect("invalid file descriptor");
+        skip_field(wire_type, tag, &mut buf, DecodeContext::default())
+            .expect("invalid file descriptor");
+        if tag != SOURCE_CODE_INFO_TAG {
+            result.extend_from_slice(&field[..field.len() - buf.len()]);
+        }
+    }
+
+    prost::encoding::message::encode(SOURCE_CODE_INFO_TAG, source_code_info, &mut result);
+    result.into()
+}
+
 impl From<FileDescriptorProto> for File {
     fn from(file: FileDescriptorProto) -> Self {
         File::from_file_descriptor_proto(file)
@@ -281,3 +626,17 @@ impl From<File> for FileDescriptorProto {
         file.descriptor
     }
 }
+
+fn map_io_err(name: &str, path: &Path, err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::NotFound => Error::file_not_found_in(name, vec![path.to_owned()]),
+        io::ErrorKind::InvalidData => Error::from_kind(ErrorKind::FileInvalidUtf8 {
+            name: name.to_owned(),
+        }),
+        _ => Error::from_kind(ErrorKind::OpenFile {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            err,
+        }),
+    }
+}
@@ -1,14 +1,18 @@
+#[cfg(feature = "source-hash")]
+use std::sync::OnceLock;
 use std::{
     io::{self, Seek, Write},
+    iter::once,
     path::{Path, PathBuf},
 };
 
 use prost_types::{source_code_info::Location, FileDescriptorProto, SourceCodeInfo};
 
-use crate::{file::FileResolver, Error};
+use crate::{error::ErrorKind, file::FileResolver, Error};
 
 use super::{
-    ChainFileResolver, DescriptorSetFileResolver, File, GoogleFileResolver, IncludeFileResolver,
+    ChainFileResolver, DescriptorSetFileResolver, File, FileEncoding, GoogleFileResolver,
+    IncludeFileResolver,
 };
 
 struct EmptyFileResolver;
@@ -55,6 +59,9 @@ fn chain_file_resolver() {
         source: Some(source.to_owned()),
         descriptor: protox_parse::parse("bar.proto", source).unwrap(),
         encoded: None,
+        warnings: Vec::new(),
+        #[cfg(feature = "source-hash")]
+        source_hash: OnceLock::new(),
     }));
 
     assert_eq!(resolver.resolve_path("./notfound.proto".as_ref()), None);
@@ -279,6 +286,275 @@ fn include_resolver() {
     );
 }
 
+#[test]
+fn chain_file_resolver_searched_paths() {
+    use miette::Diagnostic;
+
+    let first = tempfile::TempDir::new().unwrap();
+    let second = tempfile::TempDir::new().unwrap();
+
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(IncludeFileResolver::new(first.path().to_owned()));
+    resolver.add(GoogleFileResolver::new());
+    resolver.add(IncludeFileResolver::new(second.path().to_owned()));
+
+    let err = resolver.open_file("foo.proto").unwrap_err();
+    assert!(err.is_file_not_found());
+    assert_eq!(
+        err.help().unwrap().to_string(),
+        format!(
+            "searched the following paths:\n  • {}\n  • {}",
+            first.path().join("foo.proto").display(),
+            second.path().join("foo.proto").display(),
+        )
+    );
+
+    let err = ChainFileResolver::new().open_file("foo.proto").unwrap_err();
+    assert!(err.is_file_not_found());
+    assert!(err.help().is_none());
+}
+
+#[test]
+fn include_resolver_extension_filter() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("foo.proto"), "").unwrap();
+    std::fs::write(dir.path().join("foo.txt"), "").unwrap();
+    std::fs::write(dir.path().join("foo"), "").unwrap();
+
+    let resolver = IncludeFileResolver::new(dir.path().to_owned());
+    assert!(resolver.open_file("foo.proto").is_ok());
+    assert!(resolver
+        .open_file("foo.txt")
+        .unwrap_err()
+        .is_file_not_found());
+    assert!(resolver.open_file("foo").unwrap_err().is_file_not_found());
+
+    let resolver = resolver.with_extension_filter(&["protodevel", "txt"]);
+    assert!(resolver.open_file("foo.txt").is_ok());
+    assert!(resolver
+        .open_file("foo.proto")
+        .unwrap_err()
+        .is_file_not_found());
+    assert!(resolver.open_file("foo").unwrap_err().is_file_not_found());
+    assert!(resolver
+        .open_file("notfound.protodevel")
+        .unwrap_err()
+        .is_file_not_found());
+}
+
+#[test]
+#[cfg(unix)]
+fn include_resolver_follow_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let external = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("foo.proto"), "").unwrap();
+    std::fs::write(external.path().join("bar.proto"), "").unwrap();
+    symlink(
+        external.path().join("bar.proto"),
+        dir.path().join("bar.proto"),
+    )
+    .unwrap();
+    symlink(external.path(), dir.path().join("dir")).unwrap();
+
+    let resolver = IncludeFileResolver::new(dir.path().to_owned());
+    assert!(resolver.open_file("foo.proto").is_ok());
+    assert!(resolver.open_file("bar.proto").is_ok());
+    assert!(resolver.open_file("dir/bar.proto").is_ok());
+
+    let resolver = resolver.with_follow_symlinks(false);
+    assert!(resolver.open_file("foo.proto").is_ok());
+    assert!(resolver
+        .open_file("bar.proto")
+        .unwrap_err()
+        .is_file_not_found());
+    assert!(resolver
+        .open_file("dir/bar.proto")
+        .unwrap_err()
+        .is_file_not_found());
+    assert!(resolver
+        .open_file("notfound.proto")
+        .unwrap_err()
+        .is_file_not_found());
+}
+
+#[test]
+#[cfg(unix)]
+fn include_resolver_symlink_replaced_after_open() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let external = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("dir")).unwrap();
+    std::fs::write(dir.path().join("dir/foo.proto"), "").unwrap();
+    std::fs::write(external.path().join("foo.proto"), "").unwrap();
+
+    let resolver = IncludeFileResolver::new(dir.path().to_owned()).with_follow_symlinks(false);
+    let file = std::fs::File::open(external.path().join("foo.proto")).unwrap();
+    assert!(!resolver.is_opened_without_symlink("dir/foo.proto", &file));
+
+    let file = std::fs::File::open(dir.path().join("dir/foo.proto")).unwrap();
+    assert!(resolver.is_opened_without_symlink("dir/foo.proto", &file));
+
+    // The directory is replaced by a symbolic link after the file is opened.
+    std::fs::rename(dir.path().join("dir"), dir.path().join("old")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("old"), dir.path().join("dir")).unwrap();
+    assert!(!resolver.is_opened_without_symlink("dir/foo.proto", &file));
+}
+
+#[test]
+#[cfg(unix)]
+fn read_source_file_limit_unknown_size() {
+    // The file system reports a size of zero for devices, so the limit must be checked while reading.
+    let path = Path::new("/dev/zero");
+    let file = File::open_source_file("zero.proto", path).unwrap();
+    let err = File::read_source_file("zero.proto", path, file, FileEncoding::Utf8, 20).unwrap_err();
+    match err.kind() {
+        ErrorKind::FileTooLarge { name, size, limit } => {
+            assert_eq!(name, "zero.proto");
+            assert_eq!(*size, 21);
+            assert_eq!(*limit, 20);
+        }
+        kind => panic!("unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn include_resolver_list_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    std::fs::write(dir.path().join("root.proto"), "").unwrap();
+    std::fs::write(dir.path().join("a/b/nested.proto"), "").unwrap();
+    std::fs::write(dir.path().join("a/notes.txt"), "").unwrap();
+    std::fs::create_dir(dir.path().join("dir.proto")).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path().join("a"), dir.path().join("a/b/loop")).unwrap();
+
+    // Directories which were already visited through a symlink are skipped.
+    let resolver = IncludeFileResolver::new(dir.path().to_owned());
+    assert_eq!(
+        resolver.list_files().unwrap(),
+        ["a/b/nested.proto", "root.proto"]
+    );
+
+    let resolver = resolver.with_follow_symlinks(false);
+    assert_eq!(
+        resolver.list_files().unwrap(),
+        ["a/b/nested.proto", "root.proto"]
+    );
+
+    let resolver = resolver.with_extension_filter(&["txt"]);
+    assert_eq!(resolver.list_files().unwrap(), ["a/notes.txt"]);
+
+    let resolver = IncludeFileResolver::new(dir.path().join("notfound"));
+    assert!(resolver.list_files().unwrap().is_empty());
+
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(IncludeFileResolver::new(dir.path().to_owned()).with_follow_symlinks(false));
+    resolver.add(IncludeFileResolver::new(dir.path().join("a")));
+    resolver.add(GoogleFileResolver::new());
+    assert_eq!(
+        resolver.list_files().unwrap(),
+        ["a/b/nested.proto", "b/nested.proto", "root.proto"]
+    );
+}
+
+#[test]
+fn include_resolver_encoding() {
+    let source = "// caf\u{e9} \u{1f600}\nmessage Foo {}";
+    let utf16le: Vec<u8> = once(0xfeff)
+        .chain(source.encode_utf16())
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    let utf16be: Vec<u8> = source.encode_utf16().flat_map(u16::to_be_bytes).collect();
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("utf16le.proto"), utf16le).unwrap();
+    std::fs::write(dir.path().join("utf16be.proto"), utf16be).unwrap();
+    std::fs::write(
+        dir.path().join("latin1.proto"),
+        b"// caf\xe9\nmessage Foo {}",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("odd.proto"), b"\0\0\0").unwrap();
+
+    let resolver =
+        |encoding| IncludeFileResolver::new(dir.path().to_owned()).with_encoding(encoding);
+
+    let file = resolver(FileEncoding::Utf16Le)
+        .open_file("utf16le.proto")
+        .unwrap();
+    assert_eq!(file.source(), Some(source));
+    assert_eq!(file.file_descriptor_proto().message_type[0].name(), "Foo");
+    let file = resolver(FileEncoding::Utf16Be)
+        .open_file("utf16be.proto")
+        .unwrap();
+    assert_eq!(file.source(), Some(source));
+    let file = resolver(FileEncoding::Latin1)
+        .open_file("latin1.proto")
+        .unwrap();
+    assert_eq!(file.source(), Some("// caf\u{e9}\nmessage Foo {}"));
+
+    let err = resolver(FileEncoding::Utf16Be)
+        .open_file("odd.proto")
+        .unwrap_err();
+    assert!(err.is_parse());
+    assert_eq!(err.to_string(), "file 'odd.proto' is not valid UTF-16BE");
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::FileInvalidEncoding {
+            encoding: FileEncoding::Utf16Be,
+            ..
+        }
+    ));
+    assert!(resolver(FileEncoding::Utf8)
+        .open_file("latin1.proto")
+        .unwrap_err()
+        .is_parse());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_resolver() {
+    use super::ZipFileResolver;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("protos.zip");
+
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    let options = zip::write::FileOptions::default();
+    writer.add_directory("foo/", options).unwrap();
+    writer.start_file("foo/bar.proto", options).unwrap();
+    writer.write_all(b"message Bar {}").unwrap();
+    writer.start_file("invalid.proto", options).unwrap();
+    writer.write_all(&[255]).unwrap();
+    writer.finish().unwrap();
+
+    let resolver = ZipFileResolver::new(&path).unwrap();
+    assert_eq!(
+        resolver.resolve_path(Path::new("foo/bar.proto")).as_deref(),
+        Some("foo/bar.proto")
+    );
+    assert_eq!(resolver.resolve_path(Path::new("foo/notfound.proto")), None);
+    assert_eq!(resolver.resolve_path(Path::new("../foo/bar.proto")), None);
+
+    let file = resolver.open_file("foo/bar.proto").unwrap();
+    assert_eq!(file.name(), "foo/bar.proto");
+    assert_eq!(file.path(), None);
+    assert_eq!(file.source(), Some("message Bar {}"));
+
+    assert!(resolver.open_file("foo").unwrap_err().is_file_not_found());
+    assert!(resolver
+        .open_file("notfound.proto")
+        .unwrap_err()
+        .is_file_not_found());
+    assert_eq!(
+        resolver.open_file("invalid.proto").unwrap_err().to_string(),
+        "file 'invalid.proto' is not valid utf-8"
+    );
+
+    assert!(ZipFileResolver::new(&dir.path().join("notfound.zip")).is_err());
+}
+
 #[test]
 fn file_open() {
     let mut tempfile = tempfile::NamedTempFile::new().unwrap();
@@ -345,6 +621,49 @@ fn file_from_source() {
     );
 }
 
+#[test]
+fn file_estimated_complexity() {
+    assert_eq!(
+        File::from_source("empty.proto", "syntax = 'proto3';")
+            .unwrap()
+            .estimated_complexity(),
+        0
+    );
+
+    let file = File::from_source(
+        "foo.proto",
+        "
+        message Foo {
+            extensions 10 to 20;
+            map<int32, string> a = 1;
+            enum Bar { ZERO = 0; }
+            extend Foo { optional int32 b = 10; }
+        }
+        extend Foo { optional int32 c = 11; }
+        ",
+    )
+    .unwrap();
+    // Map fields also define an entry message with two fields.
+    assert_eq!(file.estimated_complexity(), 8);
+}
+
+#[test]
+#[cfg(feature = "source-hash")]
+fn file_source_hash() {
+    use sha2::{Digest, Sha256};
+
+    let file = File::from_source("foo.proto", "message Foo {}").unwrap();
+    let hash = file.source_hash().unwrap();
+    assert_eq!(hash, <[u8; 32]>::from(Sha256::digest(b"message Foo {}")));
+    assert_eq!(file.source_hash(), Some(hash));
+
+    let edited = file.with_source("message Foo {}\n".to_owned()).unwrap();
+    assert_ne!(edited.source_hash(), Some(hash));
+
+    let file = File::from_file_descriptor_proto(edited.file_descriptor_proto().clone());
+    assert_eq!(file.source_hash(), None);
+}
+
 #[test]
 fn file_from_file_descriptor_proto() {
     let file = File::from(FileDescriptorProto {
@@ -404,3 +723,56 @@ fn file_decode_file_descriptor_proto_err() {
     let invalid = b"invalid";
     assert!(File::decode_file_descriptor_proto(invalid.as_ref()).is_err());
 }
+
+#[test]
+fn file_merge_source_info() {
+    use prost::Message;
+
+    let source = "syntax = 'proto3';\n\nmessage Foo {}\n";
+    let descriptor = FileDescriptorProto {
+        source_code_info: Some(SourceCodeInfo {
+            location: vec![Location {
+                path: vec![],
+                span: vec![0, 0, 1],
+                ..Default::default()
+            }],
+        }),
+        ..protox_parse::parse("foo.proto", source).unwrap()
+    };
+
+    let file = File::decode_file_descriptor_proto(descriptor.encode_to_vec().as_slice())
+        .unwrap()
+        .merge_source_info(source)
+        .unwrap();
+
+    let expected = protox_parse::parse("foo.proto", source).unwrap();
+    assert_eq!(file.source(), Some(source));
+    assert_eq!(file.file_descriptor_proto(), &expected);
+    assert_eq!(
+        FileDescriptorProto::decode(file.encoded.unwrap()).unwrap(),
+        expected
+    );
+
+    assert!(File::from_file_descriptor_proto(expected)
+        .merge_source_info("message {")
+        .is_err());
+}
+
+#[test]
+fn file_with_source() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("foo.proto");
+    std::fs::write(&path, "message Foo {}").unwrap();
+
+    let file = File::open("foo.proto", &path).unwrap();
+    let file = file.with_source("message Bar {}".to_owned()).unwrap();
+    assert_eq!(file.name(), "foo.proto");
+    assert_eq!(file.path(), Some(path.as_path()));
+    assert_eq!(file.source(), Some("message Bar {}"));
+    assert_eq!(
+        file.file_descriptor_proto(),
+        &protox_parse::parse("foo.proto", "message Bar {}").unwrap()
+    );
+
+    assert!(file.with_source("message {".to_owned()).is_err());
+}
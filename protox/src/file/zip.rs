@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use ::zip::ZipArchive;
+
+use super::{path_to_file_name, File, FileResolver, MAX_FILE_LEN};
+use crate::error::{Error, ErrorKind};
+
+/// An implementation of [`FileResolver`] which opens files from a ZIP archive.
+///
+/// The archive is read into memory when the resolver is created. Files are named by their path
+/// within the archive, for example `foo/bar.proto`.
+#[derive(Debug)]
+pub struct ZipFileResolver {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl ZipFileResolver {
+    /// Creates a new [`ZipFileResolver`] by reading the ZIP archive at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or is not a valid ZIP archive.
+    pub fn new(path: &Path) -> Result<Self, io::Error> {
+        let mut archive = ZipArchive::new(fs::File::open(path)?)?;
+
+        let mut files = HashMap::with_capacity(archive.len());
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            if entry.is_dir() {
+                continue;
+            }
+
+            let mut contents = Vec::with_capacity(entry.size().min(MAX_FILE_LEN + 1) as usize);
+            (&mut entry)
+                .take(MAX_FILE_LEN + 1)
+                .read_to_end(&mut contents)?;
+            files.insert(entry.name().to_owned(), contents);
+        }
+
+        Ok(ZipFileResolver { files })
+    }
+}
+
+impl FileResolver for ZipFileResolver {
+    /// Converts a relative path to a file name, if it names a file in the archive.
+    fn resolve_path(&self, path: &Path) -> Option<String> {
+        let name = path_to_file_name(path)?;
+        if self.files.contains_key(&name) {
+            Some(name)
+        } else {
+            None
+        }
+    }
+
+    fn open_file(&self, name: &str) -> Result<File, Error> {
+        let contents = self
+            .files
+            .get(name)
+            .ok_or_else(|| Error::file_not_found(name))?;
+
+        if contents.len() as u64 > MAX_FILE_LEN {
+            return Err(Error::from_kind(ErrorKind::FileTooLarge {
+                name: name.to_owned(),
+                size: contents.len() as u64,
+                limit: MAX_FILE_LEN,
+            }));
+        }
+
+        let source = std::str::from_utf8(contents).map_err(|_| {
+            Error::from_kind(ErrorKind::FileInvalidUtf8 {
+                name: name.to_owned(),
+            })
+        })?;
+
+        File::from_source(name, source)
+    }
+}
@@ -0,0 +1,108 @@
+//! A compact set of integers, such as the field numbers in the extension or reserved ranges of a message.
+//!
+//! # Examples
+//!
+//! ```
+//! # use protox::inversion_list::InversionList;
+//! let ranges = InversionList::new([1..=5, 10..=19]);
+//! assert!(ranges.contains(15));
+//! assert!(!ranges.contains(7));
+//! assert_eq!(ranges.closest_miss(7), Some(5));
+//! ```
+
+use std::ops::RangeInclusive;
+
+/// A set of integers, stored as a sorted list of the boundaries between ranges which are and are not in the set.
+///
+/// Membership queries take logarithmic time in the number of ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InversionList {
+    // Alternating inclusive starts and exclusive ends of each range. These are stored as `i64` so the end of a range
+    // containing `i32::MAX` can be represented.
+    boundaries: Vec<i64>,
+}
+
+impl InversionList {
+    /// Creates a new set containing every integer in `ranges`.
+    ///
+    /// The ranges may be given in any order, and may overlap. Empty ranges are ignored.
+    pub fn new(ranges: impl IntoIterator<Item = RangeInclusive<i32>>) -> Self {
+        let mut ranges: Vec<(i64, i64)> = ranges
+            .into_iter()
+            .filter(|range| !range.is_empty())
+            .map(|range| (i64::from(*range.start()), i64::from(*range.end()) + 1))
+            .collect();
+        ranges.sort_unstable();
+
+        let mut boundaries: Vec<i64> = Vec::with_capacity(ranges.len() * 2);
+        for (start, end) in ranges {
+            match boundaries.last_mut() {
+                // Merge ranges which overlap or are adjacent to the previous range.
+                Some(last) if *last >= start => *last = (*last).max(end),
+                _ => boundaries.extend([start, end]),
+            }
+        }
+        InversionList { boundaries }
+    }
+
+    /// Returns `true` if `n` is in any of the ranges of this set.
+    pub fn contains(&self, n: i32) -> bool {
+        self.index(n) % 2 == 1
+    }
+
+    /// If `n` is not in this set, returns the closest integer which is.
+    ///
+    /// If two integers are equally close, the smaller one is returned. Returns `None` if `n` is in the set, or the set
+    /// is empty.
+    pub fn closest_miss(&self, n: i32) -> Option<i32> {
+        let index = self.index(n);
+        if index % 2 == 1 {
+            return None;
+        }
+
+        let n = i64::from(n);
+        let below = index.checked_sub(1).map(|index| self.boundaries[index] - 1);
+        let above = self.boundaries.get(index).copied();
+        let closest = match (below, above) {
+            (Some(below), Some(above)) if above - n < n - below => above,
+            (Some(below), _) => below,
+            (None, above) => above?,
+        };
+        Some(closest as i32)
+    }
+
+    /// Gets the number of boundaries less than or equal to `n`, which is odd if `n` is in the set.
+    fn index(&self, n: i32) -> usize {
+        self.boundaries
+            .partition_point(|&boundary| boundary <= i64::from(n))
+    }
+}
+
+#[test]
+fn contains() {
+    #[allow(clippy::reversed_empty_ranges)]
+    let list = InversionList::new([10..=19, 1..=5, 4..=6, 7..=7, 30..=29, 100..=i32::MAX]);
+    assert_eq!(list.boundaries, [1, 8, 10, 20, 100, 1 << 31]);
+
+    assert!(!list.contains(i32::MIN));
+    assert!(!list.contains(0));
+    assert!(list.contains(1));
+    assert!(list.contains(7));
+    assert!(!list.contains(8));
+    assert!(list.contains(19));
+    assert!(!list.contains(20));
+    assert!(!list.contains(29));
+    assert!(list.contains(i32::MAX));
+}
+
+#[test]
+fn closest_miss() {
+    let list = InversionList::new([1..=5, 10..=19]);
+
+    assert_eq!(list.closest_miss(3), None);
+    assert_eq!(list.closest_miss(i32::MIN), Some(1));
+    assert_eq!(list.closest_miss(7), Some(5));
+    assert_eq!(list.closest_miss(8), Some(10));
+    assert_eq!(list.closest_miss(i32::MAX), Some(19));
+    assert_eq!(InversionList::new([]).closest_miss(1), None);
+}
@@ -0,0 +1,363 @@
+//! Conversion of protobuf message definitions to [JSON Schema](https://json-schema.org/).
+//!
+//! The generated schemas describe the [JSON mapping](https://protobuf.dev/programming-guides/proto3/#json) of
+//! protobuf messages, for use in documentation and validation of REST APIs.
+
+use std::collections::BTreeMap;
+
+use prost_reflect::{DescriptorPool, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor};
+use serde_json::{json, Map, Value};
+
+const DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Converts a message definition into a [draft-07](https://json-schema.org/specification-links#draft-7) JSON Schema.
+///
+/// The root message and any messages or enums it references are added to the `$defs` object of the schema, keyed by
+/// their fully-qualified name, and referenced using `$ref`. Well-known types such as `google.protobuf.Timestamp` are
+/// converted to schemas for their special JSON representations.
+///
+/// # Examples
+///
+/// ```
+/// # use protox::Compiler;
+/// # fn main() -> Result<(), protox::Error> {
+/// # let tempdir = tempfile::TempDir::new().unwrap();
+/// # std::env::set_current_dir(&tempdir).unwrap();
+/// std::fs::write("foo.proto", "
+///     syntax = 'proto3';
+///     package foo;
+///
+///     message Foo {
+///         repeated int32 bar = 1;
+///     }
+/// ").unwrap();
+///
+/// let pool = Compiler::new(["."])?.open_file("foo.proto")?.descriptor_pool();
+/// let schema = protox::jsonschema::convert(&pool, pool.get_message_by_name("foo.Foo").unwrap());
+///
+/// assert_eq!(schema["$ref"], "#/$defs/foo.Foo");
+/// assert_eq!(schema["$defs"]["foo.Foo"]["properties"]["bar"]["type"], "array");
+/// # Ok(())
+/// # }
+/// ```
+pub fn convert(pool: &DescriptorPool, message: MessageDescriptor) -> Value {
+    debug_assert_eq!(message.parent_pool(), pool);
+
+    let mut ctx = Context {
+        defs: BTreeMap::new(),
+    };
+    let root = ctx.message_ref(&message);
+
+    let mut schema = Map::new();
+    schema.insert("$schema".to_owned(), Value::from(DRAFT_07));
+    if let Value::Object(root) = root {
+        schema.extend(root);
+    }
+    schema.insert(
+        "$defs".to_owned(),
+        Value::Object(ctx.defs.into_iter().collect()),
+    );
+    Value::Object(schema)
+}
+
+struct Context {
+    defs: BTreeMap<String, Value>,
+}
+
+impl Context {
+    fn message_ref(&mut self, message: &MessageDescriptor) -> Value {
+        if let Some(schema) = well_known_type(message) {
+            return schema;
+        }
+
+        if !self.defs.contains_key(message.full_name()) {
+            // Insert a placeholder first to handle recursive messages.
+            self.defs
+                .insert(message.full_name().to_owned(), Value::Null);
+            let schema = self.message(message);
+            self.defs.insert(message.full_name().to_owned(), schema);
+        }
+
+        def_ref(message.full_name())
+    }
+
+    fn enum_ref(&mut self, enum_: &EnumDescriptor) -> Value {
+        if enum_.full_name() == "google.protobuf.NullValue" {
+            return json!({ "type": "null" });
+        }
+
+        if !self.defs.contains_key(enum_.full_name()) {
+            let mut values: Vec<Value> = enum_.values().map(|v| Value::from(v.name())).collect();
+            values.extend(enum_.values().map(|v| Value::from(v.number())));
+            self.defs.insert(
+                enum_.full_name().to_owned(),
+                json!({ "title": enum_.name(), "enum": values }),
+            );
+        }
+
+        def_ref(enum_.full_name())
+    }
+
+    fn message(&mut self, message: &MessageDescriptor) -> Value {
+        let mut properties = Map::new();
+        for field in message.fields() {
+            properties.insert(field.json_name().to_owned(), self.field(&field));
+        }
+
+        let mut schema = Map::new();
+        schema.insert("title".to_owned(), Value::from(message.name()));
+        schema.insert("type".to_owned(), Value::from("object"));
+        schema.insert("properties".to_owned(), Value::Object(properties));
+
+        let mut oneofs: Vec<Vec<Value>> = message
+            .oneofs()
+            .filter(|oneof| {
+                !oneof
+                    .fields()
+                    .all(|f| f.field_descriptor_proto().proto3_optional())
+            })
+            .map(|oneof| {
+                let variants: Vec<Value> = oneof
+                    .fields()
+                    .map(|f| json!({ "required": [f.json_name()] }))
+                    .collect();
+                // At most one field of a oneof may be set, so allow the case where none are present.
+                let mut alternatives = variants.clone();
+                alternatives.push(json!({ "not": { "anyOf": variants } }));
+                alternatives
+            })
+            .collect();
+        match oneofs.len() {
+            0 => (),
+            1 => {
+                schema.insert("oneOf".to_owned(), Value::Array(oneofs.pop().unwrap()));
+            }
+            _ => {
+                let all_of = oneofs
+                    .into_iter()
+                    .map(|alternatives| json!({ "oneOf": alternatives }))
+                    .collect();
+                schema.insert("allOf".to_owned(), Value::Array(all_of));
+            }
+        }
+
+        Value::Object(schema)
+    }
+
+    fn field(&mut self, field: &FieldDescriptor) -> Value {
+        if field.is_map() {
+            let value = match field.kind() {
+                Kind::Message(entry) => self.kind(&entry.map_entry_value_field().kind()),
+                _ => unreachable!("map fields must have a message type"),
+            };
+            json!({ "type": "object", "additionalProperties": value })
+        } else if field.is_list() {
+            json!({ "type": "array", "items": self.kind(&field.kind()) })
+        } else {
+            self.kind(&field.kind())
+        }
+    }
+
+    fn kind(&mut self, kind: &Kind) -> Value {
+        match kind {
+            Kind::Message(message) => self.message_ref(message),
+            Kind::Enum(enum_) => self.enum_ref(enum_),
+            _ => scalar(kind),
+        }
+    }
+}
+
+fn scalar(kind: &Kind) -> Value {
+    match kind {
+        Kind::Double | Kind::Float => json!({
+            "oneOf": [
+                { "type": "number" },
+                { "type": "string", "enum": ["NaN", "Infinity", "-Infinity"] },
+            ]
+        }),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => json!({
+            "type": "integer",
+            "minimum": i32::MIN,
+            "maximum": i32::MAX,
+        }),
+        Kind::Uint32 | Kind::Fixed32 => json!({
+            "type": "integer",
+            "minimum": u32::MIN,
+            "maximum": u32::MAX,
+        }),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => json!({
+            "type": ["integer", "string"],
+            "pattern": "^-?[0-9]+$",
+        }),
+        Kind::Uint64 | Kind::Fixed64 => json!({
+            "type": ["integer", "string"],
+            "minimum": 0,
+            "pattern": "^[0-9]+$",
+        }),
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::String => json!({ "type": "string" }),
+        Kind::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        Kind::Message(_) | Kind::Enum(_) => unreachable!(),
+    }
+}
+
+fn def_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", name) })
+}
+
+fn well_known_type(message: &MessageDescriptor) -> Option<Value> {
+    Some(match message.full_name() {
+        "google.protobuf.Any" => json!({
+            "type": "object",
+            "properties": { "@type": { "type": "string" } },
+            "required": ["@type"],
+        }),
+        "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" => json!({
+            "type": "string",
+            "pattern": "^-?[0-9]+(\\.[0-9]{1,9})?s$",
+        }),
+        "google.protobuf.FieldMask" => json!({ "type": "string" }),
+        "google.protobuf.Struct" => json!({ "type": "object" }),
+        "google.protobuf.ListValue" => json!({ "type": "array" }),
+        "google.protobuf.Value" => json!({}),
+        "google.protobuf.Empty" => json!({ "type": "object", "additionalProperties": false }),
+        "google.protobuf.DoubleValue" => scalar(&Kind::Double),
+        "google.protobuf.FloatValue" => scalar(&Kind::Float),
+        "google.protobuf.Int64Value" => scalar(&Kind::Int64),
+        "google.protobuf.UInt64Value" => scalar(&Kind::Uint64),
+        "google.protobuf.Int32Value" => scalar(&Kind::Int32),
+        "google.protobuf.UInt32Value" => scalar(&Kind::Uint32),
+        "google.protobuf.BoolValue" => scalar(&Kind::Bool),
+        "google.protobuf.StringValue" => scalar(&Kind::String),
+        "google.protobuf.BytesValue" => scalar(&Kind::Bytes),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+fn compile(source: &str) -> DescriptorPool {
+    use crate::{
+        file::{ChainFileResolver, DescriptorSetFileResolver, GoogleFileResolver},
+        Compiler,
+    };
+
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(DescriptorSetFileResolver::new(
+        prost_types::FileDescriptorSet {
+            file: vec![protox_parse::parse("root.proto", source).unwrap()],
+        },
+    ));
+    resolver.add(GoogleFileResolver::new());
+
+    Compiler::with_file_resolver(resolver)
+        .open_file("root.proto")
+        .unwrap()
+        .descriptor_pool()
+}
+
+#[test]
+fn convert_message() {
+    let pool = compile(
+        "
+        syntax = 'proto3';
+        package pkg;
+
+        import 'google/protobuf/timestamp.proto';
+
+        message Foo {
+            string name = 1;
+            repeated int64 ids = 2;
+            map<string, Bar> bars = 3;
+            Kind kind = 4;
+            google.protobuf.Timestamp created_at = 5;
+            optional bool flag = 6;
+            oneof choice {
+                uint32 number = 7;
+                bytes data = 8;
+            }
+
+            message Bar {
+                Foo parent = 1;
+            }
+        }
+
+        enum Kind {
+            UNKNOWN = 0;
+            OTHER = 1;
+        }
+    ",
+    );
+
+    let schema = convert(&pool, pool.get_message_by_name("pkg.Foo").unwrap());
+
+    assert_eq!(
+        schema,
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "$ref": "#/$defs/pkg.Foo",
+            "$defs": {
+                "pkg.Foo": {
+                    "title": "Foo",
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "ids": {
+                            "type": "array",
+                            "items": { "type": ["integer", "string"], "pattern": "^-?[0-9]+$" },
+                        },
+                        "bars": {
+                            "type": "object",
+                            "additionalProperties": { "$ref": "#/$defs/pkg.Foo.Bar" },
+                        },
+                        "kind": { "$ref": "#/$defs/pkg.Kind" },
+                        "createdAt": { "type": "string", "format": "date-time" },
+                        "flag": { "type": "boolean" },
+                        "number": { "type": "integer", "minimum": 0, "maximum": u32::MAX },
+                        "data": { "type": "string", "contentEncoding": "base64" },
+                    },
+                    "oneOf": [
+                        { "required": ["number"] },
+                        { "required": ["data"] },
+                        { "not": { "anyOf": [{ "required": ["number"] }, { "required": ["data"] }] } },
+                    ],
+                },
+                "pkg.Foo.Bar": {
+                    "title": "Bar",
+                    "type": "object",
+                    "properties": {
+                        "parent": { "$ref": "#/$defs/pkg.Foo" },
+                    },
+                },
+                "pkg.Kind": {
+                    "title": "Kind",
+                    "enum": ["UNKNOWN", "OTHER", 0, 1],
+                },
+            },
+        })
+    );
+}
+
+#[test]
+fn convert_multiple_oneofs() {
+    let pool = compile(
+        "
+        syntax = 'proto3';
+
+        message Foo {
+            oneof a {
+                string b = 1;
+            }
+            oneof c {
+                string d = 2;
+            }
+        }
+    ",
+    );
+
+    let schema = convert(&pool, pool.get_message_by_name("Foo").unwrap());
+    let all_of = schema["$defs"]["Foo"]["allOf"].as_array().unwrap();
+    assert_eq!(all_of.len(), 2);
+    assert_eq!(all_of[0]["oneOf"][0], json!({ "required": ["b"] }));
+    assert_eq!(all_of[1]["oneOf"][0], json!({ "required": ["d"] }));
+}
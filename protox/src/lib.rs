@@ -70,7 +70,13 @@
 #![deny(unsafe_code)]
 #![doc(html_root_url = "https://docs.rs/protox/0.7.1/")]
 
+pub mod diff;
 pub mod file;
+pub mod inversion_list;
+#[cfg(feature = "jsonschema")]
+pub mod jsonschema;
+pub mod print;
+pub mod tag;
 
 mod compile;
 mod error;
@@ -79,7 +85,9 @@ use std::path::Path;
 
 pub use {prost, prost_reflect};
 
-pub use self::compile::Compiler;
+#[cfg(feature = "timing")]
+pub use self::compile::{CompilationStats, FileDuration};
+pub use self::compile::{Compiler, ShadowPolicy, SharedDescriptorPool};
 pub use self::error::Error;
 
 /// Compiles a set of protobuf files using the given include paths.
@@ -1,14 +1,41 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
 
-use clap::Parser;
-use miette::Result;
-use protox::Compiler;
+use clap::{Parser, ValueEnum};
+use miette::{bail, miette, IntoDiagnostic, Result};
+use prost::Message;
+use prost_types::{
+    compiler::{CodeGeneratorRequest, CodeGeneratorResponse},
+    FileDescriptorSet,
+};
+use protox::{
+    file::{ChainFileResolver, DescriptorSetFileResolver, File, FileResolver},
+    Compiler,
+};
+use serde_json::json;
 
 #[derive(Debug, Parser)]
 pub struct Args {
     /// The source file(s) to compile
-    #[clap(value_name = "PROTO_FILES", required = true, value_parser)]
+    #[clap(
+        value_name = "PROTO_FILES",
+        required_unless_present = "input_descriptor_set",
+        value_parser
+    )]
     files: Vec<PathBuf>,
+    /// The path of an encoded file descriptor set to check, or '-' to read it from stdin.
+    ///
+    /// All files in the set are compiled as if they were passed as PROTO_FILES.
+    #[clap(short = 'i', long, value_name = "PATH", value_parser)]
+    input_descriptor_set: Option<PathBuf>,
     /// The directory in which to search for imports.
     #[clap(
         short = 'I',
@@ -19,6 +46,17 @@ pub struct Args {
         value_parser
     )]
     includes: Vec<PathBuf>,
+    /// The path of an encoded file descriptor set containing files which may be imported.
+    ///
+    /// Imports are searched for in each descriptor set, in the order given, before the include paths. Files found in
+    /// a descriptor set are not parsed again from source.
+    #[clap(
+        long,
+        visible_alias = "descriptor_set_in",
+        value_name = "PATH",
+        value_parser
+    )]
+    descriptor_set_in: Vec<PathBuf>,
     /// The output path to write a file descriptor set to.
     #[clap(
         short = 'o',
@@ -28,27 +66,673 @@ pub struct Args {
         value_parser
     )]
     output: Option<PathBuf>,
+    /// The path to write a Makefile dependency file to, listing the source files and descriptor sets read during
+    /// compilation.
+    #[clap(long, value_name = "PATH", value_parser)]
+    depfile: Option<PathBuf>,
+    /// The target of the rule in the dependency file. Defaults to the output path.
+    #[clap(long, value_name = "TARGET", requires = "depfile")]
+    depfile_target: Option<String>,
     /// If set, includes source code information in the output file descriptor set.
     #[clap(long, visible_alias = "include_source_info")]
     include_source_info: bool,
+    /// If set, removes source code information from the output file descriptor set.
+    ///
+    /// This is the default, but may be given explicitly when converting an input descriptor set.
+    #[clap(long, conflicts_with_all = ["include_source_info", "source_dir"])]
+    strip_source_info: bool,
+    /// The directory containing the original source files of the input descriptor set.
+    ///
+    /// Source code information for each file in the set is regenerated from its source in this directory, if found,
+    /// and included in the output file descriptor set.
+    #[clap(
+        short = 's',
+        long,
+        value_name = "PATH",
+        requires = "input_descriptor_set",
+        value_parser
+    )]
+    source_dir: Option<PathBuf>,
     /// If set, all dependencies of the input files are output, so that the file descriptor set is self-contained.
     #[clap(long, visible_alias = "include_imports")]
     include_imports: bool,
+    /// If set, an error is returned for any import in the input files which is not used.
+    #[clap(long)]
+    error_on_unused_import: bool,
+    /// If set, warnings in the input files, such as for deeply nested messages, are treated as errors.
+    #[clap(long)]
+    strict: bool,
+    /// The maximum length of a chain of non-repeated message fields in the input files.
+    ///
+    /// Messages with longer chains, or which contain themselves through required fields, are reported as warnings.
+    #[clap(long, value_name = "DEPTH")]
+    max_message_nesting_depth: Option<u32>,
+    /// If set, warns about extension ranges in the input files which no extension uses.
+    ///
+    /// Files which declare extension ranges for other files to use, such as `descriptor.proto`, will have warnings.
+    /// With `--strict`, these warnings are also treated as errors.
+    #[clap(long)]
+    warn_unused_extension_ranges: bool,
+    /// If set, only checks the input files for errors, without writing any output.
+    ///
+    /// Errors are printed to stderr, and the exit code is non-zero if any file has errors.
+    #[clap(long, conflicts_with_all = ["output", "print_build_metadata", "depfile"])]
+    check: bool,
+    /// If set, prints the directories searched for imports to stdout, one per line, before compiling any files.
+    #[clap(long)]
+    print_includes: bool,
+    /// If set, prints metadata describing the input files for the given build system to stdout.
+    #[clap(long, value_name = "FORMAT", value_enum)]
+    print_build_metadata: Option<BuildMetadataFormat>,
+    /// A code generator plugin, given as 'protoc-gen-NAME=PATH', or a path to an executable named 'protoc-gen-NAME'.
+    ///
+    /// Plugins are run using '--NAME_out=[PARAMETERS:]DIR', with additional parameters passed by '--NAME_opt=PARAMETERS'.
+    /// If no path is given for a plugin, the executable 'protoc-gen-NAME' is searched for in PATH.
+    #[clap(long = "plugin", value_name = "PLUGIN", value_parser)]
+    plugins: Vec<String>,
+}
+
+/// A code generator plugin to run, given by the `--NAME_out` and `--NAME_opt` flags.
+#[derive(Debug)]
+struct PluginOutput {
+    name: String,
+    dir: PathBuf,
+    parameters: Vec<String>,
+}
+
+/// Wraps the resolver for an input descriptor set, regenerating the source code info of each file from its source
+/// in the `--source-dir` directory.
+struct SourceInfoFileResolver {
+    inner: DescriptorSetFileResolver,
+    dir: PathBuf,
+}
+
+impl FileResolver for SourceInfoFileResolver {
+    fn open_file(&self, name: &str) -> Result<File, protox::Error> {
+        let file = self.inner.open_file(name)?;
+        let path = self.dir.join(name);
+        if !path.exists() {
+            return Ok(file);
+        }
+
+        let source = fs::read_to_string(path)?;
+        Ok(file.merge_source_info(&source)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BuildMetadataFormat {
+    /// Prints a JSON object describing a `proto_library` rule for each input file.
+    Bazel,
 }
 
 pub fn main() -> Result<()> {
     miette::set_panic_hook();
 
-    let args = Args::parse();
-    let mut compiler = Compiler::new(args.includes)?;
+    let (args, plugin_outputs) = parse_args(env::args_os())?;
+    run(args, plugin_outputs)
+}
+
+fn run(args: Args, plugin_outputs: Vec<PluginOutput>) -> Result<()> {
+    let descriptor_sets = args
+        .descriptor_set_in
+        .iter()
+        .map(|path| {
+            let bytes = fs::read(path).into_diagnostic()?;
+            DescriptorSetFileResolver::decode(bytes.as_slice()).into_diagnostic()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (input_set, input_files) = match &args.input_descriptor_set {
+        Some(path) => {
+            let bytes = read_input(path)?;
+            let names: Vec<String> = FileDescriptorSet::decode(bytes.as_slice())
+                .into_diagnostic()?
+                .file
+                .into_iter()
+                .map(|file| file.name().to_owned())
+                .collect();
+
+            let mut resolver = ChainFileResolver::new();
+            let set = DescriptorSetFileResolver::decode(bytes.as_slice()).into_diagnostic()?;
+            match &args.source_dir {
+                Some(dir) => resolver.add(SourceInfoFileResolver {
+                    inner: set,
+                    dir: dir.clone(),
+                }),
+                None => resolver.add(set),
+            }
+            (Some(resolver), names)
+        }
+        None => (None, Vec::new()),
+    };
+    let mut compiler = Compiler::new(&args.includes)?;
     compiler.include_imports(args.include_imports);
-    compiler.include_source_info(args.include_source_info);
+    compiler.include_source_info(args.include_source_info || args.source_dir.is_some());
+    compiler.with_error_on_unused_import(args.error_on_unused_import);
+    compiler.with_warning_as_error(args.strict);
+    if let Some(depth) = args.max_message_nesting_depth {
+        compiler.with_max_message_nesting_depth(depth);
+    }
+    if args.print_includes {
+        for include in compiler.include_paths() {
+            match include {
+                Some(path) => println!("{}", path.display()),
+                None => println!("<built-in google/protobuf files>"),
+            }
+        }
+    }
+    for set in descriptor_sets.into_iter().rev() {
+        compiler.prepend_resolver(set);
+    }
+    if let Some(input_set) = input_set {
+        compiler.prepend_resolver(input_set);
+    }
+    for file in input_files {
+        compiler.open_file(file)?;
+    }
     for file in args.files {
         compiler.open_file(file)?;
     }
+    let unused_extension_ranges = if args.warn_unused_extension_ranges {
+        compiler.unused_extension_ranges()
+    } else {
+        Vec::new()
+    };
+    if args.strict {
+        if let Some(err) = unused_extension_ranges.into_iter().next() {
+            return Err(err.into());
+        }
+    } else {
+        for warning in compiler.warnings().iter().chain(&unused_extension_ranges) {
+            eprintln!("warning: {:?}", warning);
+        }
+    }
+    if let Some(depfile) = &args.depfile {
+        let target = match (&args.depfile_target, &args.output) {
+            (Some(target), _) => target.clone(),
+            (None, Some(output)) => output.to_string_lossy().into_owned(),
+            (None, None) => bail!("'--depfile-target' is required if '--output' is not set"),
+        };
+        // Descriptor sets are read by the CLI rather than a file resolver, so they are listed separately.
+        let mut prerequisites: Vec<&Path> = args
+            .descriptor_set_in
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+        if let Some(path) = &args.input_descriptor_set {
+            if path.as_os_str() != "-" {
+                prerequisites.push(path);
+            }
+        }
+        let mut contents = Vec::new();
+        compiler
+            .write_depfile_with_prerequisites(&target, &prerequisites, &mut contents)
+            .into_diagnostic()?;
+        fs::write(depfile, contents).into_diagnostic()?;
+    }
+    if args.check {
+        return Ok(());
+    }
     if let Some(output) = args.output {
         fs::write(output, compiler.encode_file_descriptor_set())
             .map_err(|err| miette::miette!(err))?;
     }
+    match args.print_build_metadata {
+        Some(BuildMetadataFormat::Bazel) => print_bazel_metadata(&compiler),
+        None => (),
+    }
+    if !plugin_outputs.is_empty() {
+        let plugins = parse_plugins(&args.plugins);
+        for output in &plugin_outputs {
+            run_plugin(&compiler, output, &plugins)?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses the command line arguments, separating out the `--NAME_out` and `--NAME_opt` flags for plugins since
+/// their names are not known in advance.
+fn parse_args(args: impl IntoIterator<Item = OsString>) -> Result<(Args, Vec<PluginOutput>)> {
+    let mut clap_args = Vec::new();
+    let mut outputs: Vec<PluginOutput> = Vec::new();
+    let mut options = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some((name, is_output, value)) = arg.to_str().and_then(parse_plugin_flag) else {
+            clap_args.push(arg);
+            continue;
+        };
+        let value = match value {
+            Some(value) => value.to_owned(),
+            None => match args.next().map(OsString::into_string) {
+                Some(Ok(value)) => value,
+                _ => bail!(
+                    "a value is required for '--{}_{}'",
+                    name,
+                    if is_output { "out" } else { "opt" }
+                ),
+            },
+        };
+
+        if is_output {
+            let (parameter, dir) = split_output_parameter(&value);
+            outputs.push(PluginOutput {
+                name: name.to_owned(),
+                dir: PathBuf::from(dir),
+                parameters: parameter.map(ToOwned::to_owned).into_iter().collect(),
+            });
+        } else {
+            options.push((name.to_owned(), value));
+        }
+    }
+
+    for (name, value) in options {
+        match outputs.iter_mut().find(|output| output.name == name) {
+            Some(output) => output.parameters.push(value),
+            None => bail!("'--{}_opt' was given without '--{}_out'", name, name),
+        }
+    }
+
+    let args = Args::parse_from(clap_args);
+    if args.check && !outputs.is_empty() {
+        bail!("'--check' cannot be used with '--{}_out'", outputs[0].name);
+    }
+    Ok((args, outputs))
+}
+
+/// Parses a flag of the form `--NAME_out[=VALUE]` or `--NAME_opt[=VALUE]`.
+fn parse_plugin_flag(arg: &str) -> Option<(&str, bool, Option<&str>)> {
+    let flag = arg.strip_prefix("--")?;
+    let (flag, value) = match flag.split_once('=') {
+        Some((flag, value)) => (flag, Some(value)),
+        None => (flag, None),
+    };
+
+    if let Some(name) = flag.strip_suffix("_out") {
+        // Handled by the '--descriptor_set_out' alias.
+        if name.is_empty() || name == "descriptor_set" {
+            return None;
+        }
+        Some((name, true, value))
+    } else if let Some(name) = flag.strip_suffix("_opt") {
+        if name.is_empty() {
+            return None;
+        }
+        Some((name, false, value))
+    } else {
+        None
+    }
+}
+
+/// Splits the value of a `--NAME_out` flag into the optional parameter and the output directory.
+fn split_output_parameter(value: &str) -> (Option<&str>, &str) {
+    match value.split_once(':') {
+        // Don't mistake a Windows drive letter for a parameter.
+        Some((parameter, _)) if cfg!(windows) && parameter.len() == 1 => (None, value),
+        Some((parameter, dir)) => (Some(parameter), dir),
+        None => (None, value),
+    }
+}
+
+/// Gets the path of each plugin given by the `--plugin` flag, keyed by name.
+fn parse_plugins(plugins: &[String]) -> HashMap<String, PathBuf> {
+    plugins
+        .iter()
+        .map(|plugin| {
+            let (name, path) = match plugin.split_once('=') {
+                Some((name, path)) => (name.to_owned(), PathBuf::from(path)),
+                None => {
+                    let path = PathBuf::from(plugin);
+                    let name = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    (name, path)
+                }
+            };
+            let name = name.strip_prefix("protoc-gen-").unwrap_or(&name).to_owned();
+            (name, path)
+        })
+        .collect()
+}
+
+/// Runs a code generator plugin using the protocol defined in `google/protobuf/compiler/plugin.proto`, and writes
+/// the generated files to the output directory.
+fn run_plugin(
+    compiler: &Compiler,
+    output: &PluginOutput,
+    plugins: &HashMap<String, PathBuf>,
+) -> Result<()> {
+    const PROTO_FILE_TAG: u32 = 15;
+
+    let program = match plugins.get(&output.name) {
+        Some(path) => path.clone(),
+        None => PathBuf::from(format!("protoc-gen-{}", output.name)),
+    };
+
+    let request = CodeGeneratorRequest {
+        file_to_generate: compiler.roots().map(|f| f.name().to_owned()).collect(),
+        parameter: (!output.parameters.is_empty()).then(|| output.parameters.join(",")),
+        ..Default::default()
+    };
+    // Encode files directly from the pool so that extension options are preserved.
+    let mut request_bytes = request.encode_to_vec();
+    for file in compiler.descriptor_pool().files() {
+        prost::encoding::bytes::encode(PROTO_FILE_TAG, &file.encode_to_vec(), &mut request_bytes);
+    }
+
+    let mut child = Command::new(&program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| miette!("failed to run plugin '{}': {}", program.display(), err))?;
+    // Write the request on a separate thread while reading the response, so neither process blocks on a full pipe,
+    // and a plugin which exits early is reported by its exit status rather than a broken pipe.
+    let mut stdin = child.stdin.take().expect("stdin not piped");
+    let writer = thread::spawn(move || stdin.write_all(&request_bytes));
+    let result = child.wait_with_output().into_diagnostic()?;
+    if !result.status.success() {
+        bail!("plugin '{}' failed: {}", program.display(), result.status);
+    }
+    match writer.join().expect("writer thread panicked") {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err).into_diagnostic(),
+        _ => (),
+    }
+
+    let response = CodeGeneratorResponse::decode(result.stdout.as_slice()).into_diagnostic()?;
+    if let Some(error) = response.error {
+        bail!("--{}_out: {}", output.name, error);
+    }
+
+    let mut files: Vec<(String, String)> = Vec::new();
+    for file in response.file {
+        if file.insertion_point.is_some_and(|point| !point.is_empty()) {
+            bail!("--{}_out: insertion points are not supported", output.name);
+        }
+        match (file.name, files.last_mut()) {
+            (Some(name), _) if !name.is_empty() => {
+                files.push((name, file.content.unwrap_or_default()))
+            }
+            // A file with no name continues the previous file.
+            (_, Some((_, content))) => {
+                content.push_str(file.content.as_deref().unwrap_or_default())
+            }
+            (_, None) => bail!("--{}_out: plugin returned a file with no name", output.name),
+        }
+    }
+
+    if let Some((name, _)) = files.iter().find(|(name, _)| !is_relative_file_name(name)) {
+        bail!(
+            "--{}_out: plugin returned a file name which is not a relative path: '{}'",
+            output.name,
+            name
+        );
+    }
+    for (name, content) in files {
+        write_output_file(&output.dir.join(name), &content)?;
+    }
     Ok(())
 }
+
+/// Returns true if `name` is a relative path with no `..` components, so it can be joined to the output directory
+/// without escaping it.
+fn is_relative_file_name(name: &str) -> bool {
+    Path::new(name)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+fn write_output_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).into_diagnostic()?;
+    }
+    fs::write(path, content).map_err(|err| miette!("failed to write '{}': {}", path.display(), err))
+}
+
+fn read_input(path: &PathBuf) -> Result<Vec<u8>> {
+    if path.as_os_str() == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes).into_diagnostic()?;
+        Ok(bytes)
+    } else {
+        fs::read(path).into_diagnostic()
+    }
+}
+
+fn print_bazel_metadata(compiler: &Compiler) {
+    let pool = compiler.descriptor_pool();
+    for root in compiler.roots() {
+        let file = pool
+            .get_file_by_name(root.name())
+            .expect("file not found in pool");
+        let src = root.name().rsplit('/').next().unwrap_or(root.name());
+        let deps: Vec<String> = file
+            .dependencies()
+            .map(|dep| bazel_target(dep.name()))
+            .collect();
+
+        println!(
+            "{}",
+            json!({
+                "target": bazel_target(root.name()),
+                "srcs": [src],
+                "deps": deps,
+            })
+        );
+    }
+}
+
+/// Gets the conventional `proto_library` target name for a file, relative to the include path.
+fn bazel_target(name: &str) -> String {
+    let (package, file_name) = name.rsplit_once('/').unwrap_or(("", name));
+    let stem = file_name.strip_suffix(".proto").unwrap_or(file_name);
+
+    if package == "google/protobuf" {
+        // Well-known types are provided by the protobuf repository.
+        format!("@com_google_protobuf//:{}_proto", stem)
+    } else {
+        format!("//{}:{}_proto", package, stem)
+    }
+}
+
+#[cfg(all(test, unix))]
+fn fake_plugin(dir: &Path, script: &str) -> HashMap<String, PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join("protoc-gen-fake");
+    fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    HashMap::from([("fake".to_owned(), path)])
+}
+
+#[cfg(unix)]
+#[test]
+fn plugin_invalid_file_name() {
+    use prost_types::compiler::code_generator_response;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let out_dir = dir.path().join("out");
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler.add_file_with_content("foo.proto", "").unwrap();
+
+    for name in [
+        "../escape.txt",
+        "/tmp/protox_escape.txt",
+        "a/../../escape.txt",
+    ] {
+        let response = CodeGeneratorResponse {
+            file: vec![
+                code_generator_response::File {
+                    name: Some("ok.txt".to_owned()),
+                    content: Some("ok".to_owned()),
+                    ..Default::default()
+                },
+                code_generator_response::File {
+                    name: Some(name.to_owned()),
+                    content: Some("escaped".to_owned()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let response_path = dir.path().join("response.bin");
+        fs::write(&response_path, response.encode_to_vec()).unwrap();
+        let plugins = fake_plugin(
+            dir.path(),
+            &format!("cat > /dev/null\ncat '{}'\n", response_path.display()),
+        );
+
+        let output = PluginOutput {
+            name: "fake".to_owned(),
+            dir: out_dir.clone(),
+            parameters: Vec::new(),
+        };
+        let err = run_plugin(&compiler, &output, &plugins).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "--fake_out: plugin returned a file name which is not a relative path: '{}'",
+                name
+            )
+        );
+        assert!(!out_dir.join("ok.txt").exists());
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn plugin_exits_without_reading_input() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    // Large enough that writing the request fails if the plugin exits first.
+    let source = format!("option java_package = \"{}\";", "a".repeat(1 << 20));
+    compiler
+        .add_file_with_content("foo.proto", &source)
+        .unwrap();
+
+    let plugins = fake_plugin(dir.path(), "exit 3\n");
+    let output = PluginOutput {
+        name: "fake".to_owned(),
+        dir: dir.path().join("out"),
+        parameters: Vec::new(),
+    };
+    let err = run_plugin(&compiler, &output, &plugins).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "plugin '{}' failed: exit status: 3",
+            dir.path().join("protoc-gen-fake").display()
+        )
+    );
+}
+
+#[test]
+fn depfile_descriptor_sets() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("dep.proto", "message Dep {}")
+        .unwrap()
+        .add_file_with_content("input.proto", "message Input {}")
+        .unwrap();
+    let set = compiler.file_descriptor_set();
+    fs::write(
+        dir.path().join("dep.bin"),
+        FileDescriptorSet {
+            file: vec![set.file[0].clone()],
+        }
+        .encode_to_vec(),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("input.bin"),
+        FileDescriptorSet {
+            file: vec![set.file[1].clone()],
+        }
+        .encode_to_vec(),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("root.proto"),
+        "import 'dep.proto'; message Root { optional Dep dep = 1; }",
+    )
+    .unwrap();
+
+    let path = |name: &str| dir.path().join(name).into_os_string();
+    let (args, plugin_outputs) = parse_args([
+        "protox".into(),
+        "-I".into(),
+        path(""),
+        "--descriptor-set-in".into(),
+        path("dep.bin"),
+        "--input-descriptor-set".into(),
+        path("input.bin"),
+        "--depfile".into(),
+        path("out.d"),
+        "-o".into(),
+        path("out.bin"),
+        path("root.proto"),
+    ])
+    .unwrap();
+    run(args, plugin_outputs).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("out.d")).unwrap(),
+        format!(
+            "{}: \\\n  {} \\\n  {} \\\n  {}\n",
+            dir.path().join("out.bin").display(),
+            dir.path().join("dep.bin").display(),
+            dir.path().join("input.bin").display(),
+            dir.path().join("root.proto").display(),
+        )
+    );
+}
+
+#[test]
+fn descriptor_set_in() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let mut compiler = Compiler::new::<_, &str>([]).unwrap();
+    compiler
+        .add_file_with_content("dep.proto", "message Dep {}")
+        .unwrap();
+    fs::write(
+        dir.path().join("dep.bin"),
+        compiler.encode_file_descriptor_set(),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("root.proto"),
+        "import 'dep.proto'; message Root { optional Dep dep = 1; }",
+    )
+    .unwrap();
+
+    let compile = || {
+        let path = |name: &str| dir.path().join(name).into_os_string();
+        let (args, plugin_outputs) = parse_args([
+            "protox".into(),
+            "-I".into(),
+            path(""),
+            "--descriptor-set-in".into(),
+            path("dep.bin"),
+            "--include-imports".into(),
+            "-o".into(),
+            path("out.bin"),
+            path("root.proto"),
+        ])
+        .unwrap();
+        run(args, plugin_outputs).unwrap();
+        FileDescriptorSet::decode(fs::read(dir.path().join("out.bin")).unwrap().as_slice()).unwrap()
+    };
+
+    // The import is only available from the descriptor set.
+    let set = compile();
+    assert_eq!(set.file[0].name(), "dep.proto");
+    assert_eq!(set.file[0].message_type[0].name(), "Dep");
+    assert_eq!(set.file[1].name(), "root.proto");
+
+    // A source file with the same name is not read, since the descriptor set is searched first.
+    fs::write(dir.path().join("dep.proto"), "invalid").unwrap();
+    assert_eq!(compile(), set);
+}
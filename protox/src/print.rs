@@ -0,0 +1,702 @@
+//! Conversion of file descriptors back to protobuf source.
+//!
+//! See [`to_proto()`] for details.
+
+use std::fmt::{self, Write};
+
+use prost_reflect::{Kind, ReflectMessage, Value};
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+    ServiceDescriptorProto, UninterpretedOption,
+};
+
+const UNINTERPRETED_OPTION: u32 = 999;
+const MAX_MESSAGE_FIELD_NUMBER: i32 = 536_870_911;
+
+/// Formats a file descriptor as protobuf source.
+///
+/// The descriptor may be compiled, for example by [`Compiler`](crate::Compiler) or decoded from a binary file, or
+/// only parsed by [`protox_parse::parse()`]. Map fields and groups are converted back to their shorthand syntax,
+/// and options are written using the text format for their values. Comments and formatting of the original source
+/// are not preserved, and extension options are only included if they are uninterpreted, since their values are
+/// not retained by [`prost_types`].
+///
+/// Parsing the output produces an equivalent descriptor, except for source code info and the position of messages
+/// generated for group fields in top-level `extend` blocks, which are listed after all other messages.
+///
+/// # Examples
+///
+/// ```
+/// let file = protox_parse::parse("foo.proto", "
+///     syntax = 'proto3';
+///     package foo;
+///
+///     message Foo {
+///         map<string, int32> counts = 1 [deprecated = true];
+///         oneof kind {
+///             string name = 2;
+///         }
+///     }
+/// ").unwrap();
+///
+/// assert_eq!(protox::print::to_proto(&file), r#"syntax = "proto3";
+///
+/// package foo;
+///
+/// message Foo {
+///   map<string, int32> counts = 1 [deprecated = true];
+///   oneof kind {
+///     string name = 2;
+///   }
+/// }
+/// "#);
+/// ```
+pub fn to_proto(file: &FileDescriptorProto) -> String {
+    let mut printer = Printer {
+        syntax: file.syntax(),
+        out: String::new(),
+        depth: 0,
+    };
+    printer.print_file(file);
+    printer.out
+}
+
+struct Printer<'a> {
+    syntax: &'a str,
+    out: String,
+    depth: usize,
+}
+
+impl Printer<'_> {
+    fn line(&mut self, text: impl fmt::Display) {
+        writeln!(self.out, "{:indent$}{}", "", text, indent = self.depth * 2).unwrap();
+    }
+
+    /// Separates top-level definitions with an empty line.
+    fn separate(&mut self) {
+        if self.depth == 0 && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    /// Writes a block, or `header {}` if the body is empty.
+    fn block(&mut self, header: impl fmt::Display, body: impl FnOnce(&mut Self)) {
+        self.line(format_args!("{} {{", header));
+        let start = self.out.len();
+        self.depth += 1;
+        body(self);
+        self.depth -= 1;
+        if self.out.len() == start {
+            self.out.truncate(start - 1);
+            self.out.push_str("}\n");
+        } else {
+            self.line("}");
+        }
+    }
+
+    fn print_file(&mut self, file: &FileDescriptorProto) {
+        if let Some(syntax) = &file.syntax {
+            self.line(format_args!("syntax = \"{}\";", syntax));
+        }
+        if let Some(package) = &file.package {
+            self.separate();
+            self.line(format_args!("package {};", package));
+        }
+
+        if !file.dependency.is_empty() {
+            self.separate();
+            for (index, dependency) in file.dependency.iter().enumerate() {
+                let index = index as i32;
+                let modifier = if file.public_dependency.contains(&index) {
+                    "public "
+                } else if file.weak_dependency.contains(&index) {
+                    "weak "
+                } else {
+                    ""
+                };
+                self.line(format_args!("import {}\"{}\";", modifier, dependency));
+            }
+        }
+
+        let options = options(file.options.as_ref());
+        if !options.is_empty() {
+            self.separate();
+            self.print_options(&options);
+        }
+
+        for message in &file.message_type {
+            if !is_group_type(message, &file.extension) {
+                self.separate();
+                self.print_message(message);
+            }
+        }
+        for enum_ in &file.enum_type {
+            self.separate();
+            self.print_enum(enum_);
+        }
+        for service in &file.service {
+            self.separate();
+            self.print_service(service);
+        }
+        self.print_extensions(&file.extension, &file.message_type);
+    }
+
+    fn print_options(&mut self, options: &[(String, String)]) {
+        for (name, value) in options {
+            self.line(format_args!("option {} = {};", name, value));
+        }
+    }
+
+    fn print_message(&mut self, message: &DescriptorProto) {
+        self.block(format_args!("message {}", message.name()), |printer| {
+            printer.print_message_body(message)
+        });
+    }
+
+    fn print_message_body(&mut self, message: &DescriptorProto) {
+        self.print_options(&options(message.options.as_ref()));
+
+        let mut printed_oneofs = vec![false; message.oneof_decl.len()];
+        for field in &message.field {
+            match real_oneof_index(field) {
+                Some(index) if index < printed_oneofs.len() => {
+                    if !printed_oneofs[index] {
+                        printed_oneofs[index] = true;
+                        self.print_oneof(message, index);
+                    }
+                }
+                _ => self.print_field(field, &message.nested_type),
+            }
+        }
+
+        for nested in &message.nested_type {
+            if !is_map_entry(nested)
+                && !is_group_type(nested, &message.field)
+                && !is_group_type(nested, &message.extension)
+            {
+                self.print_message(nested);
+            }
+        }
+        for enum_ in &message.enum_type {
+            self.print_enum(enum_);
+        }
+        self.print_extensions(&message.extension, &message.nested_type);
+
+        for range in &message.extension_range {
+            let options = options(range.options.as_ref());
+            // A single number cannot be followed by options, so it is written as `N to N` if there are any.
+            let (start, end) = (range.start(), range.end() - 1);
+            let range = if start == end && !options.is_empty() {
+                format!("{} to {}", start, end)
+            } else {
+                fmt_range(start, end, MAX_MESSAGE_FIELD_NUMBER)
+            };
+            self.line(format_args!(
+                "extensions {}{};",
+                range,
+                fmt_option_list(&options)
+            ));
+        }
+        if !message.reserved_range.is_empty() {
+            let ranges: Vec<String> = message
+                .reserved_range
+                .iter()
+                .map(|range| fmt_range(range.start(), range.end() - 1, MAX_MESSAGE_FIELD_NUMBER))
+                .collect();
+            self.line(format_args!("reserved {};", ranges.join(", ")));
+        }
+        self.print_reserved_names(&message.reserved_name);
+    }
+
+    fn print_oneof(&mut self, message: &DescriptorProto, index: usize) {
+        let oneof = &message.oneof_decl[index];
+        self.block(format_args!("oneof {}", oneof.name()), |printer| {
+            printer.print_options(&options(oneof.options.as_ref()));
+            for field in &message.field {
+                if real_oneof_index(field) == Some(index) {
+                    printer.print_field(field, &message.nested_type);
+                }
+            }
+        });
+    }
+
+    /// Writes a field, where `scope` contains the definitions of any group or map entry types.
+    fn print_field(&mut self, field: &FieldDescriptorProto, scope: &[DescriptorProto]) {
+        let label = self.field_label(field);
+        let options = field_options(field);
+
+        if field.r#type() == Type::Group {
+            if let Some(group) = find_type(scope, field.type_name()) {
+                self.block(
+                    format_args!(
+                        "{}group {} = {}{}",
+                        label,
+                        group.name(),
+                        field.number(),
+                        fmt_option_list(&options)
+                    ),
+                    |printer| printer.print_message_body(group),
+                );
+                return;
+            }
+        }
+
+        if let (Some(entry), Label::Repeated) = (find_type(scope, field.type_name()), field.label())
+        {
+            if let ([key, value], true) = (entry.field.as_slice(), is_map_entry(entry)) {
+                self.line(format_args!(
+                    "map<{}, {}> {} = {}{};",
+                    field_type(key),
+                    field_type(value),
+                    field.name(),
+                    field.number(),
+                    fmt_option_list(&options)
+                ));
+                return;
+            }
+        }
+
+        self.line(format_args!(
+            "{}{} {} = {}{};",
+            label,
+            field_type(field),
+            field.name(),
+            field.number(),
+            fmt_option_list(&options)
+        ));
+    }
+
+    fn field_label(&self, field: &FieldDescriptorProto) -> &'static str {
+        if real_oneof_index(field).is_some() {
+            return "";
+        }
+
+        match field.label() {
+            Label::Repeated => "repeated ",
+            Label::Required => "required ",
+            Label::Optional if field.proto3_optional() || self.syntax != "proto3" => "optional ",
+            Label::Optional => "",
+        }
+    }
+
+    /// Writes extensions in `extend` blocks, grouping consecutive extensions of the same message.
+    fn print_extensions(&mut self, extensions: &[FieldDescriptorProto], scope: &[DescriptorProto]) {
+        for group in group_by_extendee(extensions) {
+            self.separate();
+            self.block(format_args!("extend {}", group[0].extendee()), |printer| {
+                for extension in group {
+                    printer.print_field(extension, scope);
+                }
+            });
+        }
+    }
+
+    fn print_enum(&mut self, enum_: &EnumDescriptorProto) {
+        self.block(format_args!("enum {}", enum_.name()), |printer| {
+            printer.print_options(&options(enum_.options.as_ref()));
+            for value in &enum_.value {
+                printer.line(format_args!(
+                    "{} = {}{};",
+                    value.name(),
+                    value.number(),
+                    fmt_option_list(&options(value.options.as_ref()))
+                ));
+            }
+            if !enum_.reserved_range.is_empty() {
+                let ranges: Vec<String> = enum_
+                    .reserved_range
+                    .iter()
+                    .map(|range| fmt_range(range.start(), range.end(), i32::MAX))
+                    .collect();
+                printer.line(format_args!("reserved {};", ranges.join(", ")));
+            }
+            printer.print_reserved_names(&enum_.reserved_name);
+        });
+    }
+
+    fn print_reserved_names(&mut self, names: &[String]) {
+        if !names.is_empty() {
+            let names: Vec<String> = names.iter().map(|name| format!("\"{}\"", name)).collect();
+            self.line(format_args!("reserved {};", names.join(", ")));
+        }
+    }
+
+    fn print_service(&mut self, service: &ServiceDescriptorProto) {
+        self.block(format_args!("service {}", service.name()), |printer| {
+            printer.print_options(&options(service.options.as_ref()));
+            for method in &service.method {
+                let header = format!(
+                    "rpc {}({}{}) returns ({}{})",
+                    method.name(),
+                    if method.client_streaming() {
+                        "stream "
+                    } else {
+                        ""
+                    },
+                    method.input_type(),
+                    if method.server_streaming() {
+                        "stream "
+                    } else {
+                        ""
+                    },
+                    method.output_type(),
+                );
+
+                let options = options(method.options.as_ref());
+                if options.is_empty() {
+                    printer.line(format_args!("{};", header));
+                } else {
+                    printer.block(header, |printer| printer.print_options(&options));
+                }
+            }
+        });
+    }
+}
+
+/// Splits extensions into groups of consecutive extensions of the same message.
+fn group_by_extendee(extensions: &[FieldDescriptorProto]) -> Vec<&[FieldDescriptorProto]> {
+    let mut groups = Vec::new();
+    let mut rest = extensions;
+    while let Some(first) = rest.first() {
+        let len = rest
+            .iter()
+            .position(|extension| extension.extendee() != first.extendee())
+            .unwrap_or(rest.len());
+        let (group, tail) = rest.split_at(len);
+        groups.push(group);
+        rest = tail;
+    }
+    groups
+}
+
+/// Gets the index of the oneof containing a field, excluding synthetic oneofs for proto3 optional fields.
+fn real_oneof_index(field: &FieldDescriptorProto) -> Option<usize> {
+    match field.oneof_index {
+        Some(index) if !field.proto3_optional() => Some(index as usize),
+        _ => None,
+    }
+}
+
+fn is_map_entry(message: &DescriptorProto) -> bool {
+    message
+        .options
+        .as_ref()
+        .is_some_and(|options| options.map_entry())
+}
+
+/// Returns `true` if the message is the type of a group field, and so is written inline with the field.
+fn is_group_type(message: &DescriptorProto, fields: &[FieldDescriptorProto]) -> bool {
+    fields.iter().any(|field| {
+        field.r#type() == Type::Group
+            && field.type_name().rsplit('.').next() == Some(message.name())
+    })
+}
+
+fn find_type<'a>(scope: &'a [DescriptorProto], type_name: &str) -> Option<&'a DescriptorProto> {
+    let name = type_name.rsplit('.').next()?;
+    scope.iter().find(|message| message.name() == name)
+}
+
+fn field_type(field: &FieldDescriptorProto) -> String {
+    match &field.type_name {
+        Some(type_name) => type_name.clone(),
+        None => field
+            .r#type()
+            .as_str_name()
+            .trim_start_matches("TYPE_")
+            .to_ascii_lowercase(),
+    }
+}
+
+fn fmt_range(start: i32, end: i32, max: i32) -> String {
+    if start == end {
+        start.to_string()
+    } else if end == max {
+        format!("{} to max", start)
+    } else {
+        format!("{} to {}", start, end)
+    }
+}
+
+fn fmt_option_list(options: &[(String, String)]) -> String {
+    if options.is_empty() {
+        return String::new();
+    }
+
+    let options: Vec<String> = options
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect();
+    format!(" [{}]", options.join(", "))
+}
+
+/// Gets the options of a field, including the `default` and `json_name` pseudo-options.
+fn field_options(field: &FieldDescriptorProto) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+
+    if let Some(default_value) = &field.default_value {
+        let value = match field.r#type() {
+            Type::String => Value::String(default_value.clone()).to_string(),
+            // Bytes default values are already escaped.
+            Type::Bytes => format!("\"{}\"", default_value),
+            _ => default_value.clone(),
+        };
+        result.push(("default".to_owned(), value));
+    }
+    if let Some(json_name) = &field.json_name {
+        if *json_name != to_json_name(field.name()) {
+            result.push((
+                "json_name".to_owned(),
+                Value::String(json_name.clone()).to_string(),
+            ));
+        }
+    }
+
+    result.extend(options(field.options.as_ref()));
+    result
+}
+
+/// Gets the name and formatted value of each set option.
+fn options<T>(options: Option<&T>) -> Vec<(String, String)>
+where
+    T: ReflectMessage,
+{
+    let mut result = Vec::new();
+    let Some(options) = options else {
+        return result;
+    };
+
+    let message = options.transcode_to_dynamic();
+    for (field, value) in message.fields() {
+        if field.number() == UNINTERPRETED_OPTION {
+            for option in value.as_list().unwrap_or_default() {
+                let option: UninterpretedOption = option
+                    .as_message()
+                    .expect("expected message")
+                    .transcode_to()
+                    .expect("invalid uninterpreted option");
+                result.push(fmt_uninterpreted_option(&option));
+            }
+        } else if let Value::List(values) = value {
+            for value in values {
+                result.push((field.name().to_owned(), fmt_value(&field.kind(), value)));
+            }
+        } else {
+            result.push((field.name().to_owned(), fmt_value(&field.kind(), value)));
+        }
+    }
+    result
+}
+
+fn fmt_value(kind: &Kind, value: &Value) -> String {
+    match (kind, value) {
+        (Kind::Enum(enum_), Value::EnumNumber(number)) => match enum_.get_value(*number) {
+            Some(value) => value.name().to_owned(),
+            None => number.to_string(),
+        },
+        (_, Value::F32(value)) if value.is_nan() => "nan".to_owned(),
+        (_, Value::F64(value)) if value.is_nan() => "nan".to_owned(),
+        _ => value.to_string(),
+    }
+}
+
+fn fmt_uninterpreted_option(option: &UninterpretedOption) -> (String, String) {
+    let mut name = String::new();
+    for (index, part) in option.name.iter().enumerate() {
+        if index != 0 {
+            name.push('.');
+        }
+        if part.is_extension {
+            write!(name, "({})", part.name_part).unwrap();
+        } else {
+            name.push_str(&part.name_part);
+        }
+    }
+
+    let value = if let Some(ident) = &option.identifier_value {
+        ident.clone()
+    } else if let Some(int) = option.positive_int_value {
+        int.to_string()
+    } else if let Some(int) = option.negative_int_value {
+        int.to_string()
+    } else if let Some(float) = option.double_value {
+        fmt_value(&Kind::Double, &Value::F64(float))
+    } else if let Some(string) = &option.string_value {
+        Value::Bytes(string.clone().into()).to_string()
+    } else if let Some(aggregate) = &option.aggregate_value {
+        format!("{{ {} }}", aggregate)
+    } else {
+        String::new()
+    };
+
+    (name, value)
+}
+
+/// Gets the default JSON name of a field, which is the name converted to lower camel case.
+pub(crate) fn to_json_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut uppercase_next = false;
+    for ch in name.chars() {
+        if ch == '_' {
+            uppercase_next = true;
+        } else if uppercase_next {
+            result.push(ch.to_ascii_uppercase());
+            uppercase_next = false;
+        } else {
+            result.push(ch);
+        }
+    }
+    result
+}
+
+#[test]
+fn round_trip() {
+    use crate::{file::GoogleFileResolver, Compiler};
+
+    const SOURCE: &str = r#"
+        syntax = "proto2";
+        package foo.bar;
+
+        import "google/protobuf/descriptor.proto";
+        import public "google/protobuf/empty.proto";
+
+        option java_package = "com.foo.bar";
+        option optimize_for = SPEED;
+
+        message Foo {
+            option deprecated = true;
+
+            required int32 a = 1 [default = -5, json_name = "A"];
+            optional string b = 2 [default = "hello\n\"world\""];
+            optional bytes c = 3 [default = "\001\377"];
+            optional double d = 4 [default = inf];
+            repeated Kind kinds = 5 [packed = true];
+            map<string, Foo> foos = 6;
+            optional group Bar = 7 {
+                optional int32 x = 1;
+            }
+            oneof choice {
+                float e = 8;
+                Nested f = 9 [deprecated = true];
+            }
+            optional int32 foo_bar = 10;
+
+            message Nested {}
+
+            enum Kind {
+                option allow_alias = true;
+                ZERO = 0;
+                ONE = 1 [deprecated = true];
+                UNO = 1;
+                NEG = -1;
+                reserved 5, 8 to 10, 20 to max;
+                reserved "OLD";
+            }
+
+            extend Foo {
+                optional int32 ext = 100;
+                optional group Baz = 101 {
+                    optional int32 y = 1;
+                }
+            }
+
+            extensions 100 to 199;
+            extensions 1000 to max;
+            reserved 11, 15 to 20;
+            reserved "old", "older";
+        }
+
+        service Service {
+            option deprecated = true;
+
+            rpc Get(Foo) returns (Foo);
+            rpc Stream(stream Foo) returns (stream google.protobuf.Empty) {
+                option idempotency_level = NO_SIDE_EFFECTS;
+            }
+        }
+    "#;
+
+    // Options which are not retained by prost-types, and so are only printed if they are uninterpreted.
+    const EXTENSION_OPTIONS: &str = r#"
+        extend google.protobuf.OneofOptions {
+            optional int32 oneof_opt = 1000;
+        }
+
+        message Bar {
+            oneof choice {
+                option (oneof_opt) = 7;
+                int32 a = 1;
+            }
+
+            extensions 1000 to max [verification = UNVERIFIED];
+            extensions 500 to 500 [verification = UNVERIFIED];
+        }
+    "#;
+
+    let strip_source_info = |file: FileDescriptorProto| FileDescriptorProto {
+        source_code_info: None,
+        ..file
+    };
+
+    let parsed =
+        protox_parse::parse("foo.proto", &format!("{}{}", SOURCE, EXTENSION_OPTIONS)).unwrap();
+    let printed = to_proto(&parsed);
+    assert_eq!(
+        strip_source_info(protox_parse::parse("foo.proto", &printed).unwrap()),
+        strip_source_info(parsed),
+    );
+
+    let compile = |source: &str| {
+        let mut compiler = Compiler::with_file_resolver(GoogleFileResolver::new());
+        compiler.add_file_with_content("foo.proto", source).unwrap();
+        compiler.file_descriptor_set().file.pop().unwrap()
+    };
+
+    let compiled = compile(SOURCE);
+    let printed = to_proto(&compiled);
+    assert_eq!(compile(&printed), compiled, "{}", printed);
+}
+
+#[test]
+fn round_trip_google_files() {
+    use crate::file::{FileResolver, GoogleFileResolver};
+
+    let resolver = GoogleFileResolver::new();
+    for name in [
+        "google/protobuf/any.proto",
+        "google/protobuf/api.proto",
+        "google/protobuf/descriptor.proto",
+        "google/protobuf/duration.proto",
+        "google/protobuf/empty.proto",
+        "google/protobuf/field_mask.proto",
+        "google/protobuf/source_context.proto",
+        "google/protobuf/struct.proto",
+        "google/protobuf/timestamp.proto",
+        "google/protobuf/type.proto",
+        "google/protobuf/wrappers.proto",
+        "google/protobuf/compiler/plugin.proto",
+    ] {
+        let parsed = resolver
+            .open_file(name)
+            .unwrap()
+            .file_descriptor_proto()
+            .clone();
+        let printed = to_proto(&parsed);
+        let reparsed = protox_parse::parse(name, &printed).unwrap_or_else(|err| {
+            panic!("failed to parse printed '{}': {:?}\n{}", name, err, printed)
+        });
+        assert_eq!(
+            FileDescriptorProto {
+                source_code_info: None,
+                ..reparsed
+            },
+            FileDescriptorProto {
+                source_code_info: None,
+                ..parsed
+            },
+            "{}",
+            name
+        );
+    }
+}
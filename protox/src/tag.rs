@@ -0,0 +1,285 @@
+//! Field numbers of the messages in `google/protobuf/descriptor.proto`.
+//!
+//! These can be used to interpret the [`path`](prost_types::source_code_info::Location::path) of a source code
+//! location, which consists of alternating field numbers and indices into repeated fields, starting from a
+//! [`FileDescriptorProto`](prost_types::FileDescriptorProto).
+//!
+//! # Examples
+//!
+//! ```
+//! # use protox::tag::TagMap;
+//! // The path of the name of the second field of the first message in a file.
+//! let path = [4, 0, 2, 1, 1];
+//!
+//! let mut map = TagMap::for_file_descriptor_proto();
+//! let mut names = Vec::new();
+//! for chunk in path.chunks(2) {
+//!     names.push(map.get(chunk[0]).unwrap());
+//!     if let Some(child) = map.child(chunk[0]) {
+//!         map = child;
+//!     }
+//! }
+//! assert_eq!(names, ["message_type", "field", "name"]);
+//! ```
+
+use std::{collections::HashMap, sync::OnceLock};
+
+/// A mapping from the field numbers of a descriptor message to their names.
+#[derive(Debug)]
+pub struct TagMap {
+    message_name: &'static str,
+    names: HashMap<i32, &'static str>,
+    children: HashMap<i32, fn() -> &'static TagMap>,
+}
+
+type Field = (i32, &'static str, Option<fn() -> &'static TagMap>);
+
+impl TagMap {
+    /// Gets the field numbers of `google.protobuf.FileDescriptorProto`.
+    pub fn for_file_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.FileDescriptorProto",
+            &[
+                (1, "name", None),
+                (2, "package", None),
+                (3, "dependency", None),
+                (10, "public_dependency", None),
+                (11, "weak_dependency", None),
+                (4, "message_type", Some(TagMap::for_descriptor_proto)),
+                (5, "enum_type", Some(TagMap::for_enum_descriptor_proto)),
+                (6, "service", Some(TagMap::for_service_descriptor_proto)),
+                (7, "extension", Some(TagMap::for_field_descriptor_proto)),
+                (8, "options", None),
+                (9, "source_code_info", None),
+                (12, "syntax", None),
+                (13, "edition", None),
+            ],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.DescriptorProto`.
+    pub fn for_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.DescriptorProto",
+            &[
+                (1, "name", None),
+                (2, "field", Some(TagMap::for_field_descriptor_proto)),
+                (6, "extension", Some(TagMap::for_field_descriptor_proto)),
+                (3, "nested_type", Some(TagMap::for_descriptor_proto)),
+                (4, "enum_type", Some(TagMap::for_enum_descriptor_proto)),
+                (5, "extension_range", Some(TagMap::for_extension_range)),
+                (8, "oneof_decl", Some(TagMap::for_oneof_descriptor_proto)),
+                (7, "options", None),
+                (9, "reserved_range", Some(TagMap::for_reserved_range)),
+                (10, "reserved_name", None),
+            ],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.DescriptorProto.ExtensionRange`.
+    pub fn for_extension_range() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.DescriptorProto.ExtensionRange",
+            &[(1, "start", None), (2, "end", None), (3, "options", None)],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.DescriptorProto.ReservedRange`.
+    pub fn for_reserved_range() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.DescriptorProto.ReservedRange",
+            &[(1, "start", None), (2, "end", None)],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.FieldDescriptorProto`.
+    pub fn for_field_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.FieldDescriptorProto",
+            &[
+                (1, "name", None),
+                (3, "number", None),
+                (4, "label", None),
+                (5, "type", None),
+                (6, "type_name", None),
+                (2, "extendee", None),
+                (7, "default_value", None),
+                (9, "oneof_index", None),
+                (10, "json_name", None),
+                (8, "options", None),
+                (17, "proto3_optional", None),
+            ],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.OneofDescriptorProto`.
+    pub fn for_oneof_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.OneofDescriptorProto",
+            &[(1, "name", None), (2, "options", None)],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.EnumDescriptorProto`.
+    pub fn for_enum_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.EnumDescriptorProto",
+            &[
+                (1, "name", None),
+                (2, "value", Some(TagMap::for_enum_value_descriptor_proto)),
+                (3, "options", None),
+                (4, "reserved_range", Some(TagMap::for_enum_reserved_range)),
+                (5, "reserved_name", None),
+            ],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.EnumDescriptorProto.EnumReservedRange`.
+    pub fn for_enum_reserved_range() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.EnumDescriptorProto.EnumReservedRange",
+            &[(1, "start", None), (2, "end", None)],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.EnumValueDescriptorProto`.
+    pub fn for_enum_value_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.EnumValueDescriptorProto",
+            &[(1, "name", None), (2, "number", None), (3, "options", None)],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.ServiceDescriptorProto`.
+    pub fn for_service_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.ServiceDescriptorProto",
+            &[
+                (1, "name", None),
+                (2, "method", Some(TagMap::for_method_descriptor_proto)),
+                (3, "options", None),
+            ],
+        )
+    }
+
+    /// Gets the field numbers of `google.protobuf.MethodDescriptorProto`.
+    pub fn for_method_descriptor_proto() -> &'static TagMap {
+        static MAP: OnceLock<TagMap> = OnceLock::new();
+        init(
+            &MAP,
+            "google.protobuf.MethodDescriptorProto",
+            &[
+                (1, "name", None),
+                (2, "input_type", None),
+                (3, "output_type", None),
+                (4, "options", None),
+                (5, "client_streaming", None),
+                (6, "server_streaming", None),
+            ],
+        )
+    }
+
+    /// Gets the fully-qualified name of the descriptor message, such as `google.protobuf.FileDescriptorProto`.
+    pub fn message_name(&self) -> &'static str {
+        self.message_name
+    }
+
+    /// Gets the name of the field with the given number.
+    pub fn get(&self, tag: i32) -> Option<&'static str> {
+        self.names.get(&tag).copied()
+    }
+
+    /// Gets the field numbers of the message type of the field with the given number, if it is another descriptor
+    /// message.
+    ///
+    /// Options messages are not included, since their fields may be extended.
+    pub fn child(&self, tag: i32) -> Option<&'static TagMap> {
+        self.children.get(&tag).map(|child| child())
+    }
+
+    /// Gets an iterator over the field numbers and names in this map, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &'static str)> + '_ {
+        self.names.iter().map(|(&tag, &name)| (tag, name))
+    }
+}
+
+fn init(
+    cell: &'static OnceLock<TagMap>,
+    message_name: &'static str,
+    fields: &[Field],
+) -> &'static TagMap {
+    cell.get_or_init(|| TagMap {
+        message_name,
+        names: fields.iter().map(|&(tag, name, _)| (tag, name)).collect(),
+        children: fields
+            .iter()
+            .filter_map(|&(tag, _, child)| Some((tag, child?)))
+            .collect(),
+    })
+}
+
+#[test]
+fn matches_descriptor() {
+    use std::collections::HashSet;
+
+    use prost_reflect::{DescriptorPool, Kind};
+
+    fn check(pool: &DescriptorPool, map: &'static TagMap, visited: &mut HashSet<&'static str>) {
+        if !visited.insert(map.message_name()) {
+            return;
+        }
+
+        let message = pool.get_message_by_name(map.message_name()).unwrap();
+        let mut expected: Vec<_> = message
+            .fields()
+            .map(|field| (field.number() as i32, field.name().to_owned()))
+            .collect();
+        let mut actual: Vec<_> = map
+            .iter()
+            .map(|(tag, name)| (tag, name.to_owned()))
+            .collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected, "{}", map.message_name());
+
+        for field in message.fields() {
+            let child = map.child(field.number() as i32);
+            match field.kind() {
+                Kind::Message(ty)
+                    if !ty.name().ends_with("Options") && ty.name() != "SourceCodeInfo" =>
+                {
+                    let child = child.unwrap();
+                    assert_eq!(child.message_name(), ty.full_name());
+                    check(pool, child, visited);
+                }
+                _ => assert!(child.is_none()),
+            }
+        }
+    }
+
+    check(
+        &DescriptorPool::global(),
+        TagMap::for_file_descriptor_proto(),
+        &mut HashSet::new(),
+    );
+}
@@ -1,11 +1,14 @@
 use std::{
+    collections::HashSet,
     env, fs,
     path::PathBuf,
     process::{Command, Stdio},
 };
 
 use prost_reflect::{DescriptorPool, DynamicMessage, SerializeOptions, Value};
-use prost_types::{field_descriptor_proto::Type, source_code_info::Location};
+use prost_types::{
+    field_descriptor_proto::Type, source_code_info::Location, DescriptorProto, FileDescriptorProto,
+};
 use tempfile::TempDir;
 
 fn test_data_dir() -> PathBuf {
@@ -115,9 +118,24 @@ fn decode_file_descriptor(bytes: Vec<u8>) -> DynamicMessage {
     });
     debug_assert!(!files.is_empty());
 
+    // Normalize ordering of files, since protoc preserves the order in which files were imported
+    files.sort_by_cached_key(|f| {
+        f.as_message()
+            .unwrap()
+            .get_field_by_name("name")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_owned()
+    });
+
     for file in files {
         let file = file.as_message_mut().unwrap();
 
+        // protoc does not add locations for synthetic oneofs
+        let synthetic_oneofs =
+            synthetic_oneof_paths(&file.transcode_to::<FileDescriptorProto>().unwrap());
+
         // Normalize ordering of spans
         let locations = file
             .get_field_by_name_mut("source_code_info")
@@ -128,6 +146,14 @@ fn decode_file_descriptor(bytes: Vec<u8>) -> DynamicMessage {
             .unwrap()
             .as_list_mut()
             .unwrap();
+        locations.retain(|location| {
+            let location = location
+                .as_message()
+                .unwrap()
+                .transcode_to::<Location>()
+                .unwrap();
+            !synthetic_oneofs.contains(&location.path)
+        });
         locations.sort_unstable_by_key(|location| {
             let location = location
                 .as_message()
@@ -175,6 +201,29 @@ fn decode_file_descriptor(bytes: Vec<u8>) -> DynamicMessage {
     file_set
 }
 
+fn synthetic_oneof_paths(file: &FileDescriptorProto) -> HashSet<Vec<i32>> {
+    fn visit(message: &DescriptorProto, path: &mut Vec<i32>, paths: &mut HashSet<Vec<i32>>) {
+        for field in &message.field {
+            if field.proto3_optional() {
+                if let Some(index) = field.oneof_index {
+                    paths.insert([path.as_slice(), &[8, index]].concat());
+                }
+            }
+        }
+        for (index, nested) in message.nested_type.iter().enumerate() {
+            path.extend([3, index as i32]);
+            visit(nested, path, paths);
+            path.truncate(path.len() - 2);
+        }
+    }
+
+    let mut paths = HashSet::new();
+    for (index, message) in file.message_type.iter().enumerate() {
+        visit(message, &mut vec![4, index as i32], &mut paths);
+    }
+    paths
+}
+
 fn visit_messages(messages: &mut [Value], f: &impl Fn(&mut DynamicMessage)) {
     for message in messages {
         let message = message.as_message_mut().unwrap();
@@ -1,7 +1,7 @@
 use std::{env, fs, io, path::PathBuf};
 
 use insta::assert_yaml_snapshot;
-use miette::{Diagnostic, JSONReportHandler};
+use miette::{Diagnostic, JSONReportHandler, NamedSource, NarratableReportHandler};
 use prost::Message;
 use prost_reflect::{DescriptorPool, Value};
 use prost_types::{
@@ -12,6 +12,7 @@ use protox::{
     file::{ChainFileResolver, DescriptorSetFileResolver, File, FileResolver, GoogleFileResolver},
     Compiler, Error,
 };
+use protox_parse::ParseOptions;
 use tempfile::TempDir;
 
 struct TestFileResolver {
@@ -305,6 +306,496 @@ fn pass_through_extension_options() {
     );
 }
 
+#[test]
+fn parse_error_related() {
+    assert_yaml_snapshot!(check_err(&[(
+        "root.proto",
+        "
+        message Foo {
+            optional string a = 1 [default = '\\q'];
+        }
+
+        message Bar {
+            optional string b = 1 [default = '\\z'];
+        }
+    "
+    )]));
+}
+
+#[test]
+fn parse_error_with_source_code() {
+    let source = "message Foo {\n    optional string a = 1 [default = '\\q'];\n}\n";
+    let parse_err = protox_parse::parse("root.proto", source).unwrap_err();
+    let err = Error::from_parse_error(
+        parse_err,
+        NamedSource::new("path/to/root.proto", source.to_owned()),
+    );
+
+    assert_eq!(err.file(), Some("path/to/root.proto"));
+    let mut report = String::new();
+    NarratableReportHandler::new()
+        .render_report(&mut report, &err)
+        .unwrap();
+    assert_eq!(
+        report,
+        "invalid string escape
+    Diagnostic severity: error
+Begin snippet for path/to/root.proto starting at line 1, column 1
+
+snippet line 1: message Foo {
+snippet line 2:     optional string a = 1 [default = '\\q'];
+    label at line 2, column 39: defined here
+snippet line 3: }
+
+"
+    );
+}
+
+#[test]
+fn field_name_reserved() {
+    let err = check(&[(
+        "root.proto",
+        "
+        message Foo {
+            message Bar {
+                reserved 'a', 'b';
+                optional int32 b = 1;
+            }
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:5:32: field name 'b' is reserved"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn duplicate_name_in_other_file() {
+    let err = check(&[
+        ("dep.proto", "package pkg;\n\nmessage Foo {}\n"),
+        (
+            "root.proto",
+            "
+        package pkg;
+
+        enum Foo {
+            ZERO = 0;
+        }
+    ",
+        ),
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:4:14: name 'pkg.Foo' is already defined in file 'dep.proto'"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn method_type_not_found() {
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto3';
+
+        package pkg;
+
+        message Foo {}
+
+        service FooService {
+            rpc Bar(Foo) returns (Baz);
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:9:35: method 'pkg.FooService.Bar' output type 'Baz' not found"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto3';
+
+        enum Foo { ZERO = 0; }
+
+        service FooService {
+            rpc Bar(.Foo) returns (Foo);
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:7:21: 'Foo' is not a message type"
+    );
+}
+
+#[test]
+fn synthetic_oneof_name_conflict() {
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto3';
+
+        package pkg;
+
+        message Foo {
+            optional int32 foo = 1;
+            message _foo {}
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:8:21: name 'pkg.Foo._foo' of the synthetic oneof for field 'foo' is defined twice"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn map_entry_field_type() {
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto3';
+
+        message Foo {
+            map<string, int32> a = 1;
+            map<string, AEntry> b = 2;
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:6:13: 'Foo.AEntry' is a synthetic map entry message type, which cannot be used as the type of field 'Foo.BEntry.value'"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn map_entry_extension_type() {
+    let err = check(&[
+        (
+            "dep.proto",
+            "package dep; message Foo { map<string, int32> a = 1; }",
+        ),
+        (
+            "root.proto",
+            "import 'dep.proto'; message Bar { extensions 1; } extend Bar { repeated dep.Foo.AEntry b = 1; }",
+        ),
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:1:73: 'dep.Foo.AEntry' is a synthetic map entry message type, which cannot be used as the type of field 'b'"
+    );
+}
+
+#[test]
+fn extend_map_entry() {
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto2';
+
+        message Foo {
+            map<string, int32> a = 1;
+
+            extend AEntry {
+                optional int32 b = 100;
+            }
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:7:20: 'Foo.AEntry' is a synthetic map entry message type, which cannot be extended"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+
+    let err = check(&[
+        (
+            "dep.proto",
+            "package pkg; message Foo { map<int32, int32> a = 1; }",
+        ),
+        (
+            "root.proto",
+            "import 'dep.proto'; extend .pkg.Foo.AEntry { optional int32 b = 100; }",
+        ),
+    ])
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "'pkg.Foo.AEntry' is a synthetic map entry message type, which cannot be extended"
+    );
+}
+
+#[test]
+fn invalid_extension_number() {
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto2';
+
+        message Foo {
+            extensions 10 to 20, 100 to max;
+        }
+
+        extend Foo {
+            optional int32 a = 10;
+            optional int32 b = 22;
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:10:32: message 'Foo' does not define 22 as an extension number"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn overlapping_extension_ranges() {
+    let files = &[(
+        "root.proto",
+        "
+        message Foo {
+            extensions 1 to 5, 10 to 20;
+            message Bar {
+                extensions 100 to 199;
+                extensions 150 to max;
+            }
+        }
+    ",
+    )];
+
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver { files });
+    compiler.open_file("root.proto").unwrap();
+
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver { files });
+    compiler.with_protoc_compatibility_mode(true);
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:6:28: extension range 150 to 536870911 of message 'Foo.Bar' overlaps with range 100 to 199"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn invalid_json_name() {
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver {
+        files: &[
+            (
+                "root.proto",
+                "
+        message Foo {
+            optional int32 foo_bar = 1;
+            message Bar {
+                optional int32 id = 1 [json_name = '@id'];
+            }
+        }
+    ",
+            ),
+            ("valid.proto", "message Baz { optional int32 id = 1; }"),
+        ],
+    });
+    compiler.with_json_name_validator(|name, json_name| {
+        if json_name.starts_with('@') {
+            Some(format!("'{}' is a JSON-LD keyword", json_name))
+        } else {
+            assert_eq!(json_name, if name == "foo_bar" { "fooBar" } else { name });
+            None
+        }
+    });
+
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:5:40: invalid JSON name '@id' for field 'Foo.Bar.id': '@id' is a JSON-LD keyword"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+    assert!(compiler
+        .descriptor_pool()
+        .get_file_by_name("root.proto")
+        .is_none());
+
+    compiler.open_file("valid.proto").unwrap();
+}
+
+#[test]
+fn invalid_json_name_extension() {
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver {
+        files: &[(
+            "root.proto",
+            "package pkg; message Foo { extensions 1; } extend Foo { optional int32 foo_bar = 1; }",
+        )],
+    });
+    compiler.with_json_name_validator(|name, json_name| {
+        (name != json_name).then(|| "JSON names must match the field name".to_owned())
+    });
+
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid JSON name 'fooBar' for field 'pkg.foo_bar': JSON names must match the field name"
+    );
+}
+
+#[test]
+fn map_entry_field_type_restores_pool() {
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver {
+        files: &[
+            (
+                "invalid.proto",
+                "message Foo { map<int32, int32> a = 1; optional Foo.AEntry b = 2; }",
+            ),
+            (
+                "valid.proto",
+                "message LogEntry {} message Bar { repeated LogEntry e = 1; }",
+            ),
+        ],
+    });
+
+    let err = compiler.open_file("invalid.proto").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "'Foo.AEntry' is a synthetic map entry message type, which cannot be used as the type of field 'Foo.b'"
+    );
+    assert!(compiler
+        .descriptor_pool()
+        .get_file_by_name("invalid.proto")
+        .is_none());
+
+    compiler.open_file("valid.proto").unwrap();
+    assert_eq!(
+        compiler.files().map(|f| f.name()).collect::<Vec<_>>(),
+        ["valid.proto"]
+    );
+}
+
+#[test]
+fn warning_as_error() {
+    struct LenientFileResolver;
+
+    impl FileResolver for LenientFileResolver {
+        fn open_file(&self, name: &str) -> Result<File, Error> {
+            let source = match name {
+                "dep.proto" => {
+                    "syntax = 'proto3'; message Dep { optional int32 a = 1 [default = 5]; }"
+                }
+                "root.proto" => "import 'dep.proto';",
+                _ => return Err(Error::file_not_found(name)),
+            };
+
+            let mut options = ParseOptions::new();
+            options.allow_proto3_default_values(true);
+            File::from_source_with_options(name, source, &options)
+        }
+    }
+
+    let mut compiler = Compiler::with_file_resolver(LenientFileResolver);
+    compiler.open_file("root.proto").unwrap();
+
+    let mut compiler = Compiler::with_file_resolver(LenientFileResolver);
+    compiler.with_warning_as_error(true);
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(err.file(), Some("dep.proto"));
+    assert_eq!(
+        format!("{:?}", err),
+        "file 'dep.proto' has warnings, which are treated as errors: dep.proto:1:56: default values are ignored in proto3"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+    assert_eq!(compiler.files().count(), 0);
+
+    let mut compiler = Compiler::with_file_resolver(LenientFileResolver);
+    compiler.with_protoc_compatibility_mode(true);
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(err.file(), Some("dep.proto"));
+    assert_eq!(
+        err.to_string(),
+        "file 'dep.proto' has warnings, which are treated as errors"
+    );
+}
+
+#[test]
+fn require_syntax_statement() {
+    let files = &[
+        ("dep.proto", "message Dep {}"),
+        ("root.proto", "syntax = 'proto2'; import 'dep.proto';"),
+    ];
+
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver { files });
+    compiler.open_file("root.proto").unwrap();
+
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver { files });
+    compiler.with_require_syntax_statement(true);
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert!(err.is_check());
+    assert_eq!(err.file(), Some("dep.proto"));
+    assert_eq!(
+        format!("{:?}", err),
+        "file 'dep.proto' does not have a syntax statement"
+    );
+
+    // Files without source code are not checked.
+    compiler
+        .open_file_descriptor_proto(FileDescriptorProto {
+            name: Some("dep.proto".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+    compiler.open_file("root.proto").unwrap();
+}
+
+#[test]
+fn require_package_declaration() {
+    let files = &[
+        ("dep.proto", "message Dep {}"),
+        ("root.proto", "package root; import 'dep.proto';"),
+    ];
+
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver { files });
+    compiler.with_require_package_declaration(true);
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert!(err.is_check());
+    assert_eq!(err.file(), Some("dep.proto"));
+    assert_eq!(
+        format!("{:?}", err),
+        "file 'dep.proto' does not have a package statement"
+    );
+    assert_eq!(compiler.descriptor_pool().files().count(), 0);
+
+    compiler
+        .open_file_descriptor_proto(FileDescriptorProto {
+            name: Some("dep.proto".to_owned()),
+            package: Some("dep".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+    compiler.open_file("root.proto").unwrap();
+}
+
 #[test]
 fn error_fmt_debug() {
     let parse_err = check(&[("root.proto", "message {")]).unwrap_err();
@@ -382,3 +873,355 @@ fn name_resolution_incorrect() {
     );
     assert_eq!(format!("{}", error.help().unwrap()), "The innermost scope is searched first in name resolution. Consider using a leading '.' (i.e., '.foo.Foo') to start from the outermost scope.");
 }
+
+#[test]
+fn roots() {
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver {
+        files: &[
+            ("dep.proto", "message Dep {}"),
+            ("root1.proto", "import 'dep.proto';"),
+            ("root2.proto", "import 'root1.proto';"),
+        ],
+    });
+    compiler.open_file("root2.proto").unwrap();
+    compiler.open_file("root1.proto").unwrap();
+
+    assert_eq!(
+        compiler.files().map(|f| f.name()).collect::<Vec<_>>(),
+        ["dep.proto", "root1.proto", "root2.proto"]
+    );
+    assert_eq!(
+        compiler.roots().map(|f| f.name()).collect::<Vec<_>>(),
+        ["root1.proto", "root2.proto"]
+    );
+}
+
+#[test]
+fn unused_import() {
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver {
+        files: &[
+            ("dep.proto", "message Dep {}"),
+            ("dep2.proto", "message Dep2 {}"),
+            (
+                "root.proto",
+                "import 'dep.proto';\nimport 'dep2.proto';\n\nmessage Foo { optional Dep2 dep = 1; }",
+            ),
+        ],
+    });
+    compiler.with_error_on_unused_import(true);
+
+    let err = compiler.open_file("root.proto").unwrap_err();
+    assert_eq!(err.file(), Some("root.proto"));
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:1:1: import 'dep.proto' is not used"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+    assert!(compiler.files().all(|f| f.name() != "root.proto"));
+}
+
+#[test]
+fn unused_import_allowed() {
+    let mut compiler = Compiler::with_file_resolver(TestFileResolver {
+        files: &[
+            ("dep.proto", "message Dep {}"),
+            ("root.proto", "import 'dep.proto';"),
+        ],
+    });
+
+    compiler.open_file("root.proto").unwrap();
+}
+
+#[test]
+fn used_imports() {
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(TestFileResolver {
+        files: &[
+            ("dep.proto", "message Dep {}"),
+            ("public.proto", "import public 'dep.proto';"),
+            (
+                "ext.proto",
+                "
+                import 'google/protobuf/descriptor.proto';
+
+                extend google.protobuf.MessageOptions {
+                    optional int32 ext = 1001;
+                }
+            ",
+            ),
+            ("request.proto", "package pkg; message Request {}"),
+            ("reexport.proto", "import public 'dep.proto';"),
+            (
+                "root.proto",
+                "
+                import 'ext.proto';
+                import 'public.proto';
+                import 'reexport.proto';
+                import 'request.proto';
+
+                message Foo {
+                    option (ext) = 1;
+
+                    optional Dep dep = 1;
+                }
+
+                service Service {
+                    rpc Method(pkg.Request) returns (Foo);
+                }
+            ",
+            ),
+        ],
+    });
+    resolver.add(GoogleFileResolver::new());
+
+    let mut compiler = Compiler::with_file_resolver(resolver);
+    compiler.with_error_on_unused_import(true);
+    compiler.open_file("root.proto").unwrap();
+}
+
+fn check_with_google(files: &'static [(&'static str, &'static str)]) -> Result<Compiler, Error> {
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(TestFileResolver { files });
+    resolver.add(GoogleFileResolver::new());
+
+    let mut compiler = Compiler::with_file_resolver(resolver);
+    compiler.open_file(files[files.len() - 1].0)?;
+    Ok(compiler)
+}
+
+#[test]
+fn option_unknown_field() {
+    let err = check_with_google(&[(
+        "root.proto",
+        "
+        import 'google/protobuf/descriptor.proto';
+
+        package pkg;
+
+        message Opt {
+            optional int32 a = 1;
+            repeated Opt nested = 2;
+            optional string s = 3;
+        }
+
+        extend google.protobuf.MessageOptions {
+            optional Opt opt = 1001;
+        }
+
+        message Foo {
+            option (pkg.opt) = {
+                a: 1
+                s: 'x' \"y\"
+                nested: [{ a: 2 }, < a: 3 >]
+                nested { nested { b: 4 } }
+            };
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:17:13: field 'b' not found for message 'pkg.Opt' in option '(pkg.opt)'"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+}
+
+#[test]
+fn option_negative_for_unsigned() {
+    let err = check_with_google(&[(
+        "root.proto",
+        "
+        import 'google/protobuf/descriptor.proto';
+
+        package pkg;
+
+        message Opt {
+            optional uint32 a = 1;
+        }
+
+        extend google.protobuf.FieldOptions {
+            optional Opt opt = 1001;
+            optional int64 signed = 1002;
+        }
+
+        message Foo {
+            optional int32 foo = 1 [(signed) = -1, (opt).a = -5];
+        }
+    ",
+    )])
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:16:52: negative value -5 is not valid for unsigned option field 'pkg.Opt.a'"
+    );
+    assert_yaml_snapshot!(error_to_json(&err));
+
+    let err = check_with_google(&[(
+        "root.proto",
+        "
+        import 'google/protobuf/descriptor.proto';
+        extend google.protobuf.FileOptions { optional fixed64 opt = 1001; }
+        option (opt) = -1;
+    ",
+    )])
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "negative value -1 is not valid for unsigned option field 'opt'"
+    );
+}
+
+#[test]
+fn method_option_unknown_field() {
+    let err = check_with_google(&[(
+        "root.proto",
+        "
+        import 'google/protobuf/descriptor.proto';
+
+        package pkg;
+
+        message Opt {
+            optional int32 a = 1;
+        }
+
+        extend google.protobuf.MethodOptions {
+            optional Opt opt = 1001;
+        }
+
+        service Service {
+            rpc Method(Opt) returns (Opt) {
+                option idempotency_level = IDEMPOTENT;
+                option (pkg.opt) = { a: 1 b: 2 };
+            }
+        }
+    ",
+    )])
+    .unwrap_err();
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:17:17: field 'b' not found for message 'pkg.Opt' in option '(pkg.opt)'"
+    );
+
+    let err = check(&[(
+        "root.proto",
+        "
+        message Message {}
+
+        service Service {
+            rpc Method(Message) returns (Message) {
+                option unknown = true;
+            }
+        }
+    ",
+    )])
+    .unwrap_err();
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:6:17: option field 'unknown' is not defined"
+    );
+}
+
+#[test]
+fn proto3_enum_first_value_non_zero() {
+    let err = check(&[(
+        "root.proto",
+        "
+        syntax = 'proto3';
+
+        enum Enum {
+            ONE = 1;
+            ZERO = 0;
+        }
+    ",
+    )])
+    .unwrap_err();
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:5:19: the first value for proto3 enums must be 0"
+    );
+
+    check(&[(
+        "root.proto",
+        "
+        syntax = 'proto2';
+
+        enum Enum {
+            ONE = 1;
+        }
+    ",
+    )])
+    .unwrap();
+}
+
+#[test]
+fn option_already_set_nested() {
+    let err = check_with_google(&[(
+        "root.proto",
+        "
+        import 'google/protobuf/descriptor.proto';
+
+        package pkg;
+
+        message Http {
+            optional string get = 1;
+        }
+
+        extend google.protobuf.MethodOptions {
+            optional Http http = 1001;
+        }
+
+        service Service {
+            rpc Method(Http) returns (Http) {
+                option (pkg.http).get = 'a';
+                option (pkg.http).get = 'b';
+            }
+        }
+    ",
+    )])
+    .unwrap_err();
+    assert_eq!(
+        format!("{:?}", err),
+        "root.proto:17:17: option field '(pkg.http).get' has already been set"
+    );
+    assert_eq!(
+        err.labels().unwrap().next().unwrap().inner(),
+        &miette::SourceSpan::from(376..404)
+    );
+}
+
+#[test]
+fn option_aggregate_valid() {
+    check_with_google(&[(
+        "root.proto",
+        "
+        import 'google/protobuf/descriptor.proto';
+
+        message Opt {
+            optional int32 a = 1;
+            repeated Opt nested = 2;
+            optional string s = 3;
+            extensions 100 to 200;
+        }
+
+        extend Opt {
+            optional int32 ext = 100;
+        }
+
+        extend google.protobuf.FieldOptions {
+            optional Opt opt = 1001;
+        }
+
+        message Foo {
+            optional int32 foo = 1 [(opt) = {
+                a: -1,
+                s: 'x{'
+                nested: [{ a: 2 }, { [ext]: 3 }]
+                [ext]: 4
+            }];
+        }
+    ",
+    )])
+    .unwrap();
+}